@@ -0,0 +1,164 @@
+// Close reasons for `moq_quic_get_close_reason` and
+// `moq_webtransport_get_close_reason`
+//
+// quinn reports why a connection ended as a ConnectionError. This flattens it
+// into who closed the connection, at which layer, and the code and reason
+// phrase that were sent. Closes without a phrase of their own (resets, idle
+// timeouts) get a short description instead, and TLS alerts get the alert
+// described ahead of the peer's phrase, since a rejected client certificate
+// only surfaces once the server closes the connection.
+
+use quinn::ConnectionError;
+
+/// We closed the connection (including idle timeouts and local protocol errors)
+pub const MOQ_CLOSE_SOURCE_LOCAL: u32 = 0;
+/// The peer closed the connection
+pub const MOQ_CLOSE_SOURCE_REMOTE: u32 = 1;
+
+/// Closed at the QUIC transport layer; `error_code` is a transport error code
+pub const MOQ_CLOSE_KIND_TRANSPORT: u32 = 0;
+/// Closed by the application; `error_code` is the application's code
+pub const MOQ_CLOSE_KIND_APPLICATION: u32 = 1;
+
+/// Why a connection or session was closed
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MoqCloseInfo {
+    /// One of the `MOQ_CLOSE_SOURCE_*` constants
+    pub source: u32,
+    /// One of the `MOQ_CLOSE_KIND_*` constants
+    pub kind: u32,
+    /// Transport or application error code
+    pub error_code: u64,
+    /// Full length of the reason, which may exceed the caller's buffer
+    ///
+    /// The reason is the phrase that was sent, except for a stateless reset,
+    /// an idle timeout or a local close, which are described instead, and for
+    /// a TLS alert, where it reads "TLS alert from peer: <alert> (<number>)"
+    /// (or "from local") followed by the phrase that was sent.
+    pub reason_len: u64,
+}
+
+/// Flatten a quinn close reason into its FFI form plus the reason phrase
+pub(crate) fn close_info(error: &ConnectionError) -> (MoqCloseInfo, Vec<u8>) {
+    let (source, kind, error_code, reason) = match error {
+        ConnectionError::ApplicationClosed(close) => (
+            MOQ_CLOSE_SOURCE_REMOTE,
+            MOQ_CLOSE_KIND_APPLICATION,
+            close.error_code.into_inner(),
+            close.reason.to_vec(),
+        ),
+        ConnectionError::ConnectionClosed(close) => (
+            MOQ_CLOSE_SOURCE_REMOTE,
+            MOQ_CLOSE_KIND_TRANSPORT,
+            u64::from(close.error_code),
+            close.reason.to_vec(),
+        ),
+        ConnectionError::TransportError(e) => (
+            MOQ_CLOSE_SOURCE_LOCAL,
+            MOQ_CLOSE_KIND_TRANSPORT,
+            u64::from(e.code),
+            e.reason.clone().into_bytes(),
+        ),
+        ConnectionError::Reset => (
+            MOQ_CLOSE_SOURCE_REMOTE,
+            MOQ_CLOSE_KIND_TRANSPORT,
+            0,
+            b"stateless reset".to_vec(),
+        ),
+        ConnectionError::TimedOut => (
+            MOQ_CLOSE_SOURCE_LOCAL,
+            MOQ_CLOSE_KIND_TRANSPORT,
+            0,
+            b"idle timeout".to_vec(),
+        ),
+        ConnectionError::LocallyClosed => (
+            MOQ_CLOSE_SOURCE_LOCAL,
+            MOQ_CLOSE_KIND_APPLICATION,
+            0,
+            b"closed locally".to_vec(),
+        ),
+        other => (
+            MOQ_CLOSE_SOURCE_LOCAL,
+            MOQ_CLOSE_KIND_TRANSPORT,
+            0,
+            other.to_string().into_bytes(),
+        ),
+    };
+
+    let reason = match crate::tls::connection_alert_reason(error) {
+        Some(alert) => alert.into_bytes(),
+        None => reason,
+    };
+
+    let info = MoqCloseInfo { source, kind, error_code, reason_len: reason.len() as u64 };
+    (info, reason)
+}
+
+/// Describe a close we initiated with the given application code and reason
+pub(crate) fn local_close_info(error_code: u64, reason: &[u8]) -> (MoqCloseInfo, Vec<u8>) {
+    let info = MoqCloseInfo {
+        source: MOQ_CLOSE_SOURCE_LOCAL,
+        kind: MOQ_CLOSE_KIND_APPLICATION,
+        error_code,
+        reason_len: reason.len() as u64,
+    };
+    (info, reason.to_vec())
+}
+
+/// Write a close reason to FFI output parameters
+///
+/// # Returns
+/// * Always 1, the "closed" result of the `get_close_reason` calls
+pub(crate) fn write_close_info(
+    info: MoqCloseInfo,
+    reason: &[u8],
+    out_info: *mut MoqCloseInfo,
+    reason_buf: *mut u8,
+    reason_buf_len: usize,
+) -> i32 {
+    unsafe {
+        *out_info = info;
+        if !reason_buf.is_null() {
+            let to_copy = reason.len().min(reason_buf_len);
+            std::ptr::copy_nonoverlapping(reason.as_ptr(), reason_buf, to_copy);
+        }
+    }
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use quinn::{ApplicationClose, ConnectionClose, TransportErrorCode, VarInt};
+
+    #[test]
+    fn tls_alert_is_described_in_the_reason() {
+        // bad_certificate (42), as sent by a server rejecting the client certificate
+        let error = ConnectionError::ConnectionClosed(ConnectionClose {
+            error_code: TransportErrorCode::crypto(42),
+            frame_type: None,
+            reason: Bytes::from_static(b"invalid peer certificate"),
+        });
+        let (info, reason) = close_info(&error);
+        let reason = String::from_utf8(reason).unwrap();
+        assert_eq!(info.source, MOQ_CLOSE_SOURCE_REMOTE);
+        assert_eq!(info.error_code, 0x100 + 42);
+        assert_eq!(info.reason_len, reason.len() as u64);
+        assert!(reason.starts_with("TLS alert from peer: BadCertificate (42)"), "{}", reason);
+        assert!(reason.ends_with("invalid peer certificate"), "{}", reason);
+    }
+
+    #[test]
+    fn application_close_keeps_its_reason() {
+        let error = ConnectionError::ApplicationClosed(ApplicationClose {
+            error_code: VarInt::from_u32(0x100 + 42),
+            reason: Bytes::from_static(b"bye"),
+        });
+        let (info, reason) = close_info(&error);
+        assert_eq!(info.kind, MOQ_CLOSE_KIND_APPLICATION);
+        assert_eq!(info.error_code, 0x100 + 42);
+        assert_eq!(reason, b"bye");
+    }
+}
@@ -0,0 +1,233 @@
+// WebTransport CONNECT request passed to `moq_webtransport_connect_url` and
+// `moq_webtransport_connect_start`
+//
+// Extra header fields such as `authorization` are passed as an array of
+// name/value byte strings. Names are lowercased as HTTP/3 requires (RFC 9114
+// section 4.2). Fields the CONNECT request sets itself, and connection-specific
+// fields that HTTP/3 forbids, are rejected instead of being silently replaced.
+//
+// The subprotocols to offer are passed as a separate array and sent in
+// `wt-available-protocols`, a structured field list of strings (RFC 8941).
+
+use std::ffi::c_char;
+use std::slice;
+
+/// Where to connect and what to send in the CONNECT request
+///
+/// Strings are null-terminated. Only `url` is required; leave the other
+/// pointers null (and the counts 0) to keep the defaults.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MoqConnectRequest {
+    /// Session URL (`https://host[:port]/path[?query]`; the port defaults to
+    /// 443), sent as is. IPv6 literal hosts must be bracketed.
+    pub url: *const c_char,
+    /// TLS server name to send and verify the certificate against (null or
+    /// empty to use the URL host)
+    pub sni: *const c_char,
+    /// Where to connect instead of the URL host: a host name or IP address,
+    /// with an optional port (`192.0.2.1`, `[2001:db8::1]:4443`,
+    /// `edge.example.com:443`); null or empty to resolve the URL host
+    pub address: *const c_char,
+    /// Subprotocols to offer, most preferred first (may be null if
+    /// `protocol_count` is 0, which offers none). Names must be non-empty,
+    /// printable ASCII and given once.
+    pub protocols: *const MoqProtocol,
+    /// Number of entries in `protocols`
+    pub protocol_count: usize,
+    /// Extra header fields (may be null if `header_count` is 0). Invalid
+    /// names or values, pseudo-headers, fields the CONNECT request sets
+    /// itself (such as `wt-available-protocols`), connection-specific fields
+    /// and names given twice are rejected.
+    pub headers: *const MoqHttpHeader,
+    /// Number of entries in `headers`
+    pub header_count: usize,
+}
+
+/// One header field passed over FFI; neither string needs a terminating NUL
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MoqHttpHeader {
+    pub name: *const u8,
+    pub name_len: usize,
+    pub value: *const u8,
+    pub value_len: usize,
+}
+
+/// One subprotocol name passed over FFI, without a terminating NUL
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MoqProtocol {
+    pub name: *const u8,
+    pub name_len: usize,
+}
+
+// Set by the CONNECT request itself, or not allowed in HTTP/3
+const RESERVED_NAMES: &[&str] = &[
+    "wt-available-protocols",
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Read and validate the header array passed to a connect call
+///
+/// # Returns
+/// * The fields as lowercase name and trimmed value pairs, in the order given
+/// * An error message for a null string, an invalid or reserved name, a value
+///   containing CR, LF or NUL, or a name given twice
+pub(crate) fn parse(headers: *const MoqHttpHeader, count: usize) -> Result<Vec<(String, String)>, String> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    if headers.is_null() {
+        return Err("Header array is null".to_string());
+    }
+
+    let mut parsed: Vec<(String, String)> = Vec::with_capacity(count);
+    for header in unsafe { slice::from_raw_parts(headers, count) } {
+        let name = bytes(header.name, header.name_len).ok_or("Header name is null")?;
+        let value = bytes(header.value, header.value_len).ok_or("Header value is null")?;
+        let (name, value) = field(name, value)?;
+        if parsed.iter().any(|(existing, _)| *existing == name) {
+            return Err(format!("Header {} is given more than once", name));
+        }
+        parsed.push((name, value));
+    }
+    Ok(parsed)
+}
+
+/// Read and validate the subprotocol array passed to a connect call
+///
+/// # Returns
+/// * The names in the order given, most preferred first
+/// * An error message for a null or empty name, a name with characters a
+///   structured field string cannot hold (anything but printable ASCII), or a
+///   name given twice
+pub(crate) fn parse_protocols(protocols: *const MoqProtocol, count: usize) -> Result<Vec<String>, String> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    if protocols.is_null() {
+        return Err("Protocol array is null".to_string());
+    }
+
+    let mut parsed: Vec<String> = Vec::with_capacity(count);
+    for protocol in unsafe { slice::from_raw_parts(protocols, count) } {
+        let name = bytes(protocol.name, protocol.name_len).ok_or("Protocol name is null")?;
+        if name.is_empty() || !name.iter().all(|c| (b' '..=b'~').contains(c)) {
+            return Err(format!("Invalid protocol name {:?}", String::from_utf8_lossy(name)));
+        }
+        let name = String::from_utf8_lossy(name).into_owned();
+        if parsed.contains(&name) {
+            return Err(format!("Protocol {} is offered more than once", name));
+        }
+        parsed.push(name);
+    }
+    Ok(parsed)
+}
+
+/// View an FFI byte string, which may only be null when empty
+fn bytes<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (ptr.is_null(), len) {
+        (_, 0) => Some(&[]),
+        (true, _) => None,
+        (false, len) => Some(unsafe { slice::from_raw_parts(ptr, len) }),
+    }
+}
+
+/// Validate one field, returning its lowercase name and trimmed value
+fn field(name: &[u8], value: &[u8]) -> Result<(String, String), String> {
+    let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+    if name.starts_with(':') {
+        return Err(format!("Pseudo-header {} cannot be set", name));
+    }
+    if name.is_empty() || !name.bytes().all(is_token_char) {
+        return Err(format!("Invalid header name {:?}", name));
+    }
+    if RESERVED_NAMES.contains(&name.as_str()) {
+        return Err(format!("Header {} cannot be set", name));
+    }
+
+    let value = std::str::from_utf8(value).map_err(|_| format!("Header {} value is not valid UTF-8", name))?;
+    if value.contains(['\r', '\n', '\0']) {
+        return Err(format!("Header {} value contains CR, LF or NUL", name));
+    }
+    Ok((name, value.trim_matches([' ', '\t']).to_string()))
+}
+
+/// Characters allowed in a field name (RFC 9110 section 5.6.2)
+fn is_token_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(name: &str, value: &str) -> MoqHttpHeader {
+        MoqHttpHeader { name: name.as_ptr(), name_len: name.len(), value: value.as_ptr(), value_len: value.len() }
+    }
+
+    fn parse_all(headers: &[MoqHttpHeader]) -> Result<Vec<(String, String)>, String> {
+        parse(headers.as_ptr(), headers.len())
+    }
+
+    #[test]
+    fn names_are_lowercased_and_values_trimmed() {
+        let parsed = parse_all(&[header("Authorization", " Bearer abc\t"), header("X-Client-Version", "1.2.3")]).unwrap();
+        assert_eq!(
+            parsed,
+            [
+                ("authorization".to_string(), "Bearer abc".to_string()),
+                ("x-client-version".to_string(), "1.2.3".to_string()),
+            ]
+        );
+        assert_eq!(parse(std::ptr::null(), 0).unwrap(), []);
+    }
+
+    #[test]
+    fn invalid_fields_are_rejected() {
+        for invalid in [
+            header(":path", "/other"),
+            header("wt-available-protocols", "\"moqt-15\""),
+            header("Connection", "close"),
+            header("", "empty"),
+            header("x client", "space"),
+            header("x-split", "a\r\nx-injected: b"),
+        ] {
+            assert!(parse_all(&[invalid]).is_err(), "{:?}", invalid);
+        }
+        assert!(parse_all(&[header("x-id", "1"), header("X-Id", "2")]).is_err());
+        assert!(parse(std::ptr::null(), 1).is_err());
+        let null_value = MoqHttpHeader { value: std::ptr::null(), value_len: 1, ..header("x-id", "") };
+        assert!(parse_all(&[null_value]).is_err());
+    }
+
+    fn protocol(name: &str) -> MoqProtocol {
+        MoqProtocol { name: name.as_ptr(), name_len: name.len() }
+    }
+
+    #[test]
+    fn protocols_keep_their_order_and_commas() {
+        let offered = [protocol("moqt-16"), protocol("moq,lite"), protocol("moqt-15")];
+        let parsed = parse_protocols(offered.as_ptr(), offered.len()).unwrap();
+        assert_eq!(parsed, ["moqt-16", "moq,lite", "moqt-15"]);
+        assert_eq!(parse_protocols(std::ptr::null(), 0).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn invalid_protocols_are_rejected() {
+        for invalid in [
+            [protocol(""), protocol("moqt-15")],
+            [protocol("moqt-15"), protocol("moqt-15")],
+            [protocol("moqt-16"), protocol("mo\u{e9}qt")],
+        ] {
+            assert!(parse_protocols(invalid.as_ptr(), invalid.len()).is_err());
+        }
+        assert!(parse_protocols(std::ptr::null(), 1).is_err());
+    }
+}
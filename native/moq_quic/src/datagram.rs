@@ -0,0 +1,200 @@
+// Datagram send and receive support shared by the QUIC and WebTransport modules
+//
+// quinn queues outgoing datagrams in a send buffer until congestion control
+// lets them out. When the buffer is full, `send_datagram` silently discards
+// the oldest queued datagrams to make room, while `send_datagram_wait` waits
+// for space instead. Both fail outright for the reasons mapped below.
+//
+// Incoming datagrams wait in a bounded queue for the `recv_datagram` calls,
+// each stamped with its arrival time on a monotonic clock.
+
+use bytes::Bytes;
+use once_cell::sync::Lazy;
+use quinn::SendDatagramError;
+use std::collections::VecDeque;
+use std::slice;
+use std::time::Instant;
+
+// Datagram send buffer used when the connect options leave it unset
+pub(crate) const DEFAULT_SEND_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Map a send failure to the FFI error code
+///
+/// # Returns
+/// * -2 if the peer does not accept datagrams
+/// * -3 if the datagram is larger than the current maximum size
+/// * -4 if datagrams are disabled locally
+/// * -5 if the connection was lost
+pub(crate) fn send_error_code(error: &SendDatagramError) -> i64 {
+    match error {
+        SendDatagramError::UnsupportedByPeer => -2,
+        SendDatagramError::TooLarge => -3,
+        SendDatagramError::Disabled => -4,
+        SendDatagramError::ConnectionLost(_) => -5,
+    }
+}
+
+/// Size of the datagram send buffer configured by the connect options
+pub(crate) fn send_buffer_size(datagram_send_buffer_size: u64) -> usize {
+    match datagram_send_buffer_size {
+        0 => DEFAULT_SEND_BUFFER_SIZE,
+        size => size as usize,
+    }
+}
+
+// Origin of the monotonic clock behind receive timestamps
+static CLOCK_ORIGIN: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// Microseconds on the monotonic clock used for datagram receive timestamps
+pub(crate) fn monotonic_us() -> u64 {
+    CLOCK_ORIGIN.elapsed().as_micros() as u64
+}
+
+/// Current time on the clock used for datagram receive timestamps
+///
+/// The clock starts near process start and is unaffected by wall clock
+/// changes, so it is only meaningful relative to other readings.
+///
+/// # Returns
+/// * Microseconds since the clock's origin
+#[no_mangle]
+pub extern "C" fn moq_monotonic_time_us() -> u64 {
+    monotonic_us()
+}
+
+/// Received datagrams waiting to be read, with their receive timestamps
+pub(crate) struct DatagramQueue {
+    datagrams: VecDeque<(Bytes, u64)>,
+    capacity: usize,
+    drop_oldest: bool,
+}
+
+impl DatagramQueue {
+    pub(crate) fn new(capacity: usize, drop_oldest: bool) -> Self {
+        Self {
+            datagrams: VecDeque::new(),
+            capacity,
+            drop_oldest,
+        }
+    }
+
+    /// Queue a datagram received now, making room according to the drop policy
+    ///
+    /// Returns false if a datagram (this one or the oldest) was dropped.
+    pub(crate) fn push(&mut self, datagram: Bytes) -> bool {
+        let mut kept_all = true;
+        if self.datagrams.len() >= self.capacity {
+            if !self.drop_oldest {
+                return false;
+            }
+            self.datagrams.pop_front();
+            kept_all = false;
+        }
+        self.datagrams.push_back((datagram, monotonic_us()));
+        kept_all
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.datagrams.len()
+    }
+
+    /// Copy the oldest datagram to an FFI buffer, truncating it if needed
+    ///
+    /// # Returns
+    /// * Bytes copied, 0 if the queue is empty
+    pub(crate) fn pop_into(&mut self, buffer: *mut u8, buffer_len: usize, out_timestamp_us: *mut u64) -> i64 {
+        let Some((datagram, received_us)) = self.datagrams.pop_front() else {
+            return 0;
+        };
+        let copy_len = datagram.len().min(buffer_len);
+        unsafe {
+            let output = slice::from_raw_parts_mut(buffer, copy_len);
+            output.copy_from_slice(&datagram[..copy_len]);
+            if !out_timestamp_us.is_null() {
+                *out_timestamp_us = received_us;
+            }
+        }
+        if datagram.len() > buffer_len {
+            log::warn!("Datagram truncated: {} bytes available, {} bytes buffer", datagram.len(), buffer_len);
+        }
+        copy_len as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_errors_map_to_distinct_codes() {
+        assert_eq!(send_error_code(&SendDatagramError::UnsupportedByPeer), -2);
+        assert_eq!(send_error_code(&SendDatagramError::TooLarge), -3);
+        assert_eq!(send_error_code(&SendDatagramError::Disabled), -4);
+        assert_eq!(send_error_code(&SendDatagramError::ConnectionLost(quinn::ConnectionError::TimedOut)), -5);
+    }
+
+    #[test]
+    fn unset_send_buffer_uses_the_default() {
+        assert_eq!(send_buffer_size(0), DEFAULT_SEND_BUFFER_SIZE);
+        assert_eq!(send_buffer_size(4096), 4096);
+    }
+
+    /// Pop every queued datagram as (first byte, timestamp)
+    fn drain(queue: &mut DatagramQueue) -> Vec<(u8, u64)> {
+        let mut drained = Vec::new();
+        let mut buffer = [0u8; 16];
+        let mut timestamp = 0;
+        while queue.pop_into(buffer.as_mut_ptr(), buffer.len(), &mut timestamp) > 0 {
+            drained.push((buffer[0], timestamp));
+        }
+        drained
+    }
+
+    #[test]
+    fn full_queue_drops_the_oldest() {
+        let mut queue = DatagramQueue::new(2, true);
+        assert!(queue.push(Bytes::from_static(b"a")));
+        assert!(queue.push(Bytes::from_static(b"b")));
+        assert!(!queue.push(Bytes::from_static(b"c")));
+        assert_eq!(queue.len(), 2);
+        let tags: Vec<u8> = drain(&mut queue).into_iter().map(|(tag, _)| tag).collect();
+        assert_eq!(tags, b"bc");
+    }
+
+    #[test]
+    fn full_queue_drops_the_newest() {
+        let mut queue = DatagramQueue::new(2, false);
+        assert!(queue.push(Bytes::from_static(b"a")));
+        assert!(queue.push(Bytes::from_static(b"b")));
+        assert!(!queue.push(Bytes::from_static(b"c")));
+        assert_eq!(queue.len(), 2);
+        let tags: Vec<u8> = drain(&mut queue).into_iter().map(|(tag, _)| tag).collect();
+        assert_eq!(tags, b"ab");
+    }
+
+    #[test]
+    fn timestamps_record_arrival_order() {
+        let mut queue = DatagramQueue::new(4, true);
+        let before = monotonic_us();
+        queue.push(Bytes::from_static(b"a"));
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        queue.push(Bytes::from_static(b"b"));
+        let after = monotonic_us();
+
+        let drained = drain(&mut queue);
+        assert_eq!(drained.len(), 2);
+        let (first, second) = (drained[0].1, drained[1].1);
+        assert!(before <= first && first < second && second <= after);
+        assert!(second - first >= 2000);
+    }
+
+    #[test]
+    fn oversized_datagram_is_truncated_and_empty_queue_reads_zero() {
+        let mut queue = DatagramQueue::new(1, true);
+        queue.push(Bytes::from_static(b"abcdef"));
+        let mut buffer = [0u8; 4];
+        assert_eq!(queue.pop_into(buffer.as_mut_ptr(), buffer.len(), std::ptr::null_mut()), 4);
+        assert_eq!(&buffer, b"abcd");
+        assert_eq!(queue.pop_into(buffer.as_mut_ptr(), buffer.len(), std::ptr::null_mut()), 0);
+    }
+}
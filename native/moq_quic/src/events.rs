@@ -0,0 +1,252 @@
+// Per-connection event queues for `moq_quic_poll_event` and
+// `moq_webtransport_poll_event`
+//
+// Background tasks record lifecycle changes here as they happen, so Dart can
+// react to them instead of inferring state from failing calls.
+
+use crate::close::MoqCloseInfo;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// The bidirectional control stream is open and `send` can be used
+pub const MOQ_EVENT_CONTROL_STREAM_READY: u32 = 1;
+/// The peer finished its side of the control stream
+pub const MOQ_EVENT_CONTROL_STREAM_FINISHED: u32 = 2;
+/// The peer reset the control stream; `error_code` holds the reset code
+pub const MOQ_EVENT_CONTROL_STREAM_RESET: u32 = 3;
+/// A new incoming data stream was accepted; `stream_id` identifies it
+pub const MOQ_EVENT_DATA_STREAM_OPENED: u32 = 4;
+/// The peer finished a data stream
+pub const MOQ_EVENT_DATA_STREAM_FINISHED: u32 = 5;
+/// The peer reset a data stream; `error_code` holds the reset code
+pub const MOQ_EVENT_DATA_STREAM_RESET: u32 = 6;
+/// Datagrams are waiting to be received (raised when the queue stops being empty)
+pub const MOQ_EVENT_DATAGRAM_AVAILABLE: u32 = 7;
+/// A receive buffer was full and data was discarded; see `buffer` and `dropped`
+pub const MOQ_EVENT_BUFFER_OVERFLOW: u32 = 8;
+/// The connection closed; `close` describes why and the reason phrase is returned separately
+pub const MOQ_EVENT_CONNECTION_CLOSED: u32 = 9;
+/// The peer sent STOP_SENDING on one of our outgoing streams; `stream_id` is
+/// the ID from the open call and `error_code` holds the peer's code
+pub const MOQ_EVENT_STREAM_STOPPED: u32 = 10;
+/// The peer opened a bidirectional stream; claim it with the `accept_bidi_stream` call
+pub const MOQ_EVENT_BIDI_STREAM_OPENED: u32 = 11;
+/// The peer finished its side of a bidirectional stream
+pub const MOQ_EVENT_BIDI_STREAM_FINISHED: u32 = 12;
+/// The peer reset its side of a bidirectional stream; `error_code` holds the reset code
+pub const MOQ_EVENT_BIDI_STREAM_RESET: u32 = 13;
+
+/// Overflow in the control stream receive buffer
+pub const MOQ_BUFFER_CONTROL: u32 = 0;
+/// Overflow in a data stream receive buffer (`stream_id` identifies it)
+pub const MOQ_BUFFER_DATA_STREAM: u32 = 1;
+/// Overflow in the datagram receive queue
+pub const MOQ_BUFFER_DATAGRAM: u32 = 2;
+/// Overflow in the receive buffer of a bidirectional stream (`stream_id` identifies it)
+pub const MOQ_BUFFER_BIDI_STREAM: u32 = 3;
+
+// Oldest events are discarded beyond this many undrained events
+const MAX_QUEUED_EVENTS: usize = 4096;
+
+/// A connection or session event
+///
+/// Fields not relevant to `kind` are zero.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MoqEvent {
+    /// One of the `MOQ_EVENT_*` constants
+    pub kind: u32,
+    /// One of the `MOQ_BUFFER_*` constants, for `MOQ_EVENT_BUFFER_OVERFLOW`
+    pub buffer: u32,
+    /// Data stream the event refers to
+    pub stream_id: u64,
+    /// Reset code for the `*_RESET` events, STOP_SENDING code for `MOQ_EVENT_STREAM_STOPPED`
+    pub error_code: u64,
+    /// Bytes (or datagrams, for the datagram queue) discarded on overflow
+    pub dropped: u64,
+    /// Close details for `MOQ_EVENT_CONNECTION_CLOSED`
+    pub close: MoqCloseInfo,
+}
+
+/// Events waiting to be drained by Dart, with the close reason phrase if any
+#[derive(Default)]
+pub(crate) struct EventQueue {
+    events: Mutex<VecDeque<(MoqEvent, Vec<u8>)>>,
+    // How the connection closed, kept after the event is drained
+    closed: Mutex<Option<(MoqCloseInfo, Vec<u8>)>>,
+}
+
+impl EventQueue {
+    pub(crate) fn push(&self, kind: u32) {
+        self.push_event(MoqEvent { kind, ..Default::default() }, Vec::new());
+    }
+
+    pub(crate) fn push_stream(&self, kind: u32, stream_id: u64, error_code: u64) {
+        self.push_event(MoqEvent { kind, stream_id, error_code, ..Default::default() }, Vec::new());
+    }
+
+    /// Record discarded data, merging with an overflow event for the same buffer still at the back
+    pub(crate) fn push_overflow(&self, buffer: u32, stream_id: u64, dropped: u64) {
+        if self.closed.lock().unwrap().is_some() {
+            return;
+        }
+        let mut events = self.events.lock().unwrap();
+        if let Some((last, _)) = events.back_mut() {
+            if last.kind == MOQ_EVENT_BUFFER_OVERFLOW && last.buffer == buffer && last.stream_id == stream_id {
+                last.dropped += dropped;
+                return;
+            }
+        }
+        let event = MoqEvent { kind: MOQ_EVENT_BUFFER_OVERFLOW, buffer, stream_id, dropped, ..Default::default() };
+        Self::enqueue(&mut events, event, Vec::new());
+    }
+
+    /// Record the close, unless one was already recorded
+    ///
+    /// A local close is recorded with the code and reason sent before quinn
+    /// reports the connection as locally closed, so the first record wins.
+    /// The close is the last event: stream tasks that wind down afterwards
+    /// report nothing more.
+    pub(crate) fn push_closed(&self, close: MoqCloseInfo, reason: Vec<u8>) {
+        let mut closed = self.closed.lock().unwrap();
+        if closed.is_some() {
+            return;
+        }
+        *closed = Some((close, reason.clone()));
+        let event = MoqEvent { kind: MOQ_EVENT_CONNECTION_CLOSED, close, ..Default::default() };
+        Self::enqueue(&mut self.events.lock().unwrap(), event, reason);
+    }
+
+    /// How the connection closed, if it has
+    pub(crate) fn close_info(&self) -> Option<(MoqCloseInfo, Vec<u8>)> {
+        self.closed.lock().unwrap().clone()
+    }
+
+    /// Whether the connection closed and every event has been drained
+    pub(crate) fn drained_after_close(&self) -> bool {
+        self.closed.lock().unwrap().is_some() && self.events.lock().unwrap().is_empty()
+    }
+
+    fn push_event(&self, event: MoqEvent, reason: Vec<u8>) {
+        if self.closed.lock().unwrap().is_some() {
+            return;
+        }
+        Self::enqueue(&mut self.events.lock().unwrap(), event, reason);
+    }
+
+    fn enqueue(events: &mut VecDeque<(MoqEvent, Vec<u8>)>, event: MoqEvent, reason: Vec<u8>) {
+        if events.len() >= MAX_QUEUED_EVENTS {
+            log::warn!("Event queue full, discarding oldest event");
+            events.pop_front();
+        }
+        events.push_back((event, reason));
+    }
+
+    /// Pop the next event into FFI output parameters
+    ///
+    /// # Returns
+    /// * 1 if an event was written, 0 if the queue is empty
+    pub(crate) fn pop_into(&self, out_event: *mut MoqEvent, reason_buf: *mut u8, reason_buf_len: usize) -> i32 {
+        let (event, reason) = match self.events.lock().unwrap().pop_front() {
+            Some(entry) => entry,
+            None => return 0,
+        };
+        unsafe {
+            *out_event = event;
+            if !reason_buf.is_null() {
+                let to_copy = reason.len().min(reason_buf_len);
+                std::ptr::copy_nonoverlapping(reason.as_ptr(), reason_buf, to_copy);
+            }
+        }
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(queue: &EventQueue) -> Vec<MoqEvent> {
+        let mut events = Vec::new();
+        let mut event = MoqEvent::default();
+        while queue.pop_into(&mut event, std::ptr::null_mut(), 0) == 1 {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn overflows_of_one_buffer_merge_while_at_the_back() {
+        let queue = EventQueue::default();
+        queue.push_overflow(MOQ_BUFFER_DATA_STREAM, 4, 100);
+        queue.push_overflow(MOQ_BUFFER_DATA_STREAM, 4, 50);
+        queue.push_overflow(MOQ_BUFFER_DATA_STREAM, 8, 10);
+        queue.push_overflow(MOQ_BUFFER_DATAGRAM, 8, 1);
+        queue.push_stream(MOQ_EVENT_DATA_STREAM_FINISHED, 4, 0);
+        queue.push_overflow(MOQ_BUFFER_DATA_STREAM, 4, 7);
+
+        let events = drain(&queue);
+        let overflows: Vec<_> = events.iter().map(|e| (e.kind, e.buffer, e.stream_id, e.dropped)).collect();
+        assert_eq!(
+            overflows,
+            [
+                (MOQ_EVENT_BUFFER_OVERFLOW, MOQ_BUFFER_DATA_STREAM, 4, 150),
+                (MOQ_EVENT_BUFFER_OVERFLOW, MOQ_BUFFER_DATA_STREAM, 8, 10),
+                (MOQ_EVENT_BUFFER_OVERFLOW, MOQ_BUFFER_DATAGRAM, 8, 1),
+                (MOQ_EVENT_DATA_STREAM_FINISHED, 0, 4, 0),
+                (MOQ_EVENT_BUFFER_OVERFLOW, MOQ_BUFFER_DATA_STREAM, 4, 7),
+            ]
+        );
+
+        // A drained overflow is not merged into
+        queue.push_overflow(MOQ_BUFFER_DATA_STREAM, 4, 3);
+        assert_eq!(drain(&queue)[0].dropped, 3);
+    }
+
+    #[test]
+    fn oldest_events_are_discarded_when_full() {
+        let queue = EventQueue::default();
+        for stream_id in 0..MAX_QUEUED_EVENTS as u64 + 2 {
+            queue.push_stream(MOQ_EVENT_DATA_STREAM_OPENED, stream_id, 0);
+        }
+        let events = drain(&queue);
+        assert_eq!(events.len(), MAX_QUEUED_EVENTS);
+        assert_eq!(events[0].stream_id, 2);
+    }
+
+    #[test]
+    fn first_close_wins_and_outlives_draining() {
+        let queue = EventQueue::default();
+        let local = MoqCloseInfo { error_code: 7, reason_len: 4, ..Default::default() };
+        queue.push_closed(local, b"done".to_vec());
+        queue.push_closed(MoqCloseInfo { error_code: 9, ..Default::default() }, Vec::new());
+        assert!(!queue.drained_after_close());
+
+        let mut event = MoqEvent::default();
+        let mut reason = [0u8; 2];
+        assert_eq!(queue.pop_into(&mut event, reason.as_mut_ptr(), reason.len()), 1);
+        assert_eq!(event.kind, MOQ_EVENT_CONNECTION_CLOSED);
+        assert_eq!(event.close.error_code, 7);
+        assert_eq!(event.close.reason_len, 4);
+        assert_eq!(&reason, b"do");
+        assert_eq!(queue.pop_into(&mut event, reason.as_mut_ptr(), reason.len()), 0);
+
+        assert!(queue.drained_after_close());
+        let (close, reason) = queue.close_info().unwrap();
+        assert_eq!(close.error_code, 7);
+        assert_eq!(reason, b"done");
+    }
+
+    #[test]
+    fn nothing_is_queued_after_the_close() {
+        let queue = EventQueue::default();
+        queue.push_stream(MOQ_EVENT_DATA_STREAM_OPENED, 4, 0);
+        queue.push_closed(MoqCloseInfo::default(), Vec::new());
+        queue.push_stream(MOQ_EVENT_DATA_STREAM_RESET, 4, 1);
+        queue.push_overflow(MOQ_BUFFER_DATAGRAM, 0, 1);
+
+        let kinds: Vec<_> = drain(&queue).iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, [MOQ_EVENT_DATA_STREAM_OPENED, MOQ_EVENT_CONNECTION_CLOSED]);
+        assert!(queue.drained_after_close());
+    }
+}
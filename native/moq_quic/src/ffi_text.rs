@@ -0,0 +1,34 @@
+// Text results copied into caller-supplied FFI buffers
+//
+// Used for the connect attempt reports, addresses and CONNECT response
+// fields. The text is not NUL-terminated; the return value is its length.
+
+/// Copy text into a caller-supplied FFI buffer, truncating to fit
+///
+/// # Returns
+/// * Number of bytes written
+pub(crate) fn copy_text(text: &str, buffer: *mut u8, buffer_len: usize) -> i32 {
+    if buffer.is_null() || buffer_len == 0 {
+        return 0;
+    }
+    let to_copy = text.len().min(buffer_len);
+    unsafe {
+        std::ptr::copy_nonoverlapping(text.as_ptr(), buffer, to_copy);
+    }
+    to_copy as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_truncated_to_the_buffer() {
+        let mut buffer = [0u8; 4];
+        assert_eq!(copy_text("moqt-15", buffer.as_mut_ptr(), buffer.len()), 4);
+        assert_eq!(&buffer, b"moqt");
+        assert_eq!(copy_text("ok", buffer.as_mut_ptr(), buffer.len()), 2);
+        assert_eq!(&buffer[..2], b"ok");
+        assert_eq!(copy_text("ok", std::ptr::null_mut(), 4), 0);
+    }
+}
@@ -0,0 +1,204 @@
+// Happy Eyeballs (RFC 8305) connection racing for QUIC endpoints
+//
+// All resolved addresses are tried from a single dual-stack UDP socket.
+// Addresses are interleaved by family starting with IPv6, and a new attempt
+// starts every CONNECTION_ATTEMPT_DELAY or as soon as the previous one fails.
+// The first handshake to complete wins; the others are dropped.
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use quinn::{ClientConfig, Connection, Endpoint};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+// RFC 8305 section 5 recommends 250ms between connection attempts
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Outcome of one connection attempt
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum AttemptOutcome {
+    /// Handshake still running
+    Pending,
+    Failed(String),
+    Won,
+    /// Still running when another attempt won
+    Abandoned,
+}
+
+/// One connection attempt, kept for diagnostics
+#[derive(Clone, Debug)]
+pub(crate) struct ConnectAttempt {
+    pub addr: SocketAddr,
+    pub outcome: AttemptOutcome,
+}
+
+/// Bind a UDP socket able to reach both IPv4 and IPv6 peers
+///
+/// Falls back to an IPv4-only socket when IPv6 is unavailable on the host.
+pub(crate) fn bind_dual_stack_socket() -> std::io::Result<UdpSocket> {
+    let dual_stack = || -> std::io::Result<UdpSocket> {
+        let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_only_v6(false)?;
+        socket.bind(&SockAddr::from(SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))))?;
+        Ok(socket.into())
+    };
+
+    match dual_stack() {
+        Ok(socket) => Ok(socket),
+        Err(e) => {
+            log::warn!("Dual-stack UDP bind failed ({}), falling back to IPv4 only", e);
+            UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)))
+        }
+    }
+}
+
+/// Order addresses per RFC 8305 section 4: interleave families, IPv6 first
+///
+/// Repeated addresses are tried once, at their first position. IPv6 addresses
+/// are skipped when the socket cannot reach them.
+pub(crate) fn sort_addresses(addrs: impl IntoIterator<Item = SocketAddr>, ipv6_capable: bool) -> Vec<SocketAddr> {
+    let mut seen = HashSet::new();
+    let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().filter(|a| seen.insert(*a)).partition(|a| a.is_ipv6());
+    let v6 = if ipv6_capable { v6 } else { Vec::new() };
+
+    let mut ordered = Vec::with_capacity(v6.len() + v4.len());
+    let mut v6 = v6.into_iter();
+    let mut v4 = v4.into_iter();
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => break,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+    ordered
+}
+
+/// Race QUIC handshakes to every address, returning the first to complete
+///
+/// # Returns
+/// * The winning connection and address, plus the outcome of every attempt started
+pub(crate) async fn race(
+    endpoint: &Endpoint,
+    config: ClientConfig,
+    addrs: &[SocketAddr],
+    server_name: &str,
+) -> (Option<(Connection, SocketAddr)>, Vec<ConnectAttempt>) {
+    let mut attempts: Vec<ConnectAttempt> = Vec::with_capacity(addrs.len());
+    let mut pending = FuturesUnordered::new();
+    let mut next = 0;
+    let delay = tokio::time::sleep(Duration::ZERO);
+    tokio::pin!(delay);
+
+    let winner = loop {
+        tokio::select! {
+            _ = &mut delay, if next < addrs.len() => {
+                let addr = addrs[next];
+                let index = attempts.len();
+                next += 1;
+                log::info!("Connection attempt {} to {}", index + 1, addr);
+                attempts.push(ConnectAttempt { addr, outcome: AttemptOutcome::Pending });
+
+                match endpoint.connect_with(config.clone(), addr, server_name) {
+                    Ok(connecting) => {
+                        pending.push(async move { (index, connecting.await) });
+                        delay.as_mut().reset(tokio::time::Instant::now() + CONNECTION_ATTEMPT_DELAY);
+                    }
+                    Err(e) => {
+                        log::warn!("Connect to {} failed: {}", addr, e);
+                        attempts[index].outcome = AttemptOutcome::Failed(e.to_string());
+                        delay.as_mut().reset(tokio::time::Instant::now());
+                    }
+                }
+            }
+            Some((index, result)) = pending.next(), if !pending.is_empty() => {
+                match result {
+                    Ok(connection) => {
+                        attempts[index].outcome = AttemptOutcome::Won;
+                        break Some((connection, attempts[index].addr));
+                    }
+                    Err(e) => {
+                        let err_msg = crate::tls::connection_alert_reason(&e).unwrap_or_else(|| e.to_string());
+                        log::warn!("Handshake with {} failed: {}", attempts[index].addr, err_msg);
+                        attempts[index].outcome = AttemptOutcome::Failed(err_msg);
+                        // Start the next attempt right away instead of waiting out the delay
+                        delay.as_mut().reset(tokio::time::Instant::now());
+                    }
+                }
+            }
+            else => break None,
+        }
+    };
+
+    // Attempts still in flight lost the race; dropping them closes their connections
+    for attempt in attempts.iter_mut() {
+        if attempt.outcome == AttemptOutcome::Pending {
+            attempt.outcome = AttemptOutcome::Abandoned;
+        }
+    }
+
+    (winner, attempts)
+}
+
+/// Render attempts as one `address: outcome` line each
+pub(crate) fn format_attempts(attempts: &[ConnectAttempt]) -> String {
+    attempts
+        .iter()
+        .map(|a| match &a.outcome {
+            AttemptOutcome::Pending => format!("{}: in progress", a.addr),
+            AttemptOutcome::Failed(e) => format!("{}: {}", a.addr, e),
+            AttemptOutcome::Won => format!("{}: connected", a.addr),
+            AttemptOutcome::Abandoned => format!("{}: abandoned", a.addr),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Undo the IPv4-mapped form a dual-stack socket reports for IPv4 peers
+pub(crate) fn canonical_address(addr: SocketAddr) -> SocketAddr {
+    SocketAddr::new(addr.ip().to_canonical(), addr.port())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(list: &[&str]) -> Vec<SocketAddr> {
+        list.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn families_are_interleaved_starting_with_ipv6() {
+        let resolved = addrs(&["192.0.2.1:443", "192.0.2.2:443", "192.0.2.3:443", "[2001:db8::1]:443", "[2001:db8::2]:443"]);
+        let expected = addrs(&["[2001:db8::1]:443", "192.0.2.1:443", "[2001:db8::2]:443", "192.0.2.2:443", "192.0.2.3:443"]);
+        assert_eq!(sort_addresses(resolved, true), expected);
+    }
+
+    #[test]
+    fn ipv6_is_skipped_on_an_ipv4_only_socket() {
+        let resolved = addrs(&["[2001:db8::1]:443", "192.0.2.1:443", "[2001:db8::2]:443", "192.0.2.2:443"]);
+        let expected = addrs(&["192.0.2.1:443", "192.0.2.2:443"]);
+        assert_eq!(sort_addresses(resolved, false), expected);
+        assert!(sort_addresses(addrs(&["[2001:db8::1]:443"]), false).is_empty());
+    }
+
+    #[test]
+    fn repeated_addresses_are_tried_once() {
+        let resolved = addrs(&["192.0.2.1:443", "[2001:db8::1]:443", "192.0.2.2:443", "192.0.2.1:443", "[2001:db8::1]:443"]);
+        let expected = addrs(&["[2001:db8::1]:443", "192.0.2.1:443", "192.0.2.2:443"]);
+        assert_eq!(sort_addresses(resolved, true), expected);
+    }
+
+    #[test]
+    fn attempts_are_reported_one_per_line() {
+        let attempts = [
+            ConnectAttempt { addr: "[2001:db8::1]:443".parse().unwrap(), outcome: AttemptOutcome::Failed("timed out".to_string()) },
+            ConnectAttempt { addr: "192.0.2.1:443".parse().unwrap(), outcome: AttemptOutcome::Won },
+            ConnectAttempt { addr: "192.0.2.2:443".parse().unwrap(), outcome: AttemptOutcome::Abandoned },
+        ];
+        assert_eq!(
+            format_attempts(&attempts),
+            "[2001:db8::1]:443: timed out\n192.0.2.1:443: connected\n192.0.2.2:443: abandoned"
+        );
+    }
+}
@@ -7,14 +7,27 @@
 // - Receive buffer for polling from Dart
 // - Background tasks for stream handling
 
+mod close;
+mod connect_request;
+mod datagram;
+mod events;
+mod ffi_text;
+mod happy_eyeballs;
+mod options;
+mod pending_connect;
+mod push;
+mod receive_buffer;
+mod stats;
+mod stream_handles;
+mod stream_state;
 mod stream_writer;
+mod tls;
 pub mod webtransport;
 #[cfg(feature = "media-player")]
 pub mod media_player;
 
 use quinn::{Endpoint, ClientConfig, Connection, SendStream, VarInt, TokioRuntime, EndpointConfig, TransportConfig};
 use quinn::crypto::rustls::QuicClientConfig;
-use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
 use rustls::crypto::CryptoProvider;
 use dashmap::DashMap;
 use once_cell::sync::OnceCell;
@@ -24,101 +37,75 @@ use std::time;
 use std::collections::VecDeque;
 use std::sync::Mutex;
 use tokio::runtime::Runtime;
+use tokio::sync::oneshot;
 use std::slice;
 use std::ffi::c_char;
-
-// Maximum receive buffer size per connection
-// Increased from 64KB to 2MB to handle video streaming without flow control backpressure
-const MAX_RECV_BUFFER_SIZE: usize = 2 * 1024 * 1024; // 2MB
-
-// No certificate verification for testing (DANGER: only use for development!)
-#[derive(Debug)]
-struct NoVerification;
-
-impl rustls::client::danger::ServerCertVerifier for NoVerification {
-    fn verify_server_cert(
-        &self,
-        _end_entity: &CertificateDer<'_>,
-        _intermediates: &[CertificateDer<'_>],
-        _server_name: &ServerName<'_>,
-        _ocsp_response: &[u8],
-        _now: UnixTime,
-    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
-        Ok(rustls::client::danger::ServerCertVerified::assertion())
-    }
-
-    fn verify_tls12_signature(
-        &self,
-        _message: &[u8],
-        _cert: &CertificateDer<'_>,
-        _dss: &rustls::DigitallySignedStruct,
-    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
-        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
-    }
-
-    fn verify_tls13_signature(
-        &self,
-        _message: &[u8],
-        _cert: &CertificateDer<'_>,
-        _dss: &rustls::DigitallySignedStruct,
-    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
-        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
-    }
-
-    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
-        vec![
-            rustls::SignatureScheme::RSA_PKCS1_SHA1,
-            rustls::SignatureScheme::ECDSA_SHA1_Legacy,
-            rustls::SignatureScheme::RSA_PKCS1_SHA256,
-            rustls::SignatureScheme::ECDSA_NISTP256_SHA256,
-            rustls::SignatureScheme::ED25519,
-            rustls::SignatureScheme::RSA_PSS_SHA256,
-        ]
-    }
-}
-
-// Receive buffer for incoming data
-struct ReceiveBuffer {
-    data: VecDeque<u8>,
-    max_size: usize,
-}
-
-impl ReceiveBuffer {
-    fn new(max_size: usize) -> Self {
-        // Pre-allocate buffer with larger initial capacity for video streaming
-        Self {
-            data: VecDeque::with_capacity(64 * 1024), // 64KB initial
-            max_size,
-        }
-    }
-
-    fn push(&mut self, bytes: &[u8]) -> usize {
-        let available = self.max_size - self.data.len();
-        let to_copy = bytes.len().min(available);
-        for &byte in &bytes[..to_copy] {
-            self.data.push_back(byte);
-        }
-        to_copy
-    }
-
-    fn pop(&mut self, buf: &mut [u8]) -> usize {
-        let to_read = buf.len().min(self.data.len());
-        for i in 0..to_read {
-            buf[i] = self.data.pop_front().unwrap();
-        }
-        to_read
-    }
-
-    fn is_empty(&self) -> bool {
-        self.data.is_empty()
-    }
-}
+use datagram::DatagramQueue;
+use events::EventQueue;
+use pending_connect::PendingConnect;
+use receive_buffer::ReceiveBuffer;
+use stream_writer::StreamDataCallback;
+use stats::StatsTracker;
+use stream_handles::StreamHandles;
+use stream_state::IncomingStream;
+
+pub use close::{
+    MoqCloseInfo, MOQ_CLOSE_KIND_APPLICATION, MOQ_CLOSE_KIND_TRANSPORT, MOQ_CLOSE_SOURCE_LOCAL,
+    MOQ_CLOSE_SOURCE_REMOTE,
+};
+pub use connect_request::{MoqConnectRequest, MoqHttpHeader, MoqProtocol};
+pub use datagram::moq_monotonic_time_us;
+pub use events::{
+    MoqEvent, MOQ_BUFFER_BIDI_STREAM, MOQ_BUFFER_CONTROL, MOQ_BUFFER_DATAGRAM, MOQ_BUFFER_DATA_STREAM,
+    MOQ_EVENT_BIDI_STREAM_FINISHED, MOQ_EVENT_BIDI_STREAM_OPENED, MOQ_EVENT_BIDI_STREAM_RESET,
+    MOQ_EVENT_BUFFER_OVERFLOW, MOQ_EVENT_CONNECTION_CLOSED, MOQ_EVENT_CONTROL_STREAM_FINISHED,
+    MOQ_EVENT_CONTROL_STREAM_READY, MOQ_EVENT_CONTROL_STREAM_RESET, MOQ_EVENT_DATAGRAM_AVAILABLE,
+    MOQ_EVENT_DATA_STREAM_FINISHED, MOQ_EVENT_DATA_STREAM_OPENED, MOQ_EVENT_DATA_STREAM_RESET,
+    MOQ_EVENT_STREAM_STOPPED,
+};
+pub use options::{
+    MoqConnectOptions, MOQ_CONGESTION_BBR, MOQ_CONGESTION_CUBIC, MOQ_CONGESTION_DEFAULT,
+    MOQ_CONGESTION_NEW_RENO, MOQ_DATAGRAM_DROP_NEWEST, MOQ_DATAGRAM_DROP_OLDEST, MOQ_OVERFLOW_DROP,
+    MOQ_OVERFLOW_STALL,
+};
+pub use pending_connect::{
+    MOQ_CONNECT_CANCELLED, MOQ_CONNECT_CONNECTED, MOQ_CONNECT_FAILED, MOQ_CONNECT_HANDSHAKING,
+    MOQ_CONNECT_RESOLVING,
+};
+pub use push::{
+    moq_buffer_free, MoqReceiveCallback, MOQ_RECEIVE_BIDI_STREAM, MOQ_RECEIVE_CONTROL,
+    MOQ_RECEIVE_DATAGRAM, MOQ_RECEIVE_DATA_STREAM,
+};
+pub use stats::MoqConnectionStats;
+pub use stream_handles::{
+    MoqStreamInfo, MOQ_STREAM_DIR_BIDI, MOQ_STREAM_DIR_UNI, MOQ_STREAM_INITIATOR_LOCAL,
+    MOQ_STREAM_INITIATOR_REMOTE,
+};
+pub use stream_state::{
+    MOQ_STREAM_STATE_FAILED, MOQ_STREAM_STATE_FINISHED, MOQ_STREAM_STATE_OPEN, MOQ_STREAM_STATE_RESET,
+    MOQ_STREAM_STATE_STOPPED,
+};
+pub use tls::{MOQ_CERT_HASH_LEN, MOQ_TRUST_CUSTOM_ONLY, MOQ_TRUST_SYSTEM_AND_CUSTOM};
+
+// Default cap on received datagrams waiting for recv_datagram
+const MAX_QUEUED_DATAGRAMS: usize = 1000;
 
 // Control stream storage - only send stream needed (recv is handled by background task)
 struct ControlStream {
     send: SendStream,
 }
 
+// Shared handles to the per-connection and per-stream buffers
+type SharedReceiveBuffer = Arc<tokio::sync::Mutex<ReceiveBuffer>>;
+type SharedDatagramBuffer = Arc<tokio::sync::Mutex<DatagramQueue>>;
+type SharedStreamState = Arc<IncomingStream<VarInt>>;
+
+/// Receive half of a bidirectional stream
+struct BidiRecvStream {
+    buffer: SharedReceiveBuffer,
+    state: SharedStreamState,
+}
+
 // Global Tokio runtime for async operations
 static RUNTIME: OnceCell<Runtime> = OnceCell::new();
 
@@ -132,37 +119,74 @@ static ENDPOINTS: OnceCell<DashMap<u64, Arc<Endpoint>>> = OnceCell::new();
 static CONTROL_STREAMS: OnceCell<DashMap<u64, Arc<tokio::sync::Mutex<Option<ControlStream>>>>> = OnceCell::new();
 
 // Global registry of receive buffers (connection_id -> receive buffer)
-static RECV_BUFFERS: OnceCell<DashMap<u64, Arc<tokio::sync::Mutex<ReceiveBuffer>>>> = OnceCell::new();
+static RECV_BUFFERS: OnceCell<DashMap<u64, SharedReceiveBuffer>> = OnceCell::new();
 
 // Global registry of stream writers (connection_id -> stream_id -> writer)
 static STREAM_WRITERS: OnceCell<DashMap<(u64, u64), Arc<stream_writer::StreamWriter>>> = OnceCell::new();
 
 // Global registry of incoming data stream buffers (connection_id, stream_id) -> buffer
 // Used for receiving data from unidirectional streams (SUBGROUP_HEADER + objects)
-static DATA_STREAM_BUFFERS: OnceCell<DashMap<(u64, u64), Arc<tokio::sync::Mutex<ReceiveBuffer>>>> = OnceCell::new();
+static DATA_STREAM_BUFFERS: OnceCell<DashMap<(u64, u64), SharedReceiveBuffer>> = OnceCell::new();
+
+// Global registry of incoming data stream states (connection_id, stream_id) -> state
+static DATA_STREAM_STATES: OnceCell<DashMap<(u64, u64), SharedStreamState>> = OnceCell::new();
 
 // Global registry of active data streams per connection (connection_id -> list of stream_ids)
 static ACTIVE_DATA_STREAMS: OnceCell<DashMap<u64, Arc<tokio::sync::Mutex<Vec<u64>>>>> = OnceCell::new();
 
+// Global registry of receive halves of bidirectional streams (connection_id, stream_id) -> stream
+static BIDI_RECV_STREAMS: OnceCell<DashMap<(u64, u64), BidiRecvStream>> = OnceCell::new();
+
+// Global registry of peer-opened bidi streams not yet accepted (connection_id -> stream_ids)
+static PENDING_BIDI_STREAMS: OnceCell<DashMap<u64, Arc<Mutex<VecDeque<u64>>>>> = OnceCell::new();
+
+// Global registry of stream handle allocators (connection_id -> handles)
+static STREAM_HANDLES: OnceCell<DashMap<u64, Arc<StreamHandles>>> = OnceCell::new();
+
+// Global registry of connect options (connection_id -> options), for streams opened later
+static CONNECTION_OPTIONS: OnceCell<DashMap<u64, MoqConnectOptions>> = OnceCell::new();
+
 // Global registry of datagram receive buffers (connection_id -> buffer of complete datagrams)
-static DATAGRAM_BUFFERS: OnceCell<DashMap<u64, Arc<tokio::sync::Mutex<VecDeque<Vec<u8>>>>>> = OnceCell::new();
+static DATAGRAM_BUFFERS: OnceCell<DashMap<u64, SharedDatagramBuffer>> = OnceCell::new();
+
+// Global registry of push delivery callbacks (connection_id -> callback)
+static RECEIVE_CALLBACKS: OnceCell<DashMap<u64, Arc<dyn StreamDataCallback>>> = OnceCell::new();
+
+// Global registry of lifecycle event queues (connection_id -> queue)
+static EVENT_QUEUES: OnceCell<DashMap<u64, Arc<EventQueue>>> = OnceCell::new();
+
+// Global registry of per-connection statistics (connection_id -> tracker)
+static CONNECTION_STATS: OnceCell<DashMap<u64, Arc<StatsTracker>>> = OnceCell::new();
+
+// Global registry of the addresses tried to establish each connection (connection_id -> report)
+static CONNECT_ATTEMPTS: OnceCell<DashMap<u64, String>> = OnceCell::new();
 
 // Next connection ID counter
 static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
 
+// Global registry of connects started with moq_quic_connect_start (handle -> progress)
+static PENDING_CONNECTS: OnceCell<DashMap<u64, Arc<PendingConnect>>> = OnceCell::new();
+static NEXT_PENDING_CONNECT_ID: AtomicU64 = AtomicU64::new(1);
+
 // Last error message
 static LAST_ERROR: OnceCell<Mutex<Vec<u8>>> = OnceCell::new();
 const MAX_ERROR_LEN: usize = 512;
 
 /// Set the last error message
-fn set_last_error(msg: &str) {
-    if let Some(error_buf) = LAST_ERROR.get() {
-        let mut buf = error_buf.lock().unwrap();
-        let msg_bytes = msg.as_bytes();
-        let len = msg_bytes.len().min(MAX_ERROR_LEN);
-        buf.clear();
-        buf.extend_from_slice(&msg_bytes[..len]);
-    }
+///
+/// Also used by the TLS config functions, which may run before `moq_quic_init`.
+pub(crate) fn set_last_error(msg: &str) {
+    let error_buf = LAST_ERROR.get_or_init(|| Mutex::new(Vec::new()));
+    let mut buf = error_buf.lock().unwrap();
+    let msg_bytes = msg.as_bytes();
+    let len = msg_bytes.len().min(MAX_ERROR_LEN);
+    buf.clear();
+    buf.extend_from_slice(&msg_bytes[..len]);
+}
+
+/// Get the push delivery callback for a connection, if one is registered
+fn receive_callback(connection_id: u64) -> Option<Arc<dyn StreamDataCallback>> {
+    RECEIVE_CALLBACKS.get()?.get(&connection_id).map(|cb| cb.clone())
 }
 
 /// Get the global Tokio runtime
@@ -220,26 +244,76 @@ pub extern "C" fn moq_quic_init() {
         log::warn!("Data stream buffers registry already initialized");
     }
 
+    // Initialize data stream states registry
+    if DATA_STREAM_STATES.set(DashMap::new()).is_err() {
+        log::warn!("Data stream states registry already initialized");
+    }
+
     // Initialize active data streams registry
     if ACTIVE_DATA_STREAMS.set(DashMap::new()).is_err() {
         log::warn!("Active data streams registry already initialized");
     }
 
+    // Initialize bidirectional stream registries
+    if BIDI_RECV_STREAMS.set(DashMap::new()).is_err() {
+        log::warn!("Bidirectional streams registry already initialized");
+    }
+    if PENDING_BIDI_STREAMS.set(DashMap::new()).is_err() {
+        log::warn!("Pending bidirectional streams registry already initialized");
+    }
+
+    // Initialize stream handles registry
+    if STREAM_HANDLES.set(DashMap::new()).is_err() {
+        log::warn!("Stream handles registry already initialized");
+    }
+
+    // Initialize connect options registry
+    if CONNECTION_OPTIONS.set(DashMap::new()).is_err() {
+        log::warn!("Connection options registry already initialized");
+    }
+
     // Initialize datagram buffers registry
     if DATAGRAM_BUFFERS.set(DashMap::new()).is_err() {
         log::warn!("Datagram buffers registry already initialized");
     }
 
-    // Initialize last error buffer
-    if LAST_ERROR.set(Mutex::new(Vec::new())).is_err() {
-        log::warn!("Last error buffer already initialized");
+    // Initialize receive callbacks registry
+    if RECEIVE_CALLBACKS.set(DashMap::new()).is_err() {
+        log::warn!("Receive callbacks registry already initialized");
+    }
+
+    // Initialize event queues registry
+    if EVENT_QUEUES.set(DashMap::new()).is_err() {
+        log::warn!("Event queues registry already initialized");
+    }
+
+    // Initialize connection statistics registry
+    if CONNECTION_STATS.set(DashMap::new()).is_err() {
+        log::warn!("Connection statistics registry already initialized");
+    }
+
+    if CONNECT_ATTEMPTS.set(DashMap::new()).is_err() {
+        log::warn!("Connect attempts registry already initialized");
+    }
+
+    // Initialize pending connects registry
+    if PENDING_CONNECTS.set(DashMap::new()).is_err() {
+        log::warn!("Pending connects registry already initialized");
     }
 
+    // Initialize last error buffer
+    LAST_ERROR.get_or_init(|| Mutex::new(Vec::new()));
+
     log::info!("MoQ QUIC transport initialized");
 }
 
 /// Create a new QUIC connection with bidirectional control stream
 ///
+/// Every address the host resolves to is tried, IPv6 and IPv4 interleaved
+/// with staggered starts (RFC 8305); the first handshake to complete wins.
+/// See `moq_quic_get_connect_attempts` for what was tried; if the connect
+/// fails, the attempts are listed in the last error instead.
+///
 /// # Arguments
 /// * `host` - The hostname to connect to (must be null-terminated)
 /// * `port` - The port to connect to
@@ -257,13 +331,245 @@ pub extern "C" fn moq_quic_connect(
     alpn: *const c_char,
     out_connection_id: *mut u64,
 ) -> i32 {
+    moq_quic_connect_with_options(host, port, insecure, moq_version, alpn, std::ptr::null(), out_connection_id)
+}
+
+/// Create a new QUIC connection with caller-supplied transport parameters
+///
+/// Same as `moq_quic_connect`, but idle timeout, keep-alive, stream limits,
+/// initial RTT, receive windows, buffer sizes and the congestion controller
+/// can be overridden.
+///
+/// # Arguments
+/// * `options` - Optional pointer to connect options (null or zeroed fields keep the defaults)
+///
+/// # Returns
+/// * 0 on success, negative error code on failure (-3 for invalid options,
+///   -9 if the server certificate does not match a pinned hash)
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_connect_with_options(
+    host: *const c_char,
+    port: u16,
+    insecure: u8,
+    moq_version: u32,
+    alpn: *const c_char,
+    options: *const MoqConnectOptions,
+    out_connection_id: *mut u64,
+) -> i32 {
+    let (host_str, alpn_override) = match parse_connect_args(host, alpn) {
+        Ok(args) => args,
+        Err(e) => return e,
+    };
+    let options = MoqConnectOptions::from_ptr(options);
+
+    let runtime = get_runtime();
+
+    // Perform all connection setup within the runtime
+    let result = runtime.block_on(establish_connection(
+        host_str,
+        port,
+        insecure != 0,
+        moq_version,
+        alpn_override,
+        options,
+        None,
+    ));
+
+    let (endpoint, connection, attempts) = match result {
+        Ok(established) => established,
+        Err(e) => return e,
+    };
+
+    let connection_id = register_connection(endpoint, connection, attempts, &options);
+
+    unsafe {
+        *out_connection_id = connection_id;
+    }
+
+    log::info!("QUIC connection established (ID: {})", connection_id);
+    0
+}
+
+/// Start a QUIC connection without blocking the caller
+///
+/// Takes the same arguments as `moq_quic_connect_with_options`, but returns
+/// as soon as the connect task is spawned. Poll the handle with
+/// `moq_quic_connect_poll` and release it with `moq_quic_connect_free`.
+///
+/// # Arguments
+/// * `out_handle` - Output parameter for the pending connect handle
+///
+/// # Returns
+/// * 0 on success, negative error code if the arguments are invalid
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_connect_start(
+    host: *const c_char,
+    port: u16,
+    insecure: u8,
+    moq_version: u32,
+    alpn: *const c_char,
+    options: *const MoqConnectOptions,
+    out_handle: *mut u64,
+) -> i32 {
+    if out_handle.is_null() {
+        return -1;
+    }
+    let (host_str, alpn_override) = match parse_connect_args(host, alpn) {
+        Ok(args) => args,
+        Err(e) => return e,
+    };
+    let options = MoqConnectOptions::from_ptr(options);
+
+    let runtime = get_runtime();
+    let pending_connects = PENDING_CONNECTS.get().expect("Pending connects not initialized");
+
+    let handle = NEXT_PENDING_CONNECT_ID.fetch_add(1, Ordering::SeqCst);
+    let progress = Arc::new(PendingConnect::new());
+    pending_connects.insert(handle, progress.clone());
+
+    let progress_for_task = progress.clone();
+    let task = runtime.spawn(async move {
+        let result = establish_connection(
+            host_str,
+            port,
+            insecure != 0,
+            moq_version,
+            alpn_override,
+            options,
+            Some(&progress_for_task),
+        )
+        .await;
+
+        match result {
+            Ok((endpoint, connection, attempts)) => {
+                let conn_for_cancel = connection.clone();
+                let connected =
+                    progress_for_task.complete(|| register_connection(endpoint, connection, attempts, &options));
+                if connected {
+                    log::info!("QUIC connection established for pending connect {}", handle);
+                } else {
+                    log::info!("Pending connect {} was cancelled, closing connection", handle);
+                    conn_for_cancel.close(VarInt::from_u32(0), b"cancelled");
+                }
+            }
+            Err(e) => progress_for_task.fail(e),
+        }
+    });
+    progress.set_task(task.abort_handle());
+
+    unsafe {
+        *out_handle = handle;
+    }
+    0
+}
+
+/// Poll a connect started with `moq_quic_connect_start`
+///
+/// # Arguments
+/// * `handle` - The pending connect handle
+/// * `out_connection_id` - Set to the connection ID once connected (may be null)
+/// * `out_error` - Set to the connect error code once failed (may be null)
+///
+/// # Returns
+/// * One of the `MOQ_CONNECT_*` states, or -1 if the handle is not found
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_connect_poll(
+    handle: u64,
+    out_connection_id: *mut u64,
+    out_error: *mut i32,
+) -> i32 {
+    let pending_connects = PENDING_CONNECTS.get().expect("Pending connects not initialized");
+
+    let progress = match pending_connects.get(&handle) {
+        Some(p) => p.clone(),
+        None => return -1,
+    };
+
+    let (state, result) = progress.poll();
+    unsafe {
+        if state == MOQ_CONNECT_CONNECTED && !out_connection_id.is_null() {
+            *out_connection_id = result as u64;
+        }
+        if state == MOQ_CONNECT_FAILED && !out_error.is_null() {
+            *out_error = result as i32;
+        }
+    }
+    state
+}
+
+/// Cancel a connect started with `moq_quic_connect_start`
+///
+/// A connect that already completed is left alone; if it connected, the
+/// connection still belongs to the caller and must be closed normally.
+///
+/// # Returns
+/// * The state the connect was in before the call, or -1 if the handle is not found
+#[no_mangle]
+pub extern "C" fn moq_quic_connect_cancel(handle: u64) -> i32 {
+    let pending_connects = PENDING_CONNECTS.get().expect("Pending connects not initialized");
+
+    match pending_connects.get(&handle) {
+        Some(progress) => progress.cancel(),
+        None => -1,
+    }
+}
+
+/// Get the addresses tried by a connect started with `moq_quic_connect_start`
+///
+/// Same format as `moq_quic_get_connect_attempts`, and also available after
+/// the connect failed.
+///
+/// # Arguments
+/// * `handle` - The pending connect handle
+/// * `buffer` - Pointer to buffer to store the report
+/// * `buffer_len` - Length of buffer
+///
+/// # Returns
+/// * Number of bytes written (truncated to fit), 0 if no address has been
+///   tried yet, -1 if the handle is not found
+#[no_mangle]
+pub extern "C" fn moq_quic_connect_get_attempts(
+    handle: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i32 {
+    let pending_connects = PENDING_CONNECTS.get().expect("Pending connects not initialized");
+
+    match pending_connects.get(&handle) {
+        Some(progress) => ffi_text::copy_text(&progress.attempts(), buffer, buffer_len),
+        None => -1,
+    }
+}
+
+/// Release a pending connect handle, cancelling the connect if still in flight
+///
+/// # Returns
+/// * 0 on success, -1 if the handle is not found
+#[no_mangle]
+pub extern "C" fn moq_quic_connect_free(handle: u64) -> i32 {
+    let pending_connects = PENDING_CONNECTS.get().expect("Pending connects not initialized");
+
+    match pending_connects.remove(&handle) {
+        Some((_, progress)) => {
+            progress.cancel();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Validate the host and optional ALPN override passed over FFI
+fn parse_connect_args(host: *const c_char, alpn: *const c_char) -> Result<(String, Option<String>), i32> {
     let host_str = unsafe {
         if host.is_null() {
-            return -1; // Invalid host
+            return Err(-1); // Invalid host
         }
         match std::ffi::CStr::from_ptr(host).to_str() {
             Ok(s) => s.to_string(),
-            Err(_) => return -2, // Invalid UTF-8
+            Err(_) => return Err(-2), // Invalid UTF-8
         }
     };
     let alpn_override = unsafe {
@@ -273,154 +579,178 @@ pub extern "C" fn moq_quic_connect(
             match std::ffi::CStr::from_ptr(alpn).to_str() {
                 Ok("") => None,
                 Ok(s) => Some(s.to_string()),
-                Err(_) => return -2, // Invalid UTF-8
+                Err(_) => return Err(-2), // Invalid UTF-8
             }
         }
     };
+    Ok((host_str, alpn_override))
+}
 
-    let runtime = get_runtime();
+/// Resolve, race and complete the QUIC handshake
+///
+/// Sets the last error and returns the FFI error code on failure.
+///
+/// # Returns
+/// * The endpoint and connection, plus the report of every address tried
+///   (also recorded in `progress`, which keeps it when the connect fails)
+async fn establish_connection(
+    host_str: String,
+    port: u16,
+    insecure: bool,
+    moq_version: u32,
+    alpn_override: Option<String>,
+    options: MoqConnectOptions,
+    progress: Option<&PendingConnect>,
+) -> Result<(Endpoint, Connection, String), i32> {
+    // Resolve hostname to IP address (supports DNS)
+    let addr_str = format!("{}:{}", host_str, port);
+    let addrs = match tokio::net::lookup_host(&addr_str).await {
+        Ok(addrs) => addrs,
+        Err(e) => {
+            let err_msg = format!("DNS resolution error for {}: {:?}", addr_str, e);
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-4);
+        }
+    };
+    if let Some(progress) = progress {
+        progress.handshaking();
+    }
 
-    // Perform all connection setup within the runtime
-    let result = runtime.block_on(async {
-        // Resolve hostname to IP address (supports DNS)
-        let addr_str = format!("{}:{}", host_str, port);
-        let addrs = match tokio::net::lookup_host(&addr_str).await {
-            Ok(addrs) => addrs,
-            Err(e) => {
-                let err_msg = format!("DNS resolution error for {}: {:?}", addr_str, e);
-                log::error!("{}", err_msg);
-                set_last_error(&err_msg);
-                return Err(-4);
-            }
-        };
+    // Resolve the TLS trust settings for this connection
+    let tls_config = match tls::lookup(options.tls_config_id) {
+        Ok(c) => c,
+        Err(err_msg) => {
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-3);
+        }
+    };
 
-        // Use the first resolved address
-        let addr = match addrs.into_iter().next() {
-            Some(a) => a,
-            None => {
-                set_last_error("No addresses resolved");
-                return Err(-4);
-            }
-        };
+    // Build transport config with standard settings (from moq-native-ietf)
+    let mut transport = TransportConfig::default();
+    transport.max_idle_timeout(Some(time::Duration::from_secs(10).try_into().unwrap()));
+    transport.keep_alive_interval(Some(time::Duration::from_secs(4)));
+    transport.max_concurrent_bidi_streams(100u32.into());
+    transport.max_concurrent_uni_streams(100u32.into());
+    // Enable datagrams with max size (for low-latency audio)
+    transport.datagram_receive_buffer_size(Some(65536));
+    transport.datagram_send_buffer_size(datagram::DEFAULT_SEND_BUFFER_SIZE);
+    if let Err(err_msg) = options.apply(&mut transport) {
+        log::error!("{}", err_msg);
+        set_last_error(&err_msg);
+        return Err(-3);
+    }
 
-        // Create client configuration with proper transport settings
-        // Load system root certificates for TLS validation
-        let mut certs = rustls::RootCertStore::empty();
-        let native_certs_result = rustls_native_certs::load_native_certs();
-        if let Some(ref e) = native_certs_result.errors.first() {
-            log::warn!("Error loading some native certs: {:?}", e);
-        }
-        for cert in native_certs_result.certs {
-            if let Err(e) = certs.add(cert) {
-                log::warn!("Failed to add native cert: {:?}", e);
-            }
+    // Create client configuration with ALPN protocols
+    let (client_crypto, pin_status) = match tls::client_crypto(insecure, tls_config.as_ref(), "QUIC") {
+        Ok((c, p)) => (c, p),
+        Err(err_msg) => {
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-6);
         }
-        log::info!("Loaded {} system root certificates", certs.len());
-
-        // Build transport config with standard settings (from moq-native-ietf)
-        let mut transport = TransportConfig::default();
-        transport.max_idle_timeout(Some(time::Duration::from_secs(10).try_into().unwrap()));
-        transport.keep_alive_interval(Some(time::Duration::from_secs(4)));
-        transport.max_concurrent_bidi_streams(100u32.into());
-        transport.max_concurrent_uni_streams(100u32.into());
-        // Enable datagrams with max size (for low-latency audio)
-        transport.datagram_receive_buffer_size(Some(65536));
-        transport.datagram_send_buffer_size(65536);
-
-        // Create client configuration with ALPN protocols
-        let client_crypto = if insecure != 0 {
-            // Disable certificate verification for testing
-            let builder = rustls::ClientConfig::builder()
-                .dangerous()
-                .with_custom_certificate_verifier(Arc::new(NoVerification));
-            builder.with_no_client_auth()
-        } else {
-            rustls::ClientConfig::builder()
-                .with_root_certificates(certs)
-                .with_no_client_auth()
-        };
+    };
 
-        // Set ALPN based on the requested MoQ draft version.
-        let mut client_crypto = client_crypto;
-        let alpn = if let Some(alpn_override) = &alpn_override {
-            alpn_override.as_bytes().to_vec()
-        } else if moq_version >= 0xff00_0010 {
-            b"moqt-16".to_vec()
-        } else {
-            b"moq-00".to_vec()
-        };
-        log::info!("Using ALPN {:?}", String::from_utf8_lossy(&alpn));
-        client_crypto.alpn_protocols = vec![alpn];
+    // Set ALPN based on the requested MoQ draft version.
+    let mut client_crypto = client_crypto;
+    let alpn = if let Some(alpn_override) = &alpn_override {
+        alpn_override.as_bytes().to_vec()
+    } else if moq_version >= 0xff00_0010 {
+        b"moqt-16".to_vec()
+    } else {
+        b"moq-00".to_vec()
+    };
+    log::info!("Using ALPN {:?}", String::from_utf8_lossy(&alpn));
+    client_crypto.alpn_protocols = vec![alpn];
 
-        let crypto = match QuicClientConfig::try_from(client_crypto) {
-            Ok(c) => c,
-            Err(e) => {
-                let err_msg = format!("QuicClientConfig error: {:?}", e);
-                log::error!("{}", err_msg);
-                set_last_error(&err_msg);
-                return Err(-6);
-            }
-        };
-        let mut client_config = ClientConfig::new(Arc::new(crypto));
-        client_config.transport_config(Arc::new(transport));
+    let crypto = match QuicClientConfig::try_from(client_crypto) {
+        Ok(c) => c,
+        Err(e) => {
+            let err_msg = format!("QuicClientConfig error: {:?}", e);
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-6);
+        }
+    };
+    let mut client_config = ClientConfig::new(Arc::new(crypto));
+    client_config.transport_config(Arc::new(transport));
 
-        // Create endpoint with a UDP socket (std::net::UdpSocket, not tokio)
-        let socket = match std::net::UdpSocket::bind("0.0.0.0:0") {
-            Ok(s) => s,
-            Err(e) => {
-                let err_msg = format!("UDP bind error: {:?}", e);
-                log::error!("{}", err_msg);
-                set_last_error(&err_msg);
-                return Err(-5);
-            }
-        };
+    // Create endpoint with a dual-stack UDP socket (std::net::UdpSocket, not tokio)
+    let socket = match happy_eyeballs::bind_dual_stack_socket() {
+        Ok(s) => s,
+        Err(e) => {
+            let err_msg = format!("UDP bind error: {:?}", e);
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-5);
+        }
+    };
 
-        let mut endpoint = match Endpoint::new(
-            EndpointConfig::default(),
-            None, // No server config for client-only
-            socket,
-            Arc::new(TokioRuntime),
-        ) {
-            Ok(e) => e,
-            Err(e) => {
-                let err_msg = format!("Endpoint creation error: {:?}", e);
-                log::error!("{}", err_msg);
-                set_last_error(&err_msg);
-                return Err(-6);
-            }
-        };
+    let endpoint = match Endpoint::new(
+        EndpointConfig::default(),
+        None, // No server config for client-only
+        socket,
+        Arc::new(TokioRuntime),
+    ) {
+        Ok(e) => e,
+        Err(e) => {
+            let err_msg = format!("Endpoint creation error: {:?}", e);
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-6);
+        }
+    };
 
-        // Set the default client config
-        endpoint.set_default_client_config(client_config);
+    // Race every resolved address (RFC 8305)
+    let ipv6_capable = endpoint.local_addr().map(|a| a.is_ipv6()).unwrap_or(false);
+    let addrs = happy_eyeballs::sort_addresses(addrs, ipv6_capable);
+    if addrs.is_empty() {
+        set_last_error("No usable addresses resolved");
+        return Err(-4);
+    }
 
-        // Connect
-        let connecting = match endpoint.connect(addr, &host_str) {
-            Ok(c) => c,
-            Err(e) => {
-                let err_msg = format!("Connect error: {:?}", e);
-                log::error!("{}", err_msg);
-                set_last_error(&err_msg);
-                return Err(-6);
-            }
-        };
+    let (winner, attempts) = happy_eyeballs::race(&endpoint, client_config, &addrs, &host_str).await;
+    let report = happy_eyeballs::format_attempts(&attempts);
+    if let Some(progress) = progress {
+        progress.set_attempts(report.clone());
+    }
 
-        let connection = match connecting.await {
-            Ok(conn) => conn,
-            Err(e) => {
-                let err_msg = format!("Connection await error: {:?}", e);
-                log::error!("{}", err_msg);
-                set_last_error(&err_msg);
-                return Err(-7);
-            }
-        };
+    let connection = match winner {
+        Some((conn, addr)) => {
+            log::info!("Connected to {} after {} attempt(s)", happy_eyeballs::canonical_address(addr), attempts.len());
+            conn
+        }
+        None if pin_status.mismatched() => {
+            set_last_error("Server certificate does not match any pinned hash");
+            return Err(-9);
+        }
+        None => {
+            let err_msg = format!(
+                "Connection await error: all {} attempt(s) failed: {}",
+                attempts.len(),
+                report.replace('\n', "; "),
+            );
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-7);
+        }
+    };
 
-        Ok((endpoint, connection))
-    });
+    Ok((endpoint, connection, report))
+}
 
-    let (endpoint, connection) = match result {
-        Ok((e, c)) => (e, c),
-        Err(e) => return e,
-    };
+/// Register a freshly established connection and start its background tasks
+///
+/// # Returns
+/// * The new connection ID
+fn register_connection(
+    endpoint: Endpoint,
+    connection: Connection,
+    attempts: String,
+    options: &MoqConnectOptions,
+) -> u64 {
+    let runtime = get_runtime();
 
     // Allocate connection ID
     let connection_id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::SeqCst);
@@ -434,13 +764,27 @@ pub extern "C" fn moq_quic_connect(
 
     let connection_arc = Arc::new(connection);
     let endpoint_arc = Arc::new(endpoint);
-    let recv_buffer = Arc::new(tokio::sync::Mutex::new(ReceiveBuffer::new(MAX_RECV_BUFFER_SIZE)));
+    let recv_buffer = Arc::new(tokio::sync::Mutex::new(ReceiveBuffer::new(
+        options.control_recv_buffer_size(),
+    )));
+    let data_recv_buffer_size = options.data_recv_buffer_size();
+    let stall_control = options.control_stalls_when_full();
+    let stall_data = options.data_stalls_when_full();
 
     connections.insert(connection_id, connection_arc.clone());
     endpoints.insert(connection_id, endpoint_arc);
     control_streams.insert(connection_id, Arc::new(tokio::sync::Mutex::new(None)));
     recv_buffers.insert(connection_id, recv_buffer.clone());
     active_data_streams.insert(connection_id, Arc::new(tokio::sync::Mutex::new(Vec::new())));
+    PENDING_BIDI_STREAMS.get().expect("Pending bidirectional streams not initialized")
+        .insert(connection_id, Arc::new(Mutex::new(VecDeque::new())));
+    CONNECTION_OPTIONS.get().expect("Connection options not initialized")
+        .insert(connection_id, *options);
+    let stream_handles = Arc::new(StreamHandles::default());
+    STREAM_HANDLES.get().expect("Stream handles not initialized")
+        .insert(connection_id, stream_handles.clone());
+    CONNECT_ATTEMPTS.get().expect("Connect attempts not initialized")
+        .insert(connection_id, attempts);
 
     // Log datagram capability negotiated with peer
     match connection_arc.max_datagram_size() {
@@ -450,11 +794,36 @@ pub extern "C" fn moq_quic_connect(
 
     // Initialize datagram buffer for this connection
     let datagram_buffers = DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
-    datagram_buffers.insert(connection_id, Arc::new(tokio::sync::Mutex::new(VecDeque::new())));
+    let datagram_queue = DatagramQueue::new(
+        options.datagram_queue_capacity(MAX_QUEUED_DATAGRAMS),
+        options.datagram_drops_oldest(),
+    );
+    datagram_buffers.insert(connection_id, Arc::new(tokio::sync::Mutex::new(datagram_queue)));
+
+    // Track statistics quinn does not keep itself
+    let stats_tracker = Arc::new(StatsTracker::default());
+    CONNECTION_STATS.get().expect("Connection statistics not initialized")
+        .insert(connection_id, stats_tracker.clone());
+
+    // Lifecycle events for moq_quic_poll_event
+    let events = Arc::new(EventQueue::default());
+    EVENT_QUEUES.get().expect("Event queues not initialized")
+        .insert(connection_id, events.clone());
+
+    let connection_for_close = connection_arc.clone();
+    let events_for_close = events.clone();
+    runtime.spawn(async move {
+        let error = connection_for_close.closed().await;
+        log::info!("Connection {} closed: {}", connection_id, error);
+        let (info, reason) = close::close_info(&error);
+        events_for_close.push_closed(info, reason);
+    });
 
     // Open bidirectional control stream (required by MoQ spec)
     let connection_for_control = connection_arc.clone();
     let recv_buffer_for_control = recv_buffer.clone();
+    let events_for_control = events.clone();
+    let stats_for_control = stats_tracker.clone();
     runtime.spawn(async move {
         log::info!("Opening bidirectional control stream for connection {}", connection_id);
         match connection_for_control.open_bi().await {
@@ -466,27 +835,54 @@ pub extern "C" fn moq_quic_connect(
                 if let Some(ctrl_stream_mutex) = control_streams.get(&connection_id) {
                     *ctrl_stream_mutex.lock().await = Some(ControlStream { send });
                 }
+                events_for_control.push(MOQ_EVENT_CONTROL_STREAM_READY);
 
                 // Start reading from the control stream's receive side
-                // Use larger buffer for video streaming
-                let mut buffer = vec![0u8; 64 * 1024]; // 64KB
+                // Chunks are kept as quinn hands them over, up to 64KB each
                 loop {
-                    match recv.read(&mut buffer).await {
+                    // When stalling, only read what fits so the rest stays under flow control
+                    let max_len = if stall_control && receive_callback(connection_id).is_none() {
+                        match receive_buffer::wait_for_space(&recv_buffer_for_control, &connection_for_control).await {
+                            Some(space) => space.min(receive_buffer::READ_CHUNK_SIZE),
+                            None => break,
+                        }
+                    } else {
+                        receive_buffer::READ_CHUNK_SIZE
+                    };
+                    match recv.read_chunk(max_len, true).await {
                         Ok(None) => {
                             log::debug!("Control stream closed for connection {}", connection_id);
+                            events_for_control.push(MOQ_EVENT_CONTROL_STREAM_FINISHED);
                             break;
                         }
-                        Ok(Some(n)) => {
+                        Ok(Some(chunk)) => {
+                            let n = chunk.bytes.len();
+                            if let Some(callback) = receive_callback(connection_id) {
+                                callback.on_control_data(connection_id, &chunk.bytes);
+                                continue;
+                            }
+
                             // Add data to receive buffer
                             let mut recv_buf = recv_buffer_for_control.lock().await;
-                            let pushed = recv_buf.push(&buffer[..n]);
+                            let pushed = recv_buf.push_bytes(chunk.bytes);
                             if pushed < n {
                                 log::warn!("Receive buffer full, dropped {} bytes", n - pushed);
+                                stats_for_control.stream_bytes_dropped(n - pushed);
+                                events_for_control.push_overflow(MOQ_BUFFER_CONTROL, 0, (n - pushed) as u64);
                             }
                             log::trace!("Received {} bytes on control stream for connection {}", n, connection_id);
                         }
+                        Err(quinn::ReadError::Reset(code)) => {
+                            log::warn!("Control stream reset by peer for connection {} (code {})", connection_id, code);
+                            events_for_control.push_stream(MOQ_EVENT_CONTROL_STREAM_RESET, 0, code.into_inner());
+                            break;
+                        }
                         Err(e) => {
                             log::error!("Error reading from control stream: {:?}", e);
+                            // A rejected client certificate only shows up once the server closes
+                            if let Some(alert) = tls::tls_alert_reason(&e) {
+                                set_last_error(&alert);
+                            }
                             break;
                         }
                     }
@@ -494,25 +890,32 @@ pub extern "C" fn moq_quic_connect(
             }
             Err(e) => {
                 log::error!("Failed to open control stream for connection {}: {:?}", connection_id, e);
+                if let Some(alert) = tls::tls_alert_reason(&e) {
+                    set_last_error(&alert);
+                }
             }
         }
     });
 
     // Start accepting incoming unidirectional streams (data streams)
     let connection_for_streams = connection_arc.clone();
+    let events_for_streams = events.clone();
+    let stats_for_streams = stats_tracker.clone();
+    let handles_for_streams = stream_handles.clone();
     runtime.spawn(async move {
         log::info!("Starting data stream acceptor for connection {}", connection_id);
         let data_stream_buffers = DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
         let active_streams = ACTIVE_DATA_STREAMS.get().expect("Active data streams not initialized");
+        let data_stream_states = DATA_STREAM_STATES.get().expect("Data stream states not initialized");
 
         loop {
             match connection_for_streams.accept_uni().await {
-                Ok(mut recv_stream) => {
-                    let stream_id = recv_stream.id().index();
+                Ok(recv_stream) => {
+                    let stream_id = handles_for_streams.allocate(recv_stream.id(), false);
                     log::info!("*** ACCEPTED INCOMING UNI STREAM {} on connection {} ***", stream_id, connection_id);
 
                     // Create a buffer for this specific data stream
-                    let stream_buffer = Arc::new(tokio::sync::Mutex::new(ReceiveBuffer::new(MAX_RECV_BUFFER_SIZE)));
+                    let stream_buffer = Arc::new(tokio::sync::Mutex::new(ReceiveBuffer::new(data_recv_buffer_size)));
                     data_stream_buffers.insert((connection_id, stream_id), stream_buffer.clone());
 
                     // Add to active streams list
@@ -520,35 +923,23 @@ pub extern "C" fn moq_quic_connect(
                         let mut list = streams_list.lock().await;
                         list.push(stream_id);
                     }
+                    let (stream_state, stop_rx) = IncomingStream::new();
+                    data_stream_states.insert((connection_id, stream_id), stream_state.clone());
+                    events_for_streams.push_stream(MOQ_EVENT_DATA_STREAM_OPENED, stream_id, 0);
 
                     // Spawn task to read from this stream
-                    // Use larger buffer for video streaming to reduce syscalls
-                    tokio::spawn(async move {
-                        let mut buffer = vec![0u8; 64 * 1024]; // 64KB
-                        loop {
-                            match recv_stream.read(&mut buffer).await {
-                                Ok(None) => {
-                                    log::debug!("Data stream {} closed on connection {}", stream_id, connection_id);
-                                    // Note: We don't remove the buffer here - let Dart poll it dry first
-                                    // Dart will call moq_quic_close_data_stream when done
-                                    break;
-                                }
-                                Ok(Some(n)) => {
-                                    // Add data to this stream's buffer (not the control stream buffer)
-                                    let mut recv_buf = stream_buffer.lock().await;
-                                    let pushed = recv_buf.push(&buffer[..n]);
-                                    if pushed < n {
-                                        log::warn!("Data stream {} buffer full, dropped {} bytes", stream_id, n - pushed);
-                                    }
-                                    log::info!("*** STREAM DATA: {} bytes on stream {} for conn {} ***", n, stream_id, connection_id);
-                                }
-                                Err(e) => {
-                                    log::error!("Error reading from data stream {}: {:?}", stream_id, e);
-                                    break;
-                                }
-                            }
-                        }
-                    });
+                    let reader = IncomingStreamReader {
+                        connection_id,
+                        stream_id,
+                        bidi: false,
+                        connection: connection_for_streams.clone(),
+                        buffer: stream_buffer,
+                        state: stream_state,
+                        events: events_for_streams.clone(),
+                        stats: stats_for_streams.clone(),
+                        stall: stall_data,
+                    };
+                    tokio::spawn(reader.run(recv_stream, stop_rx));
                 }
                 Err(e) => {
                     log::error!("Error accepting incoming stream: {:?}", e);
@@ -560,8 +951,36 @@ pub extern "C" fn moq_quic_connect(
         log::info!("Data stream acceptor stopped for connection {}", connection_id);
     });
 
+    // Start accepting bidirectional streams opened by the peer
+    let connection_for_bidi = connection_arc.clone();
+    let events_for_bidi = events.clone();
+    let handles_for_bidi = stream_handles.clone();
+    runtime.spawn(async move {
+        loop {
+            match connection_for_bidi.accept_bi().await {
+                Ok((send_stream, recv_stream)) => {
+                    let stream_id = handles_for_bidi.allocate(send_stream.id(), false);
+                    log::info!("Accepted bidirectional stream {} on connection {}", stream_id, connection_id);
+                    if !start_bidi_stream(connection_id, stream_id, connection_for_bidi.clone(), send_stream, recv_stream) {
+                        break;
+                    }
+                    if let Some(pending) = PENDING_BIDI_STREAMS.get().and_then(|p| p.get(&connection_id).map(|l| l.clone())) {
+                        pending.lock().unwrap().push_back(stream_id);
+                    }
+                    events_for_bidi.push_stream(MOQ_EVENT_BIDI_STREAM_OPENED, stream_id, 0);
+                }
+                Err(e) => {
+                    log::debug!("Bidirectional stream acceptor stopped for connection {}: {}", connection_id, e);
+                    break;
+                }
+            }
+        }
+    });
+
     // Start datagram receiver task
     let connection_for_datagrams = connection_arc.clone();
+    let stats_for_datagrams = stats_tracker.clone();
+    let events_for_datagrams = events.clone();
     runtime.spawn(async move {
         log::info!("Starting datagram receiver for connection {}", connection_id);
         let datagram_buffers = DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
@@ -571,15 +990,21 @@ pub extern "C" fn moq_quic_connect(
                 Ok(datagram) => {
                     log::trace!("Received datagram ({} bytes) on connection {}", datagram.len(), connection_id);
 
-                    // Store the complete datagram in the buffer
+                    if let Some(callback) = receive_callback(connection_id) {
+                        callback.on_datagram(connection_id, &datagram);
+                        continue;
+                    }
+
+                    // Store the complete datagram in the buffer
                     if let Some(buffer) = datagram_buffers.get(&connection_id) {
                         let mut buf = buffer.lock().await;
-                        // Limit buffer size to prevent unbounded growth
-                        const MAX_DATAGRAM_BUFFER: usize = 1000;
-                        if buf.len() < MAX_DATAGRAM_BUFFER {
-                            buf.push_back(datagram.to_vec());
-                        } else {
+                        if !buf.push(datagram) {
                             log::warn!("Datagram buffer full, dropping datagram");
+                            stats_for_datagrams.datagram_dropped();
+                            events_for_datagrams.push_overflow(MOQ_BUFFER_DATAGRAM, 0, 1);
+                        }
+                        if buf.len() == 1 {
+                            events_for_datagrams.push(MOQ_EVENT_DATAGRAM_AVAILABLE);
                         }
                     }
                 }
@@ -593,12 +1018,157 @@ pub extern "C" fn moq_quic_connect(
         log::info!("Datagram receiver stopped for connection {}", connection_id);
     });
 
-    unsafe {
-        *out_connection_id = connection_id;
+    connection_id
+}
+
+/// Delivers one incoming stream (a data stream or the receive side of a
+/// bidirectional stream) to its buffer or the push callback
+struct IncomingStreamReader {
+    connection_id: u64,
+    stream_id: u64,
+    bidi: bool,
+    connection: Arc<Connection>,
+    buffer: SharedReceiveBuffer,
+    state: SharedStreamState,
+    events: Arc<EventQueue>,
+    stats: Arc<StatsTracker>,
+    stall: bool,
+}
+
+impl IncomingStreamReader {
+    /// Read until the stream ends or is stopped through `stop_rx`
+    ///
+    /// Chunks of up to 64KB are buffered without copying.
+    async fn run(self, mut recv_stream: quinn::RecvStream, mut stop_rx: oneshot::Receiver<VarInt>) {
+        let Self { connection_id, stream_id, bidi, .. } = self;
+        let (finished_event, reset_event, overflow_buffer) = if bidi {
+            (MOQ_EVENT_BIDI_STREAM_FINISHED, MOQ_EVENT_BIDI_STREAM_RESET, MOQ_BUFFER_BIDI_STREAM)
+        } else {
+            (MOQ_EVENT_DATA_STREAM_FINISHED, MOQ_EVENT_DATA_STREAM_RESET, MOQ_BUFFER_DATA_STREAM)
+        };
+
+        loop {
+            let read = async {
+                let max_len = if self.stall && receive_callback(connection_id).is_none() {
+                    receive_buffer::wait_for_space(&self.buffer, &self.connection).await?.min(receive_buffer::READ_CHUNK_SIZE)
+                } else {
+                    receive_buffer::READ_CHUNK_SIZE
+                };
+                Some(recv_stream.read_chunk(max_len, true).await)
+            };
+            // Waiting for buffer space or data both give way to a stop request
+            let result = tokio::select! {
+                result = read => result,
+                Ok(code) = &mut stop_rx, if !stop_rx.is_terminated() => {
+                    let _ = recv_stream.stop(code);
+                    log::debug!("Stopped stream {} on connection {} (code {})", stream_id, connection_id, code);
+                    self.state.set_final(MOQ_STREAM_STATE_STOPPED, code.into_inner());
+                    break;
+                }
+            };
+            let Some(result) = result else {
+                // Connection closed while waiting for buffer space
+                self.state.set_final(MOQ_STREAM_STATE_FAILED, 0);
+                break;
+            };
+            match result {
+                Ok(None) => {
+                    log::debug!("Stream {} closed on connection {}", stream_id, connection_id);
+                    // Note: We don't remove the buffer here - let Dart poll it dry first
+                    // Dart will call the close call for the stream when done
+                    self.state.set_final(MOQ_STREAM_STATE_FINISHED, 0);
+                    self.events.push_stream(finished_event, stream_id, 0);
+                    break;
+                }
+                Ok(Some(chunk)) => {
+                    let n = chunk.bytes.len();
+                    if let Some(callback) = receive_callback(connection_id) {
+                        if bidi {
+                            callback.on_bidi_stream_data(connection_id, stream_id, &chunk.bytes);
+                        } else {
+                            callback.on_stream_data(connection_id, stream_id, &chunk.bytes);
+                        }
+                        continue;
+                    }
+
+                    // Add data to this stream's buffer (not the control stream buffer)
+                    let mut recv_buf = self.buffer.lock().await;
+                    let pushed = recv_buf.push_bytes(chunk.bytes);
+                    if pushed < n {
+                        log::warn!("Stream {} buffer full, dropped {} bytes", stream_id, n - pushed);
+                        self.stats.stream_bytes_dropped(n - pushed);
+                        self.events.push_overflow(overflow_buffer, stream_id, (n - pushed) as u64);
+                    }
+                    log::trace!("Received {} bytes on stream {} for connection {}", n, stream_id, connection_id);
+                }
+                Err(quinn::ReadError::Reset(code)) => {
+                    log::warn!("Stream {} reset by peer (code {})", stream_id, code);
+                    self.state.set_final(MOQ_STREAM_STATE_RESET, code.into_inner());
+                    self.events.push_stream(reset_event, stream_id, code.into_inner());
+                    break;
+                }
+                Err(e) => {
+                    log::error!("Error reading from stream {}: {:?}", stream_id, e);
+                    self.state.set_final(MOQ_STREAM_STATE_FAILED, 0);
+                    break;
+                }
+            }
+        }
     }
+}
 
-    log::info!("QUIC connection established (ID: {})", connection_id);
-    0
+/// Register both sides of a bidirectional stream under `stream_id`
+///
+/// The send side becomes a StreamWriter used through the `moq_quic_stream_*`
+/// calls; the receive side is buffered for `moq_quic_bidi_recv`.
+///
+/// # Returns
+/// * false if the connection is already gone
+fn start_bidi_stream(
+    connection_id: u64,
+    stream_id: u64,
+    connection: Arc<Connection>,
+    send_stream: SendStream,
+    recv_stream: quinn::RecvStream,
+) -> bool {
+    let events = EVENT_QUEUES.get().and_then(|queues| queues.get(&connection_id).map(|q| q.clone()));
+    let stats = CONNECTION_STATS.get().and_then(|stats| stats.get(&connection_id).map(|s| s.clone()));
+    let options = CONNECTION_OPTIONS.get().and_then(|options| options.get(&connection_id).map(|o| *o));
+    let (Some(events), Some(stats), Some(options)) = (events, stats, options) else {
+        return false;
+    };
+
+    let buffer = Arc::new(tokio::sync::Mutex::new(ReceiveBuffer::new(
+        options.data_recv_buffer_size(),
+    )));
+    let (state, stop_rx) = IncomingStream::new();
+    BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized")
+        .insert((connection_id, stream_id), BidiRecvStream { buffer: buffer.clone(), state: state.clone() });
+
+    let reader = IncomingStreamReader {
+        connection_id,
+        stream_id,
+        bidi: true,
+        connection,
+        buffer,
+        state,
+        events,
+        stats,
+        stall: options.data_stalls_when_full(),
+    };
+    let writer = stream_writer::handle_bidirectional_stream(
+        connection_id,
+        stream_id,
+        send_stream,
+        recv_stream,
+        |recv_stream| reader.run(recv_stream, stop_rx),
+        128, // Channel capacity for buffered writes
+    );
+
+    STREAM_WRITERS.get().expect("Stream writers not initialized")
+        .insert((connection_id, stream_id), writer.clone());
+    watch_stream_writer(connection_id, stream_id, writer);
+    true
 }
 
 /// Send data over the bidirectional control stream
@@ -613,6 +1183,7 @@ pub extern "C" fn moq_quic_connect(
 ///
 /// # Returns
 /// * Number of bytes sent on success, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_quic_send(
     connection_id: u64,
@@ -681,6 +1252,7 @@ pub extern "C" fn moq_quic_send(
 ///
 /// # Returns
 /// * Number of bytes received on success, 0 if no data available, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_quic_recv(
     connection_id: u64,
@@ -722,16 +1294,54 @@ pub extern "C" fn moq_quic_recv(
 #[no_mangle]
 pub extern "C" fn moq_quic_is_connected(connection_id: u64) -> i32 {
     let connections = CONNECTIONS.get().expect("Connection registry not initialized");
-    if connections.contains_key(&connection_id) {
-        1
-    } else {
-        0
+    match connections.get(&connection_id) {
+        Some(connection) if connection.close_reason().is_none() => 1,
+        _ => 0,
     }
 }
 
 /// Close a QUIC connection
 #[no_mangle]
 pub extern "C" fn moq_quic_close(connection_id: u64) -> i32 {
+    moq_quic_close_with_reason(connection_id, 0, std::ptr::null(), 0)
+}
+
+/// Close a QUIC connection with an application error code and reason phrase
+///
+/// Everything but the event queue is released right away. The queue ends
+/// with `MOQ_EVENT_CONNECTION_CLOSED` describing the close that was sent
+/// (unless the connection had already closed), and is released once drained
+/// or by `moq_quic_release`.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `error_code` - Application error code sent to the peer (e.g. a MoQ termination code)
+/// * `reason` - Pointer to the reason phrase (may be null)
+/// * `reason_len` - Length of the reason phrase
+///
+/// # Returns
+/// * 0 on success, -1 if the connection is not found, -2 if the code exceeds 2^62
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_close_with_reason(
+    connection_id: u64,
+    error_code: u64,
+    reason: *const u8,
+    reason_len: usize,
+) -> i32 {
+    let error_code = match VarInt::from_u64(error_code) {
+        Ok(code) => code,
+        Err(_) => {
+            set_last_error("Close error code exceeds 2^62");
+            return -2;
+        }
+    };
+    let reason = if reason.is_null() || reason_len == 0 {
+        &[][..]
+    } else {
+        unsafe { slice::from_raw_parts(reason, reason_len) }
+    };
+
     let connections = CONNECTIONS.get().expect("Connection registry not initialized");
     let endpoints = ENDPOINTS.get().expect("Endpoint registry not initialized");
     let control_streams = CONTROL_STREAMS.get().expect("Control streams not initialized");
@@ -765,19 +1375,48 @@ pub extern "C" fn moq_quic_close(connection_id: u64) -> i32 {
 
     // Clean up data stream buffers for this connection
     data_stream_buffers.retain(|key, _| key.0 != connection_id);
+    let data_stream_states = DATA_STREAM_STATES.get().expect("Data stream states not initialized");
+    data_stream_states.retain(|key, _| key.0 != connection_id);
 
     // Clean up active data streams list
     active_data_streams.remove(&connection_id);
 
+    // Clean up bidirectional streams
+    let bidi_recv_streams = BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized");
+    bidi_recv_streams.retain(|key, _| key.0 != connection_id);
+    let pending_bidi_streams = PENDING_BIDI_STREAMS.get().expect("Pending bidirectional streams not initialized");
+    pending_bidi_streams.remove(&connection_id);
+
+    let stream_handles = STREAM_HANDLES.get().expect("Stream handles not initialized");
+    stream_handles.remove(&connection_id);
+
+    let connection_options = CONNECTION_OPTIONS.get().expect("Connection options not initialized");
+    connection_options.remove(&connection_id);
+    let connect_attempts = CONNECT_ATTEMPTS.get().expect("Connect attempts not initialized");
+    connect_attempts.remove(&connection_id);
+
     // Clean up datagram buffer
     let datagram_buffers = DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
     datagram_buffers.remove(&connection_id);
 
+    let connection_stats = CONNECTION_STATS.get().expect("Connection statistics not initialized");
+    connection_stats.remove(&connection_id);
+
+    let receive_callbacks = RECEIVE_CALLBACKS.get().expect("Receive callbacks not initialized");
+    receive_callbacks.remove(&connection_id);
+
+    // The event queue outlives the connection so the app can still read the close
+    let event_queues = EVENT_QUEUES.get().expect("Event queues not initialized");
+    if let Some(events) = event_queues.get(&connection_id) {
+        let (info, reason) = close::local_close_info(error_code.into_inner(), reason);
+        events.push_closed(info, reason);
+    }
+
     let runtime = get_runtime();
 
     // Close connection within runtime context
-    let _ = runtime.block_on(async {
-        connection.close(VarInt::from_u32(0), b"");
+    runtime.block_on(async {
+        connection.close(error_code, reason);
         endpoint.wait_idle().await;
     });
 
@@ -790,6 +1429,13 @@ pub extern "C" fn moq_quic_close(connection_id: u64) -> i32 {
 pub extern "C" fn moq_quic_cleanup() {
     let runtime = get_runtime();
 
+    // Abandon connects that are still in flight
+    let pending_connects = PENDING_CONNECTS.get().expect("Pending connects not initialized");
+    for entry in pending_connects.iter() {
+        entry.value().cancel();
+    }
+    pending_connects.clear();
+
     // Collect all connections and endpoints to close
     let connections_to_close: Vec<_> = {
         let connections = CONNECTIONS.get().expect("Connection registry not initialized");
@@ -802,7 +1448,7 @@ pub extern "C" fn moq_quic_cleanup() {
     };
 
     // Close all connections within runtime context
-    let _ = runtime.block_on(async {
+    runtime.block_on(async {
         for (_id, connection) in connections_to_close {
             runtime.spawn(async move {
                 connection.close(VarInt::from_u32(0), b"");
@@ -835,12 +1481,38 @@ pub extern "C" fn moq_quic_cleanup() {
     let data_stream_buffers = DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
     data_stream_buffers.clear();
 
+    let data_stream_states = DATA_STREAM_STATES.get().expect("Data stream states not initialized");
+    data_stream_states.clear();
+
     let active_data_streams = ACTIVE_DATA_STREAMS.get().expect("Active data streams not initialized");
     active_data_streams.clear();
 
+    let bidi_recv_streams = BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized");
+    bidi_recv_streams.clear();
+
+    let pending_bidi_streams = PENDING_BIDI_STREAMS.get().expect("Pending bidirectional streams not initialized");
+    pending_bidi_streams.clear();
+
+    let stream_handles = STREAM_HANDLES.get().expect("Stream handles not initialized");
+    stream_handles.clear();
+
+    let connection_options = CONNECTION_OPTIONS.get().expect("Connection options not initialized");
+    connection_options.clear();
+    let connect_attempts = CONNECT_ATTEMPTS.get().expect("Connect attempts not initialized");
+    connect_attempts.clear();
+
     let datagram_buffers = DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
     datagram_buffers.clear();
 
+    let connection_stats = CONNECTION_STATS.get().expect("Connection statistics not initialized");
+    connection_stats.clear();
+
+    let event_queues = EVENT_QUEUES.get().expect("Event queues not initialized");
+    event_queues.clear();
+
+    let receive_callbacks = RECEIVE_CALLBACKS.get().expect("Receive callbacks not initialized");
+    receive_callbacks.clear();
+
     log::info!("MoQ QUIC transport cleanup complete");
 }
 
@@ -852,6 +1524,7 @@ pub extern "C" fn moq_quic_cleanup() {
 ///
 /// # Returns
 /// * Number of bytes written to buffer on success, 0 if no error
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_quic_get_last_error(
     buffer: *mut u8,
@@ -889,6 +1562,7 @@ pub extern "C" fn moq_quic_get_last_error(
 ///
 /// # Returns
 /// * Number of bytes sent on success, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_quic_send_data(
     connection_id: u64,
@@ -943,9 +1617,6 @@ pub extern "C" fn moq_quic_send_data(
     result
 }
 
-// Counter for stream IDs within a connection
-static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
-
 /// Open a persistent unidirectional stream for subgroup data
 ///
 /// For MoQ publishing, each subgroup gets its own unidirectional stream.
@@ -961,13 +1632,39 @@ static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
 pub extern "C" fn moq_quic_open_stream(
     connection_id: u64,
     out_stream_id: *mut u64,
+) -> i32 {
+    moq_quic_open_stream_with_priority(connection_id, 0, out_stream_id)
+}
+
+/// Open a persistent unidirectional stream with a send priority
+///
+/// When the connection is congestion or flow control limited, pending data
+/// on streams with a higher priority is sent first; streams with equal
+/// priority share bandwidth round-robin. Map MoQ publisher priority and
+/// group order onto this value (e.g. audio above video, newer groups above
+/// older ones).
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `priority` - Send priority, higher is sent first (0 is the default)
+/// * `out_stream_id` - Output parameter for the stream ID
+///
+/// # Returns
+/// * 0 on success, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_open_stream_with_priority(
+    connection_id: u64,
+    priority: i32,
+    out_stream_id: *mut u64,
 ) -> i32 {
     let connections = CONNECTIONS.get().expect("Connection registry not initialized");
     let stream_writers = STREAM_WRITERS.get().expect("Stream writers not initialized");
+    let stream_handles = STREAM_HANDLES.get().expect("Stream handles not initialized");
 
-    let connection = match connections.get(&connection_id) {
-        Some(conn) => conn.clone(),
-        None => {
+    let (connection, handles) = match (connections.get(&connection_id), stream_handles.get(&connection_id)) {
+        (Some(conn), Some(handles)) => (conn.clone(), handles.clone()),
+        _ => {
             log::error!("Connection {} not found for open_stream", connection_id);
             return -1;
         }
@@ -978,7 +1675,9 @@ pub extern "C" fn moq_quic_open_stream(
     let result = runtime.block_on(async {
         match connection.open_uni().await {
             Ok(send_stream) => {
-                let stream_id = NEXT_STREAM_ID.fetch_add(1, Ordering::SeqCst);
+                let stream_id = handles.allocate(send_stream.id(), true);
+                // Only fails once the stream is closed, which a new stream is not
+                let _ = send_stream.set_priority(priority);
 
                 // Create a persistent stream writer
                 let writer = Arc::new(stream_writer::StreamWriter::new(
@@ -988,7 +1687,8 @@ pub extern "C" fn moq_quic_open_stream(
                     128, // Channel capacity for buffered writes
                 ));
 
-                stream_writers.insert((connection_id, stream_id), writer);
+                stream_writers.insert((connection_id, stream_id), writer.clone());
+                watch_stream_writer(connection_id, stream_id, writer);
 
                 log::debug!("Opened unidirectional stream {} for connection {}", stream_id, connection_id);
                 Ok(stream_id)
@@ -1009,8 +1709,39 @@ pub extern "C" fn moq_quic_open_stream(
     }
 }
 
+/// Drop a stream writer from the registry once it can no longer be used,
+/// reporting STOP_SENDING from the peer as an event
+fn watch_stream_writer(connection_id: u64, stream_id: u64, writer: Arc<stream_writer::StreamWriter>) {
+    let events = EVENT_QUEUES.get().expect("Event queues not initialized")
+        .get(&connection_id)
+        .map(|queue| queue.clone());
+    let stopped = writer.stopped();
+
+    tokio::spawn(async move {
+        tokio::select! {
+            code = stopped => {
+                if let Some(code) = code {
+                    log::info!("Peer stopped stream {} on connection {} (code {})", stream_id, connection_id, code);
+                    if let Some(events) = &events {
+                        events.push_stream(MOQ_EVENT_STREAM_STOPPED, stream_id, code.into_inner());
+                    }
+                }
+            }
+            // Finished, reset, or a write failed
+            _ = writer.closed() => {}
+        }
+        if let Some(stream_writers) = STREAM_WRITERS.get() {
+            stream_writers.remove(&(connection_id, stream_id));
+        }
+    });
+}
+
 /// Write data to an open stream
 ///
+/// Writes are queued and sent in the background. If the peer sends
+/// STOP_SENDING (reported as `MOQ_EVENT_STREAM_STOPPED`) or a write fails,
+/// the stream is dropped and later writes return -1.
+///
 /// # Arguments
 /// * `connection_id` - The connection ID
 /// * `stream_id` - The stream ID from moq_quic_open_stream
@@ -1019,6 +1750,7 @@ pub extern "C" fn moq_quic_open_stream(
 ///
 /// # Returns
 /// * Number of bytes queued on success, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_quic_stream_write(
     connection_id: u64,
@@ -1051,6 +1783,100 @@ pub extern "C" fn moq_quic_stream_write(
     }
 }
 
+/// Change the send priority of an open stream
+///
+/// Applies immediately, including to data already queued on the stream.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `stream_id` - The stream ID from moq_quic_open_stream
+/// * `priority` - Send priority, higher is sent first
+///
+/// # Returns
+/// * 0 on success
+/// * -1 if the stream is not found
+/// * -2 if the stream is already closed
+#[no_mangle]
+pub extern "C" fn moq_quic_stream_set_priority(
+    connection_id: u64,
+    stream_id: u64,
+    priority: i32,
+) -> i32 {
+    let stream_writers = STREAM_WRITERS.get().expect("Stream writers not initialized");
+
+    let writer = match stream_writers.get(&(connection_id, stream_id)) {
+        Some(w) => w.clone(),
+        None => {
+            log::error!("Stream {} not found for connection {}", stream_id, connection_id);
+            return -1;
+        }
+    };
+
+    match writer.set_priority(priority) {
+        Ok(()) => {
+            log::debug!("Set priority of stream {} on connection {} to {}", stream_id, connection_id, priority);
+            0
+        }
+        Err(e) => {
+            log::warn!("Failed to set priority of stream {}: {:?}", stream_id, e);
+            -2
+        }
+    }
+}
+
+/// Abandon an open stream with RESET_STREAM
+///
+/// Data not yet sent, including writes still queued, is discarded and the
+/// peer sees the stream end with `error_code`. Use this instead of
+/// `moq_quic_stream_finish` when a subscription ends or a delivery timeout
+/// fires mid-subgroup.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `stream_id` - The stream ID from moq_quic_open_stream
+/// * `error_code` - Application error code (up to 2^62 - 1)
+///
+/// # Returns
+/// * 0 on success
+/// * -1 if the stream is not found
+/// * -2 if the error code is out of range
+/// * -3 if the stream was already closed
+#[no_mangle]
+pub extern "C" fn moq_quic_stream_reset(
+    connection_id: u64,
+    stream_id: u64,
+    error_code: u64,
+) -> i32 {
+    let stream_writers = STREAM_WRITERS.get().expect("Stream writers not initialized");
+
+    let error_code = match VarInt::from_u64(error_code) {
+        Ok(code) => code,
+        Err(_) => {
+            log::error!("Reset code {} out of range for stream {}", error_code, stream_id);
+            return -2;
+        }
+    };
+
+    let writer = match stream_writers.remove(&(connection_id, stream_id)) {
+        Some((_, w)) => w,
+        None => {
+            log::warn!("Stream {} not found for reset on connection {}", stream_id, connection_id);
+            return -1;
+        }
+    };
+
+    match writer.reset(error_code) {
+        Ok(()) => {
+            log::debug!("Reset stream {} for connection {} (code {})", stream_id, connection_id, error_code);
+            0
+        }
+        Err(e) => {
+            log::warn!("Failed to reset stream {}: {:?}", stream_id, e);
+            -3
+        }
+    }
+}
+
 /// Finish/close an open stream
 ///
 /// # Arguments
@@ -1096,6 +1922,7 @@ pub extern "C" fn moq_quic_stream_finish(
 ///
 /// # Returns
 /// * Number of stream IDs written on success, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_quic_get_data_streams(
     connection_id: u64,
@@ -1145,6 +1972,7 @@ pub extern "C" fn moq_quic_get_data_streams(
 ///
 /// # Returns
 /// * Number of bytes received on success, 0 if no data available, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_quic_recv_data(
     connection_id: u64,
@@ -1183,134 +2011,611 @@ pub extern "C" fn moq_quic_recv_data(
     result
 }
 
-/// Close and clean up a data stream
+/// Look at buffered control stream data without copying it
 ///
-/// Call this after the stream has been fully processed to free resources.
+/// Exposes the oldest contiguous chunk of received data; more data may be
+/// buffered behind it, so peek again after consuming. The pointer is borrowed
+/// from the receive buffer and becomes invalid as soon as any of these runs,
+/// on any thread:
+/// * `moq_quic_recv_consume` or `moq_quic_recv`, which release the bytes
+/// * `moq_quic_close*` or `moq_quic_cleanup`, which free the buffer
+///
+/// Copy the data out first if it is needed after one of these calls.
 ///
 /// # Arguments
 /// * `connection_id` - The connection ID
-/// * `stream_id` - The data stream ID
+/// * `out_data` - Set to the start of the chunk
+/// * `out_len` - Set to the chunk length (0 if nothing is buffered)
 ///
 /// # Returns
-/// * 0 on success, negative error code on failure
+/// * Chunk length, or -1 if the connection is not found or an output pointer is null
 #[no_mangle]
-pub extern "C" fn moq_quic_close_data_stream(
+pub extern "C" fn moq_quic_recv_peek(
     connection_id: u64,
-    stream_id: u64,
-) -> i32 {
-    let data_stream_buffers = DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
-    let active_streams = ACTIVE_DATA_STREAMS.get().expect("Active data streams not initialized");
+    out_data: *mut *const u8,
+    out_len: *mut usize,
+) -> i64 {
+    if out_data.is_null() || out_len.is_null() {
+        return -1;
+    }
 
-    // Remove the buffer
-    data_stream_buffers.remove(&(connection_id, stream_id));
+    let recv_buffers = RECV_BUFFERS.get().expect("Receive buffers not initialized");
 
-    // Remove from active streams list
-    if let Some(streams_list) = active_streams.get(&connection_id) {
-        let runtime = get_runtime();
-        runtime.block_on(async {
-            let mut list = streams_list.lock().await;
-            list.retain(|&id| id != stream_id);
-        });
-    }
+    let recv_buffer = match recv_buffers.get(&connection_id) {
+        Some(rb) => rb.clone(),
+        None => {
+            log::error!("Connection {} not found for recv_peek", connection_id);
+            return -1;
+        }
+    };
 
-    log::debug!("Closed data stream {} for connection {}", stream_id, connection_id);
-    0
+    receive_buffer::peek_into(&recv_buffer, out_data, out_len)
 }
 
-/// Send a datagram (unreliable, unordered)
-///
-/// Datagrams are used for low-latency data that doesn't require
-/// reliable delivery (e.g., audio frames in MoQ).
-///
-/// # Arguments
-/// * `connection_id` - The connection ID
-/// * `data` - Pointer to data to send
-/// * `len` - Length of data
+/// Release control stream data exposed by `moq_quic_recv_peek`
 ///
 /// # Returns
-/// * Number of bytes sent on success, negative error code on failure
+/// * Number of bytes discarded, or -1 if the connection is not found
 #[no_mangle]
-pub extern "C" fn moq_quic_send_datagram(
-    connection_id: u64,
-    data: *const u8,
-    len: usize,
-) -> i64 {
-    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+pub extern "C" fn moq_quic_recv_consume(connection_id: u64, len: usize) -> i64 {
+    let recv_buffers = RECV_BUFFERS.get().expect("Receive buffers not initialized");
 
-    let connection = match connections.get(&connection_id) {
-        Some(conn) => conn.clone(),
+    let recv_buffer = match recv_buffers.get(&connection_id) {
+        Some(rb) => rb.clone(),
         None => {
-            log::error!("Connection {} not found for send_datagram", connection_id);
+            log::error!("Connection {} not found for recv_consume", connection_id);
             return -1;
         }
     };
 
-    let data_bytes = unsafe { slice::from_raw_parts(data, len) };
-
-    match connection.send_datagram(bytes::Bytes::copy_from_slice(data_bytes)) {
-        Ok(()) => {
-            log::trace!("Sent datagram ({} bytes) on connection {}", len, connection_id);
-            len as i64
-        }
-        Err(e) => {
-            log::error!("Failed to send datagram: {:?}", e);
-            -2
-        }
-    }
+    let consumed = recv_buffer.blocking_lock().consume(len);
+    consumed as i64
 }
 
-/// Receive a datagram (non-blocking poll)
-///
-/// Returns the next complete datagram from the buffer, if available.
+/// Look at buffered data stream data without copying it
 ///
-/// # Arguments
-/// * `connection_id` - The connection ID
-/// * `buffer` - Pointer to buffer to store received datagram
-/// * `buffer_len` - Length of buffer
+/// Same contract as `moq_quic_recv_peek`, for one incoming data stream: the
+/// pointer is invalidated by `moq_quic_data_consume`, `moq_quic_recv_data`,
+/// `moq_quic_close_data_stream` (which frees the stream's buffer),
+/// `moq_quic_close*` and `moq_quic_cleanup`.
 ///
 /// # Returns
-/// * Number of bytes received on success, 0 if no datagram available, negative error code on failure
+/// * Chunk length, or -1 if the stream is not found or an output pointer is null
 #[no_mangle]
-pub extern "C" fn moq_quic_recv_datagram(
+pub extern "C" fn moq_quic_data_peek(
     connection_id: u64,
-    buffer: *mut u8,
-    buffer_len: usize,
+    stream_id: u64,
+    out_data: *mut *const u8,
+    out_len: *mut usize,
 ) -> i64 {
-    let datagram_buffers = DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
+    if out_data.is_null() || out_len.is_null() {
+        return -1;
+    }
 
-    let datagram_buffer = match datagram_buffers.get(&connection_id) {
-        Some(buf) => buf.clone(),
-        None => {
-            log::error!("Connection {} not found for recv_datagram", connection_id);
-            return -1;
-        }
-    };
+    let data_stream_buffers = DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
+
+    let stream_buffer = match data_stream_buffers.get(&(connection_id, stream_id)) {
+        Some(rb) => rb.clone(),
+        None => return -1,
+    };
+
+    receive_buffer::peek_into(&stream_buffer, out_data, out_len)
+}
+
+/// Release data stream data exposed by `moq_quic_data_peek`
+///
+/// # Returns
+/// * Number of bytes discarded, or -1 if the stream is not found
+#[no_mangle]
+pub extern "C" fn moq_quic_data_consume(connection_id: u64, stream_id: u64, len: usize) -> i64 {
+    let data_stream_buffers = DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
+
+    let stream_buffer = match data_stream_buffers.get(&(connection_id, stream_id)) {
+        Some(rb) => rb.clone(),
+        None => return -1,
+    };
+
+    let consumed = stream_buffer.blocking_lock().consume(len);
+    consumed as i64
+}
+
+/// Get the state of an incoming data stream
+///
+/// Tells a clean end of stream (FIN) apart from a reset by the peer, which
+/// carries the publisher's MoQ error code.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `stream_id` - The data stream ID
+/// * `out_error_code` - Set to the reset or stop code (0 otherwise); may be null
+///
+/// # Returns
+/// * One of the `MOQ_STREAM_STATE_*` constants, or -1 if the stream is not found
+#[no_mangle]
+pub extern "C" fn moq_quic_get_data_stream_state(
+    connection_id: u64,
+    stream_id: u64,
+    out_error_code: *mut u64,
+) -> i32 {
+    let data_stream_states = DATA_STREAM_STATES.get().expect("Data stream states not initialized");
+
+    match data_stream_states.get(&(connection_id, stream_id)) {
+        Some(state) => state.write_state(out_error_code),
+        None => -1,
+    }
+}
+
+/// Ask the peer to stop sending an incoming data stream (STOP_SENDING)
+///
+/// Use this to abandon a stream we no longer want, e.g. after skipping to a
+/// newer group. Data already buffered stays readable until
+/// `moq_quic_close_data_stream`.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `stream_id` - The data stream ID
+/// * `error_code` - Application error code (up to 2^62 - 1)
+///
+/// # Returns
+/// * 0 on success
+/// * -1 if the stream is not found
+/// * -2 if the error code is out of range
+/// * -3 if the stream already ended
+#[no_mangle]
+pub extern "C" fn moq_quic_stop_data_stream(
+    connection_id: u64,
+    stream_id: u64,
+    error_code: u64,
+) -> i32 {
+    let data_stream_states = DATA_STREAM_STATES.get().expect("Data stream states not initialized");
+
+    let error_code = match VarInt::from_u64(error_code) {
+        Ok(code) => code,
+        Err(_) => {
+            log::error!("Stop code {} out of range for data stream {}", error_code, stream_id);
+            return -2;
+        }
+    };
+
+    let state = match data_stream_states.get(&(connection_id, stream_id)) {
+        Some(state) => state.clone(),
+        None => {
+            log::warn!("Data stream {} not found for stop on connection {}", stream_id, connection_id);
+            return -1;
+        }
+    };
+
+    if state.stop(error_code) {
+        0
+    } else {
+        -3
+    }
+}
+
+/// Close and clean up a data stream
+///
+/// Call this after the stream has been fully processed to free resources.
+/// A stream that is still open is stopped with error code 0.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `stream_id` - The data stream ID
+///
+/// # Returns
+/// * 0 on success, negative error code on failure
+#[no_mangle]
+pub extern "C" fn moq_quic_close_data_stream(
+    connection_id: u64,
+    stream_id: u64,
+) -> i32 {
+    let data_stream_buffers = DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
+    let data_stream_states = DATA_STREAM_STATES.get().expect("Data stream states not initialized");
+    let active_streams = ACTIVE_DATA_STREAMS.get().expect("Active data streams not initialized");
+
+    // Remove the buffer
+    data_stream_buffers.remove(&(connection_id, stream_id));
+
+    // Stop the reader too, or it would keep filling (or stall on) the removed buffer
+    if let Some((_, state)) = data_stream_states.remove(&(connection_id, stream_id)) {
+        state.stop(VarInt::from_u32(0));
+    }
+
+    // Remove from active streams list
+    if let Some(streams_list) = active_streams.get(&connection_id) {
+        let runtime = get_runtime();
+        runtime.block_on(async {
+            let mut list = streams_list.lock().await;
+            list.retain(|&id| id != stream_id);
+        });
+    }
+
+    log::debug!("Closed data stream {} for connection {}", stream_id, connection_id);
+    0
+}
+
+/// Open an additional bidirectional stream
+///
+/// The send side is used like any outgoing stream (`moq_quic_stream_write`,
+/// `moq_quic_stream_finish`, `moq_quic_stream_reset`,
+/// `moq_quic_stream_set_priority`); replies arrive on the receive side, read
+/// with `moq_quic_bidi_recv`. The peer only learns of the stream once data
+/// has been written to it.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `out_stream_id` - Output parameter for the stream ID
+///
+/// # Returns
+/// * 0 on success, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_open_bidi_stream(
+    connection_id: u64,
+    out_stream_id: *mut u64,
+) -> i32 {
+    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+    let stream_handles = STREAM_HANDLES.get().expect("Stream handles not initialized");
+
+    let (connection, handles) = match (connections.get(&connection_id), stream_handles.get(&connection_id)) {
+        (Some(conn), Some(handles)) => (conn.clone(), handles.clone()),
+        _ => {
+            log::error!("Connection {} not found for open_bidi_stream", connection_id);
+            return -1;
+        }
+    };
+
+    let runtime = get_runtime();
+
+    let result = runtime.block_on(async {
+        match connection.open_bi().await {
+            Ok((send_stream, recv_stream)) => {
+                let stream_id = handles.allocate(send_stream.id(), true);
+                if !start_bidi_stream(connection_id, stream_id, connection, send_stream, recv_stream) {
+                    return Err(-1);
+                }
+                log::debug!("Opened bidirectional stream {} for connection {}", stream_id, connection_id);
+                Ok(stream_id)
+            }
+            Err(e) => {
+                log::error!("Failed to open bidirectional stream: {:?}", e);
+                Err(-2)
+            }
+        }
+    });
+
+    match result {
+        Ok(stream_id) => {
+            unsafe { *out_stream_id = stream_id; }
+            0
+        }
+        Err(code) => code
+    }
+}
+
+/// Take the next bidirectional stream opened by the peer (non-blocking poll)
+///
+/// Each new stream is also announced with `MOQ_EVENT_BIDI_STREAM_OPENED`.
+/// Received data is buffered from the moment the stream is accepted, so
+/// nothing is lost if this is called late.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `out_stream_id` - Output parameter for the stream ID
+///
+/// # Returns
+/// * 1 if a stream was returned, 0 if none is pending, -1 if the connection is not found
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_accept_bidi_stream(
+    connection_id: u64,
+    out_stream_id: *mut u64,
+) -> i32 {
+    let pending_bidi_streams = PENDING_BIDI_STREAMS.get().expect("Pending bidirectional streams not initialized");
+
+    let pending = match pending_bidi_streams.get(&connection_id) {
+        Some(pending) => pending.clone(),
+        None => return -1,
+    };
+
+    let next = pending.lock().unwrap().pop_front();
+    match next {
+        Some(stream_id) => {
+            unsafe { *out_stream_id = stream_id; }
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Receive data from the receive side of a bidirectional stream (non-blocking poll)
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `stream_id` - The bidirectional stream ID
+/// * `buffer` - Pointer to buffer to store received data
+/// * `buffer_len` - Length of buffer
+///
+/// # Returns
+/// * Number of bytes received on success, 0 if no data available, -1 if the stream is not found
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_bidi_recv(
+    connection_id: u64,
+    stream_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i64 {
+    let bidi_recv_streams = BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized");
+
+    let stream_buffer = match bidi_recv_streams.get(&(connection_id, stream_id)) {
+        Some(stream) => stream.buffer.clone(),
+        None => {
+            log::trace!("Bidirectional stream {} not found for connection {}", stream_id, connection_id);
+            return -1;
+        }
+    };
 
     if buffer.is_null() || buffer_len == 0 {
         return 0;
     }
 
-    let runtime = get_runtime();
+    let mut recv_buf = stream_buffer.blocking_lock();
+    let output_buf = unsafe { slice::from_raw_parts_mut(buffer, buffer_len) };
+    recv_buf.pop(output_buf) as i64
+}
+
+/// Get how the receive side of a bidirectional stream ended
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `stream_id` - The bidirectional stream ID
+/// * `out_error_code` - Set to the reset or stop code (0 otherwise); may be null
+///
+/// # Returns
+/// * One of the `MOQ_STREAM_STATE_*` constants, or -1 if the stream is not found
+#[no_mangle]
+pub extern "C" fn moq_quic_get_bidi_stream_state(
+    connection_id: u64,
+    stream_id: u64,
+    out_error_code: *mut u64,
+) -> i32 {
+    let bidi_recv_streams = BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized");
+
+    match bidi_recv_streams.get(&(connection_id, stream_id)) {
+        Some(stream) => stream.state.write_state(out_error_code),
+        None => -1,
+    }
+}
+
+/// Close and clean up the receive side of a bidirectional stream
+///
+/// A receive side that is still open is stopped with error code 0. The send
+/// side is unaffected; finish or reset it separately.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `stream_id` - The bidirectional stream ID
+///
+/// # Returns
+/// * 0 on success, -1 if the stream is not found
+#[no_mangle]
+pub extern "C" fn moq_quic_close_bidi_stream(
+    connection_id: u64,
+    stream_id: u64,
+) -> i32 {
+    let bidi_recv_streams = BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized");
+
+    match bidi_recv_streams.remove(&(connection_id, stream_id)) {
+        Some((_, stream)) => {
+            stream.state.stop(VarInt::from_u32(0));
+            log::debug!("Closed bidirectional stream {} for connection {}", stream_id, connection_id);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Send a datagram (unreliable, unordered)
+///
+/// Datagrams are used for low-latency data that doesn't require
+/// reliable delivery (e.g., audio frames in MoQ).
+///
+/// If the send buffer is full, the oldest queued datagrams are discarded to
+/// make room; this is counted in `datagram_send_overflows` of the stats. Use
+/// `moq_quic_datagram_send_buffered` to shed load before that happens, or
+/// `moq_quic_send_datagram_wait` to wait for room instead.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `data` - Pointer to data to send
+/// * `len` - Length of data
+///
+/// # Returns
+/// * Number of bytes sent on success
+/// * -1 if the connection is not found
+/// * -2 if the peer does not accept datagrams
+/// * -3 if the datagram is larger than `moq_quic_max_datagram_size`
+/// * -4 if datagrams are disabled locally
+/// * -5 if the connection was lost
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_send_datagram(
+    connection_id: u64,
+    data: *const u8,
+    len: usize,
+) -> i64 {
+    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+
+    let connection = match connections.get(&connection_id) {
+        Some(conn) => conn.clone(),
+        None => {
+            log::error!("Connection {} not found for send_datagram", connection_id);
+            return -1;
+        }
+    };
+
+    let data_bytes = unsafe { slice::from_raw_parts(data, len) };
+    let overflows = connection.datagram_send_buffer_space() < len;
+
+    match connection.send_datagram(bytes::Bytes::copy_from_slice(data_bytes)) {
+        Ok(()) => {
+            log::trace!("Sent datagram ({} bytes) on connection {}", len, connection_id);
+            if overflows {
+                log::debug!("Datagram send buffer full on connection {}, discarded older datagrams", connection_id);
+                if let Some(stats) = CONNECTION_STATS.get().and_then(|stats| stats.get(&connection_id).map(|s| s.clone())) {
+                    stats.datagram_send_overflowed();
+                }
+            }
+            len as i64
+        }
+        Err(e) => {
+            log::error!("Failed to send datagram: {:?}", e);
+            datagram::send_error_code(&e)
+        }
+    }
+}
+
+/// Send a datagram, waiting for room in the send buffer instead of
+/// discarding older datagrams
+///
+/// Blocks the calling thread while the connection is congested, so queued
+/// datagrams are sent in order and none are discarded.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `data` - Pointer to data to send
+/// * `len` - Length of data
+/// * `timeout_ms` - Give up after this long (0 waits until there is room)
+///
+/// # Returns
+/// * Number of bytes sent on success
+/// * -1 to -5 as for `moq_quic_send_datagram`
+/// * -6 if the timeout expired first; the datagram was not queued
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_send_datagram_wait(
+    connection_id: u64,
+    data: *const u8,
+    len: usize,
+    timeout_ms: u64,
+) -> i64 {
+    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+
+    let connection = match connections.get(&connection_id) {
+        Some(conn) => conn.clone(),
+        None => {
+            log::error!("Connection {} not found for send_datagram_wait", connection_id);
+            return -1;
+        }
+    };
+
+    let data_bytes = unsafe { slice::from_raw_parts(data, len) };
+    let send = connection.send_datagram_wait(bytes::Bytes::copy_from_slice(data_bytes));
+
+    let result = get_runtime().block_on(async {
+        match timeout_ms {
+            0 => Some(send.await),
+            ms => tokio::time::timeout(time::Duration::from_millis(ms), send).await.ok(),
+        }
+    });
+
+    match result {
+        Some(Ok(())) => {
+            log::trace!("Sent datagram ({} bytes) on connection {}", len, connection_id);
+            len as i64
+        }
+        Some(Err(e)) => {
+            log::error!("Failed to send datagram: {:?}", e);
+            datagram::send_error_code(&e)
+        }
+        None => {
+            log::debug!("Timed out waiting for datagram send buffer on connection {}", connection_id);
+            -6
+        }
+    }
+}
+
+/// Get how many bytes are queued in the datagram send buffer
+///
+/// Compare against the configured `datagram_send_buffer_size` (64KB by
+/// default) to decide when to stop producing datagrams.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+///
+/// # Returns
+/// * Bytes waiting to be sent, or -1 if the connection is not found
+#[no_mangle]
+pub extern "C" fn moq_quic_datagram_send_buffered(connection_id: u64) -> i64 {
+    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+    let connection_options = CONNECTION_OPTIONS.get().expect("Connection options not initialized");
 
-    let result = runtime.block_on(async {
-        let mut buf = datagram_buffer.lock().await;
+    let (connection, options) = match (connections.get(&connection_id), connection_options.get(&connection_id)) {
+        (Some(conn), Some(options)) => (conn.clone(), *options),
+        _ => return -1,
+    };
 
-        if let Some(datagram) = buf.pop_front() {
-            let copy_len = datagram.len().min(buffer_len);
-            unsafe {
-                let output = slice::from_raw_parts_mut(buffer, copy_len);
-                output.copy_from_slice(&datagram[..copy_len]);
-            }
-            if datagram.len() > buffer_len {
-                log::warn!("Datagram truncated: {} bytes available, {} bytes buffer", datagram.len(), buffer_len);
-            }
-            copy_len as i64
-        } else {
-            0
+    let capacity = datagram::send_buffer_size(options.datagram_send_buffer_size);
+    capacity.saturating_sub(connection.datagram_send_buffer_space()) as i64
+}
+
+/// Receive a datagram (non-blocking poll)
+///
+/// Returns the next complete datagram from the buffer, if available.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `buffer` - Pointer to buffer to store received datagram
+/// * `buffer_len` - Length of buffer
+///
+/// # Returns
+/// * Number of bytes received on success, 0 if no datagram available, negative error code on failure
+#[no_mangle]
+pub extern "C" fn moq_quic_recv_datagram(
+    connection_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i64 {
+    moq_quic_recv_datagram_with_timestamp(connection_id, buffer, buffer_len, std::ptr::null_mut())
+}
+
+/// Receive a datagram together with the time it arrived (non-blocking poll)
+///
+/// Timestamps are taken when the datagram is queued, on the clock read by
+/// `moq_monotonic_time_us`, so the spacing between them can be used to
+/// estimate jitter.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `buffer` - Pointer to buffer to store received datagram
+/// * `buffer_len` - Length of buffer
+/// * `out_timestamp_us` - Set to the receive time in microseconds; may be null
+///
+/// # Returns
+/// * Number of bytes received on success, 0 if no datagram available, negative error code on failure
+#[no_mangle]
+pub extern "C" fn moq_quic_recv_datagram_with_timestamp(
+    connection_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+    out_timestamp_us: *mut u64,
+) -> i64 {
+    let datagram_buffers = DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
+
+    let datagram_buffer = match datagram_buffers.get(&connection_id) {
+        Some(buf) => buf.clone(),
+        None => {
+            log::error!("Connection {} not found for recv_datagram", connection_id);
+            return -1;
         }
-    });
+    };
 
-    result
+    if buffer.is_null() || buffer_len == 0 {
+        return 0;
+    }
+
+    let mut queue = datagram_buffer.blocking_lock();
+    queue.pop_into(buffer, buffer_len, out_timestamp_us)
 }
 
 /// Query the maximum datagram size negotiated with the peer.
@@ -1337,3 +2642,318 @@ pub extern "C" fn moq_quic_max_datagram_size(
         None => 0,
     }
 }
+
+/// Query the congestion controller in use on a connection
+///
+/// # Returns
+/// * One of the `MOQ_CONGESTION_*` constants (never `MOQ_CONGESTION_DEFAULT`),
+///   -1 if the connection is not found, -2 if the controller is not recognised
+#[no_mangle]
+pub extern "C" fn moq_quic_get_congestion_controller(
+    connection_id: u64,
+) -> i32 {
+    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+
+    let connection = match connections.get(&connection_id) {
+        Some(conn) => conn.clone(),
+        None => {
+            log::error!("Connection {} not found for get_congestion_controller", connection_id);
+            return -1;
+        }
+    };
+
+    match options::congestion_controller_of(&connection) {
+        -1 => -2,
+        controller => controller,
+    }
+}
+
+/// Get the remote address a connection is using
+///
+/// With several resolved addresses this is the one that won the race.
+///
+/// # Arguments
+/// * `buffer` - Receives the address as text, e.g. `192.0.2.1:443` or `[2001:db8::1]:443`
+/// * `buffer_len` - Length of buffer
+///
+/// # Returns
+/// * Number of bytes written (truncated to fit), -1 if the connection is not found
+#[no_mangle]
+pub extern "C" fn moq_quic_get_remote_address(
+    connection_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i32 {
+    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+
+    let connection = match connections.get(&connection_id) {
+        Some(conn) => conn.clone(),
+        None => {
+            log::error!("Connection {} not found for get_remote_address", connection_id);
+            return -1;
+        }
+    };
+
+    let addr = happy_eyeballs::canonical_address(connection.remote_address());
+    ffi_text::copy_text(&addr.to_string(), buffer, buffer_len)
+}
+
+/// Get the addresses tried while establishing a connection
+///
+/// One line per attempt in the order they were started, formatted as
+/// `address: outcome` where the outcome is `connected`, `abandoned` (lost
+/// the race) or the error that attempt failed with.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `buffer` - Pointer to buffer to store the report
+/// * `buffer_len` - Length of buffer
+///
+/// # Returns
+/// * Number of bytes written (truncated to fit), -1 if the connection is not found
+#[no_mangle]
+pub extern "C" fn moq_quic_get_connect_attempts(
+    connection_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i32 {
+    let connect_attempts = CONNECT_ATTEMPTS.get().expect("Connect attempts not initialized");
+
+    match connect_attempts.get(&connection_id) {
+        Some(attempts) => ffi_text::copy_text(&attempts, buffer, buffer_len),
+        None => -1,
+    }
+}
+
+/// Look up what a stream handle refers to
+///
+/// Stream handles are allocated per connection for streams in both
+/// directions, so a handle is only meaningful together with its connection.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `stream_id` - A stream handle from any open, accept or data stream call
+/// * `out_info` - Output parameter for the stream's direction, initiator and QUIC stream ID
+///
+/// # Returns
+/// * 0 on success, -1 if the stream is not found or `out_info` is null
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_get_stream_info(
+    connection_id: u64,
+    stream_id: u64,
+    out_info: *mut MoqStreamInfo,
+) -> i32 {
+    if out_info.is_null() {
+        return -1;
+    }
+
+    let stream_handles = STREAM_HANDLES.get().expect("Stream handles not initialized");
+
+    let info = match stream_handles.get(&connection_id).and_then(|handles| handles.get(stream_id)) {
+        Some(info) => info,
+        None => {
+            log::warn!("Stream {} not found for get_stream_info on connection {}", stream_id, connection_id);
+            return -1;
+        }
+    };
+
+    unsafe {
+        *out_info = info;
+    }
+    0
+}
+
+/// Get transport statistics for a connection
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `out_stats` - Output parameter for the statistics snapshot
+///
+/// # Returns
+/// * 0 on success, -1 if the connection is not found or `out_stats` is null
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_quic_get_stats(
+    connection_id: u64,
+    out_stats: *mut MoqConnectionStats,
+) -> i32 {
+    if out_stats.is_null() {
+        return -1;
+    }
+
+    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+    let connection_stats = CONNECTION_STATS.get().expect("Connection statistics not initialized");
+
+    let (connection, tracker) = match (connections.get(&connection_id), connection_stats.get(&connection_id)) {
+        (Some(conn), Some(tracker)) => (conn.clone(), tracker.clone()),
+        _ => {
+            log::error!("Connection {} not found for get_stats", connection_id);
+            return -1;
+        }
+    };
+
+    unsafe {
+        *out_stats = tracker.collect(&connection);
+    }
+    0
+}
+
+/// Get why a connection was closed
+///
+/// After `moq_quic_close`, this reports the code and reason that were sent
+/// until the event queue is drained or released. A close caused by a TLS
+/// alert (e.g. a rejected client certificate) has the alert described in its
+/// reason phrase.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `out_info` - Output parameter for the close source, kind, code and reason length
+/// * `reason_buf` - Buffer for the reason phrase (may be null; truncated to fit)
+/// * `reason_buf_len` - Length of `reason_buf`
+///
+/// # Returns
+/// * 1 if the connection is closed, 0 if it is still open,
+///   -1 if the connection is not found or `out_info` is null
+#[no_mangle]
+pub extern "C" fn moq_quic_get_close_reason(
+    connection_id: u64,
+    out_info: *mut MoqCloseInfo,
+    reason_buf: *mut u8,
+    reason_buf_len: usize,
+) -> i32 {
+    if out_info.is_null() {
+        return -1;
+    }
+
+    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+
+    let connection = match connections.get(&connection_id) {
+        Some(conn) => conn.clone(),
+        None => {
+            // Closed locally; the event queue remembers what was sent
+            let event_queues = EVENT_QUEUES.get().expect("Event queues not initialized");
+            return match event_queues.get(&connection_id).and_then(|events| events.close_info()) {
+                Some((info, reason)) => close::write_close_info(info, &reason, out_info, reason_buf, reason_buf_len),
+                None => {
+                    log::error!("Connection {} not found for get_close_reason", connection_id);
+                    -1
+                }
+            };
+        }
+    };
+
+    match connection.close_reason() {
+        Some(error) => {
+            let (info, reason) = close::close_info(&error);
+            close::write_close_info(info, &reason, out_info, reason_buf, reason_buf_len)
+        }
+        None => 0,
+    }
+}
+
+/// Take the next lifecycle event for a connection
+///
+/// Events are queued by the connection's background tasks in the order they
+/// happen. Call repeatedly until it returns 0 to drain the queue. After
+/// `moq_quic_close`, the queue is released once its last event (the close) is
+/// taken, and later calls return -1.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `out_event` - Output parameter for the event
+/// * `reason_buf` - Buffer for the close reason phrase of `MOQ_EVENT_CONNECTION_CLOSED`
+///   (may be null; truncated to fit, full length in `close.reason_len`)
+/// * `reason_buf_len` - Length of `reason_buf`
+///
+/// # Returns
+/// * 1 if an event was written, 0 if there are none,
+///   -1 if the connection is not found or `out_event` is null
+#[no_mangle]
+pub extern "C" fn moq_quic_poll_event(
+    connection_id: u64,
+    out_event: *mut MoqEvent,
+    reason_buf: *mut u8,
+    reason_buf_len: usize,
+) -> i32 {
+    if out_event.is_null() {
+        return -1;
+    }
+
+    let event_queues = EVENT_QUEUES.get().expect("Event queues not initialized");
+
+    let events = match event_queues.get(&connection_id) {
+        Some(queue) => queue.clone(),
+        None => return -1,
+    };
+
+    let result = events.pop_into(out_event, reason_buf, reason_buf_len);
+    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+    if events.drained_after_close() && !connections.contains_key(&connection_id) {
+        event_queues.remove(&connection_id);
+    }
+    result
+}
+
+/// Release the event queue a closed connection kept for the application
+///
+/// Only needed when the queue is not drained with `moq_quic_poll_event`.
+///
+/// # Returns
+/// * 0 on success, -1 if nothing is kept for the ID or the connection is still open
+#[no_mangle]
+pub extern "C" fn moq_quic_release(connection_id: u64) -> i32 {
+    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+    if connections.contains_key(&connection_id) {
+        return -1;
+    }
+
+    let event_queues = EVENT_QUEUES.get().expect("Event queues not initialized");
+    match event_queues.remove(&connection_id) {
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+/// Switch a connection between polled and pushed delivery of received data
+///
+/// With a callback registered, control bytes, data stream chunks and
+/// datagrams are passed to it as they arrive and no longer reach
+/// `moq_quic_recv`, `moq_quic_recv_data` or `moq_quic_recv_datagram`. Data
+/// buffered before registration stays available to those calls. Passing a
+/// null callback returns to polling.
+///
+/// The `MOQ_OVERFLOW_*` policies only govern the receive buffers, so readers
+/// never stall while a callback is registered. A reader already stalled on a
+/// full buffer delivers to the callback once that buffer is drained.
+///
+/// # Arguments
+/// * `connection_id` - The connection ID
+/// * `callback` - Receives each buffer, which it then owns (see `moq_buffer_free`)
+/// * `user_data` - Passed back to every callback invocation; must be usable from any thread
+///
+/// # Returns
+/// * 0 on success, -1 if the connection is not found
+#[no_mangle]
+pub extern "C" fn moq_quic_set_receive_callback(
+    connection_id: u64,
+    callback: Option<MoqReceiveCallback>,
+    user_data: *mut std::ffi::c_void,
+) -> i32 {
+    let connections = CONNECTIONS.get().expect("Connection registry not initialized");
+    if !connections.contains_key(&connection_id) {
+        log::error!("Connection {} not found for set_receive_callback", connection_id);
+        return -1;
+    }
+
+    let receive_callbacks = RECEIVE_CALLBACKS.get().expect("Receive callbacks not initialized");
+    match callback {
+        Some(callback) => {
+            receive_callbacks.insert(connection_id, Arc::new(push::FfiReceiveCallback::new(callback, user_data)));
+        }
+        None => {
+            receive_callbacks.remove(&connection_id);
+        }
+    }
+    0
+}
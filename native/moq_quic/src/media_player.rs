@@ -12,32 +12,31 @@
 
 use libmpv2_sys::*;
 use parking_lot::{Mutex, Condvar};
-use std::collections::VecDeque;
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_void, c_char, c_int};
 use std::ptr;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicI32, Ordering};
 
+use crate::receive_buffer::ReceiveBuffer;
+
 /// Ring buffer for streaming media data
 pub struct MediaBuffer {
-    data: Mutex<VecDeque<u8>>,
+    data: Mutex<ReceiveBuffer>,
     condvar: Condvar,
     eof: AtomicBool,
     total_written: AtomicU64,
     total_read: AtomicU64,
-    max_size: usize,
 }
 
 impl MediaBuffer {
     pub fn new(max_size: usize) -> Self {
         Self {
-            data: Mutex::new(VecDeque::with_capacity(max_size)),
+            data: Mutex::new(ReceiveBuffer::new(max_size)),
             condvar: Condvar::new(),
             eof: AtomicBool::new(false),
             total_written: AtomicU64::new(0),
             total_read: AtomicU64::new(0),
-            max_size,
         }
     }
 
@@ -45,12 +44,7 @@ impl MediaBuffer {
     /// Returns number of bytes written (may be less than requested if buffer is full)
     pub fn write(&self, data: &[u8]) -> usize {
         let mut buffer = self.data.lock();
-        let available = self.max_size.saturating_sub(buffer.len());
-        let to_write = data.len().min(available);
-
-        for &byte in &data[..to_write] {
-            buffer.push_back(byte);
-        }
+        let to_write = buffer.push(data);
 
         self.total_written.fetch_add(to_write as u64, Ordering::Relaxed);
 
@@ -83,10 +77,7 @@ impl MediaBuffer {
         }
 
         // Read available data
-        let to_read = buf.len().min(buffer.len());
-        for i in 0..to_read {
-            buf[i] = buffer.pop_front().unwrap();
-        }
+        let to_read = buffer.pop(buf);
 
         self.total_read.fetch_add(to_read as u64, Ordering::Relaxed);
 
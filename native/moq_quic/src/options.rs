@@ -0,0 +1,334 @@
+// Connection options for the QUIC and WebTransport connect paths
+//
+// Every field uses 0 to mean "unset", in which case the transport keeps the
+// default it has always used. This lets Dart zero-initialise the struct and
+// only fill in the values it cares about.
+
+use quinn::congestion::{Bbr, BbrConfig, Cubic, CubicConfig, NewReno, NewRenoConfig};
+use quinn::{Connection, TransportConfig, VarInt};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::receive_buffer;
+
+/// Use the transport's default congestion controller (Cubic)
+pub const MOQ_CONGESTION_DEFAULT: u32 = 0;
+/// CUBIC (RFC 9438)
+pub const MOQ_CONGESTION_CUBIC: u32 = 1;
+/// NewReno (RFC 9002)
+pub const MOQ_CONGESTION_NEW_RENO: u32 = 2;
+/// BBR (experimental in quinn)
+pub const MOQ_CONGESTION_BBR: u32 = 3;
+
+/// Stop reading from the stream while its buffer is full, so QUIC flow
+/// control pushes back on the sender (default)
+///
+/// Neither policy applies while a receive callback is registered, since data
+/// then bypasses the buffers.
+pub const MOQ_OVERFLOW_STALL: u32 = 0;
+/// Discard data that does not fit in the buffer (corrupts stream framing)
+pub const MOQ_OVERFLOW_DROP: u32 = 1;
+
+/// Discard newly received datagrams while the queue is full (default)
+pub const MOQ_DATAGRAM_DROP_NEWEST: u32 = 0;
+/// Discard the oldest queued datagram to make room for a new one
+pub const MOQ_DATAGRAM_DROP_OLDEST: u32 = 1;
+
+/// Transport parameters passed to `moq_quic_connect_with_options` and
+/// `moq_webtransport_connect_with_options`
+///
+/// All fields are optional: a value of 0 keeps the built-in default.
+/// `idle_timeout_ms` and `keep_alive_interval_ms` additionally accept
+/// `u64::MAX` to disable the idle timeout / keep-alive entirely.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MoqConnectOptions {
+    /// Maximum idle time before the connection is closed (milliseconds)
+    pub idle_timeout_ms: u64,
+    /// Interval between keep-alive PINGs (milliseconds)
+    pub keep_alive_interval_ms: u64,
+    /// Maximum number of concurrent peer-initiated bidirectional streams
+    pub max_concurrent_bidi_streams: u32,
+    /// Maximum number of concurrent peer-initiated unidirectional streams
+    pub max_concurrent_uni_streams: u32,
+    /// Initial RTT estimate before the first sample (milliseconds)
+    pub initial_rtt_ms: u64,
+    /// Per-stream flow control receive window (bytes)
+    pub stream_receive_window: u64,
+    /// Connection-wide flow control receive window (bytes)
+    pub receive_window: u64,
+    /// Buffer for incoming datagrams not yet read by the application (bytes)
+    pub datagram_receive_buffer_size: u64,
+    /// Buffer for outgoing datagrams not yet sent (bytes)
+    pub datagram_send_buffer_size: u64,
+    /// Cap on buffered control stream data waiting for `recv` (bytes, 2MB when unset)
+    pub control_recv_buffer_size: u64,
+    /// Cap on buffered data per incoming data stream (bytes, 2MB when unset)
+    pub data_recv_buffer_size: u64,
+    /// Congestion controller, one of the `MOQ_CONGESTION_*` constants
+    pub congestion_controller: u32,
+    /// TLS config from `moq_tls_config_new` (0 verifies against the system roots)
+    pub tls_config_id: u64,
+    /// What the control stream reader does when its buffer is full, one of `MOQ_OVERFLOW_*`
+    pub control_overflow_policy: u32,
+    /// What data stream readers do when their buffer is full, one of `MOQ_OVERFLOW_*`
+    pub data_overflow_policy: u32,
+    /// Cap on received datagrams waiting for `recv_datagram` (datagrams)
+    pub datagram_queue_capacity: u32,
+    /// Which datagram is discarded when the queue is full, one of `MOQ_DATAGRAM_DROP_*`
+    pub datagram_overflow_policy: u32,
+}
+
+impl MoqConnectOptions {
+    /// Read options from an FFI pointer, falling back to defaults when null
+    pub(crate) fn from_ptr(options: *const MoqConnectOptions) -> Self {
+        if options.is_null() {
+            Self::default()
+        } else {
+            unsafe { *options }
+        }
+    }
+
+    /// Override the transport settings that were explicitly set
+    ///
+    /// The caller configures its own defaults first, so unset fields keep them.
+    pub(crate) fn apply(&self, transport: &mut TransportConfig) -> Result<(), String> {
+        match self.idle_timeout_ms {
+            0 => {}
+            u64::MAX => {
+                transport.max_idle_timeout(None);
+            }
+            ms => {
+                let timeout = Duration::from_millis(ms)
+                    .try_into()
+                    .map_err(|e| format!("Invalid idle timeout {} ms: {:?}", ms, e))?;
+                transport.max_idle_timeout(Some(timeout));
+            }
+        }
+        match self.keep_alive_interval_ms {
+            0 => {}
+            u64::MAX => {
+                transport.keep_alive_interval(None);
+            }
+            ms => {
+                transport.keep_alive_interval(Some(Duration::from_millis(ms)));
+            }
+        }
+        if self.max_concurrent_bidi_streams != 0 {
+            transport.max_concurrent_bidi_streams(self.max_concurrent_bidi_streams.into());
+        }
+        if self.max_concurrent_uni_streams != 0 {
+            transport.max_concurrent_uni_streams(self.max_concurrent_uni_streams.into());
+        }
+        if self.initial_rtt_ms != 0 {
+            transport.initial_rtt(Duration::from_millis(self.initial_rtt_ms));
+        }
+        if self.stream_receive_window != 0 {
+            transport.stream_receive_window(varint(self.stream_receive_window, "stream receive window")?);
+        }
+        if self.receive_window != 0 {
+            transport.receive_window(varint(self.receive_window, "receive window")?);
+        }
+        if self.datagram_receive_buffer_size != 0 {
+            transport.datagram_receive_buffer_size(Some(self.datagram_receive_buffer_size as usize));
+        }
+        if self.datagram_send_buffer_size != 0 {
+            transport.datagram_send_buffer_size(self.datagram_send_buffer_size as usize);
+        }
+        match self.congestion_controller {
+            MOQ_CONGESTION_DEFAULT => {}
+            MOQ_CONGESTION_CUBIC => {
+                transport.congestion_controller_factory(Arc::new(CubicConfig::default()));
+            }
+            MOQ_CONGESTION_NEW_RENO => {
+                transport.congestion_controller_factory(Arc::new(NewRenoConfig::default()));
+            }
+            MOQ_CONGESTION_BBR => {
+                transport.congestion_controller_factory(Arc::new(BbrConfig::default()));
+            }
+            other => return Err(format!("Unknown congestion controller {}", other)),
+        }
+        for policy in [self.control_overflow_policy, self.data_overflow_policy] {
+            if policy != MOQ_OVERFLOW_STALL && policy != MOQ_OVERFLOW_DROP {
+                return Err(format!("Unknown overflow policy {}", policy));
+            }
+        }
+        if self.datagram_overflow_policy != MOQ_DATAGRAM_DROP_NEWEST
+            && self.datagram_overflow_policy != MOQ_DATAGRAM_DROP_OLDEST
+        {
+            return Err(format!("Unknown datagram overflow policy {}", self.datagram_overflow_policy));
+        }
+        Ok(())
+    }
+
+    /// Control stream receive buffer cap, shared default when unset
+    pub(crate) fn control_recv_buffer_size(&self) -> usize {
+        match self.control_recv_buffer_size {
+            0 => receive_buffer::DEFAULT_MAX_SIZE,
+            size => size as usize,
+        }
+    }
+
+    /// Whether the control stream reader waits for buffer space instead of dropping
+    pub(crate) fn control_stalls_when_full(&self) -> bool {
+        self.control_overflow_policy == MOQ_OVERFLOW_STALL
+    }
+
+    /// Whether data stream readers wait for buffer space instead of dropping
+    pub(crate) fn data_stalls_when_full(&self) -> bool {
+        self.data_overflow_policy == MOQ_OVERFLOW_STALL
+    }
+
+    /// Per data stream receive buffer cap, shared default when unset
+    pub(crate) fn data_recv_buffer_size(&self) -> usize {
+        match self.data_recv_buffer_size {
+            0 => receive_buffer::DEFAULT_MAX_SIZE,
+            size => size as usize,
+        }
+    }
+
+    /// Received datagram queue cap, or `default` when unset
+    pub(crate) fn datagram_queue_capacity(&self, default: usize) -> usize {
+        match self.datagram_queue_capacity {
+            0 => default,
+            capacity => capacity as usize,
+        }
+    }
+
+    /// Whether a full datagram queue discards its oldest entry instead of the new datagram
+    pub(crate) fn datagram_drops_oldest(&self) -> bool {
+        self.datagram_overflow_policy == MOQ_DATAGRAM_DROP_OLDEST
+    }
+}
+
+/// Identify the congestion controller a live connection is running
+///
+/// # Returns
+/// * One of the non-default `MOQ_CONGESTION_*` constants, or -1 if unrecognised
+pub(crate) fn congestion_controller_of(connection: &Connection) -> i32 {
+    let controller = connection.congestion_state().into_any();
+    if controller.is::<Cubic>() {
+        MOQ_CONGESTION_CUBIC as i32
+    } else if controller.is::<NewReno>() {
+        MOQ_CONGESTION_NEW_RENO as i32
+    } else if controller.is::<Bbr>() {
+        MOQ_CONGESTION_BBR as i32
+    } else {
+        -1
+    }
+}
+
+fn varint(value: u64, what: &str) -> Result<VarInt, String> {
+    VarInt::from_u64(value).map_err(|_| format!("Invalid {}: {} exceeds 2^62", what, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // TransportConfig has no getters, so compare its Debug output instead
+    fn applied(options: MoqConnectOptions, mut transport: TransportConfig) -> Result<String, String> {
+        options.apply(&mut transport)?;
+        Ok(format!("{:?}", transport))
+    }
+
+    fn caller_defaults() -> TransportConfig {
+        let mut transport = TransportConfig::default();
+        transport.max_idle_timeout(Some(Duration::from_secs(30).try_into().unwrap()));
+        transport.keep_alive_interval(Some(Duration::from_secs(5)));
+        transport
+    }
+
+    #[test]
+    fn unset_fields_keep_caller_defaults() {
+        let expected = format!("{:?}", caller_defaults());
+        assert_eq!(applied(MoqConnectOptions::default(), caller_defaults()).unwrap(), expected);
+        assert_eq!(applied(MoqConnectOptions::from_ptr(std::ptr::null()), caller_defaults()).unwrap(), expected);
+    }
+
+    #[test]
+    fn set_fields_override_defaults() {
+        let options = MoqConnectOptions {
+            idle_timeout_ms: 1_000,
+            keep_alive_interval_ms: 250,
+            max_concurrent_bidi_streams: 7,
+            max_concurrent_uni_streams: 9,
+            initial_rtt_ms: 50,
+            stream_receive_window: 1 << 20,
+            receive_window: 1 << 22,
+            datagram_receive_buffer_size: 4096,
+            datagram_send_buffer_size: 8192,
+            ..Default::default()
+        };
+
+        let mut expected = caller_defaults();
+        expected.max_idle_timeout(Some(Duration::from_millis(1_000).try_into().unwrap()));
+        expected.keep_alive_interval(Some(Duration::from_millis(250)));
+        expected.max_concurrent_bidi_streams(7u32.into());
+        expected.max_concurrent_uni_streams(9u32.into());
+        expected.initial_rtt(Duration::from_millis(50));
+        expected.stream_receive_window(VarInt::from_u32(1 << 20));
+        expected.receive_window(VarInt::from_u32(1 << 22));
+        expected.datagram_receive_buffer_size(Some(4096));
+        expected.datagram_send_buffer_size(8192);
+
+        assert_eq!(applied(options, caller_defaults()).unwrap(), format!("{:?}", expected));
+    }
+
+    #[test]
+    fn max_disables_idle_timeout_and_keep_alive() {
+        let options = MoqConnectOptions {
+            idle_timeout_ms: u64::MAX,
+            keep_alive_interval_ms: u64::MAX,
+            ..Default::default()
+        };
+
+        let mut expected = caller_defaults();
+        expected.max_idle_timeout(None);
+        expected.keep_alive_interval(None);
+
+        assert_eq!(applied(options, caller_defaults()).unwrap(), format!("{:?}", expected));
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases = [
+            MoqConnectOptions { congestion_controller: 99, ..Default::default() },
+            MoqConnectOptions { control_overflow_policy: 2, ..Default::default() },
+            MoqConnectOptions { data_overflow_policy: 2, ..Default::default() },
+            MoqConnectOptions { datagram_overflow_policy: 2, ..Default::default() },
+            MoqConnectOptions { receive_window: 1 << 62, ..Default::default() },
+            MoqConnectOptions { stream_receive_window: u64::MAX - 1, ..Default::default() },
+        ];
+        for options in cases {
+            assert!(applied(options, caller_defaults()).is_err(), "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn buffer_sizes_fall_back_when_unset() {
+        let unset = MoqConnectOptions::default();
+        assert_eq!(unset.control_recv_buffer_size(), receive_buffer::DEFAULT_MAX_SIZE);
+        assert_eq!(unset.data_recv_buffer_size(), receive_buffer::DEFAULT_MAX_SIZE);
+        assert_eq!(unset.datagram_queue_capacity(300), 300);
+        assert!(unset.control_stalls_when_full());
+        assert!(unset.data_stalls_when_full());
+        assert!(!unset.datagram_drops_oldest());
+
+        let set = MoqConnectOptions {
+            control_recv_buffer_size: 1,
+            data_recv_buffer_size: 2,
+            datagram_queue_capacity: 3,
+            control_overflow_policy: MOQ_OVERFLOW_DROP,
+            data_overflow_policy: MOQ_OVERFLOW_DROP,
+            datagram_overflow_policy: MOQ_DATAGRAM_DROP_OLDEST,
+            ..Default::default()
+        };
+        assert_eq!(set.control_recv_buffer_size(), 1);
+        assert_eq!(set.data_recv_buffer_size(), 2);
+        assert_eq!(set.datagram_queue_capacity(300), 3);
+        assert!(!set.control_stalls_when_full());
+        assert!(!set.data_stalls_when_full());
+        assert!(set.datagram_drops_oldest());
+    }
+}
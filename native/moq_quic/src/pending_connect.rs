@@ -0,0 +1,155 @@
+// Non-blocking connects started with `moq_quic_connect_start` and
+// `moq_webtransport_connect_start`
+//
+// The connect runs as a task on the transport's runtime instead of inside
+// `block_on`, so the calling Dart isolate is never blocked. Dart polls the
+// shared state below until it reaches a final state.
+
+use std::sync::Mutex;
+use tokio::task::AbortHandle;
+
+/// Resolving the host name
+pub const MOQ_CONNECT_RESOLVING: i32 = 0;
+/// QUIC handshake (and, for WebTransport, the CONNECT request) in progress
+pub const MOQ_CONNECT_HANDSHAKING: i32 = 1;
+/// Connected; the connection or session ID is available
+pub const MOQ_CONNECT_CONNECTED: i32 = 2;
+/// Failed; the error code is available
+pub const MOQ_CONNECT_FAILED: i32 = 3;
+/// Cancelled before it completed
+pub const MOQ_CONNECT_CANCELLED: i32 = 4;
+
+struct State {
+    state: i32,
+    // Connection/session ID once connected, error code once failed
+    result: i64,
+    task: Option<AbortHandle>,
+    // Report of every address tried, once the handshake race is over
+    attempts: String,
+}
+
+/// Progress of one connect task, shared between the task and FFI callers
+pub(crate) struct PendingConnect {
+    state: Mutex<State>,
+}
+
+impl PendingConnect {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Mutex::new(State { state: MOQ_CONNECT_RESOLVING, result: 0, task: None, attempts: String::new() }),
+        }
+    }
+
+    /// Remember the task so `cancel` can abort it
+    pub(crate) fn set_task(&self, task: AbortHandle) {
+        self.state.lock().unwrap().task = Some(task);
+    }
+
+    /// Name resolution is done and the handshake has started
+    pub(crate) fn handshaking(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.state == MOQ_CONNECT_RESOLVING {
+            state.state = MOQ_CONNECT_HANDSHAKING;
+        }
+    }
+
+    /// Record which addresses were tried, whether or not one succeeded
+    pub(crate) fn set_attempts(&self, attempts: String) {
+        self.state.lock().unwrap().attempts = attempts;
+    }
+
+    /// Report of the addresses tried, empty until the handshake race is over
+    pub(crate) fn attempts(&self) -> String {
+        self.state.lock().unwrap().attempts.clone()
+    }
+
+    /// Publish the connection, registering it only if the connect was not cancelled
+    ///
+    /// Registration runs under the state lock so a concurrent `cancel` either
+    /// happens before it (and the caller must drop the connection) or finds
+    /// the connect already complete.
+    ///
+    /// # Returns
+    /// * `false` if the connect was cancelled and `register` was not called
+    pub(crate) fn complete(&self, register: impl FnOnce() -> u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.state == MOQ_CONNECT_CANCELLED {
+            return false;
+        }
+        state.result = register() as i64;
+        state.state = MOQ_CONNECT_CONNECTED;
+        state.task = None;
+        true
+    }
+
+    pub(crate) fn fail(&self, error_code: i32) {
+        let mut state = self.state.lock().unwrap();
+        if state.state != MOQ_CONNECT_CANCELLED {
+            state.state = MOQ_CONNECT_FAILED;
+            state.result = error_code as i64;
+        }
+        state.task = None;
+    }
+
+    /// Abort the connect if it is still in progress
+    ///
+    /// # Returns
+    /// * The state the connect was in before the call
+    pub(crate) fn cancel(&self) -> i32 {
+        let mut state = self.state.lock().unwrap();
+        let previous = state.state;
+        if previous == MOQ_CONNECT_RESOLVING || previous == MOQ_CONNECT_HANDSHAKING {
+            state.state = MOQ_CONNECT_CANCELLED;
+            if let Some(task) = state.task.take() {
+                task.abort();
+            }
+        }
+        previous
+    }
+
+    /// Current state, plus the ID (connected) or error code (failed)
+    pub(crate) fn poll(&self) -> (i32, i64) {
+        let state = self.state.lock().unwrap();
+        (state.state, state.result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progresses_to_connected() {
+        let pending = PendingConnect::new();
+        assert_eq!(pending.poll(), (MOQ_CONNECT_RESOLVING, 0));
+        pending.handshaking();
+        assert_eq!(pending.poll(), (MOQ_CONNECT_HANDSHAKING, 0));
+        assert!(pending.complete(|| 42));
+        assert_eq!(pending.poll(), (MOQ_CONNECT_CONNECTED, 42));
+        // Too late to cancel; the connection belongs to the caller
+        assert_eq!(pending.cancel(), MOQ_CONNECT_CONNECTED);
+        assert_eq!(pending.poll(), (MOQ_CONNECT_CONNECTED, 42));
+    }
+
+    #[test]
+    fn cancelled_connect_is_never_registered() {
+        let pending = PendingConnect::new();
+        pending.handshaking();
+        assert_eq!(pending.cancel(), MOQ_CONNECT_HANDSHAKING);
+        assert!(!pending.complete(|| unreachable!("registered after cancel")));
+        pending.fail(-2);
+        assert_eq!(pending.poll(), (MOQ_CONNECT_CANCELLED, 0));
+        // Resolution finishing late does not revive it either
+        pending.handshaking();
+        assert_eq!(pending.poll().0, MOQ_CONNECT_CANCELLED);
+    }
+
+    #[test]
+    fn failure_keeps_the_error_code() {
+        let pending = PendingConnect::new();
+        pending.fail(-2);
+        assert_eq!(pending.poll(), (MOQ_CONNECT_FAILED, -2));
+        assert_eq!(pending.cancel(), MOQ_CONNECT_FAILED);
+        assert_eq!(pending.poll(), (MOQ_CONNECT_FAILED, -2));
+    }
+}
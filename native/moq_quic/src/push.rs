@@ -0,0 +1,94 @@
+// Push delivery of received data through a C callback
+//
+// When a callback is registered for a connection, control bytes, data stream
+// chunks and datagrams are handed to it as they arrive instead of being
+// buffered for the recv calls. Each delivery transfers ownership of a heap
+// buffer to the callee, which releases it with `moq_buffer_free`.
+//
+// From Dart, wrap the handler in `NativeCallable.listener`: it may be called
+// from any runtime thread and the buffer stays valid until freed.
+
+use crate::stream_writer::StreamDataCallback;
+use std::ffi::c_void;
+
+/// Bytes from the bidirectional control stream (`stream_id` is 0)
+pub const MOQ_RECEIVE_CONTROL: u32 = 0;
+/// A chunk from an incoming data stream
+pub const MOQ_RECEIVE_DATA_STREAM: u32 = 1;
+/// One complete datagram (`stream_id` is 0)
+pub const MOQ_RECEIVE_DATAGRAM: u32 = 2;
+/// A chunk from the receive side of a bidirectional stream
+pub const MOQ_RECEIVE_BIDI_STREAM: u32 = 3;
+
+/// Callback receiving pushed data
+///
+/// # Arguments
+/// * `user_data` - The pointer passed when the callback was registered
+/// * `connection_id` - The connection or session ID
+/// * `kind` - One of the `MOQ_RECEIVE_*` constants
+/// * `stream_id` - The stream for `MOQ_RECEIVE_DATA_STREAM` and `MOQ_RECEIVE_BIDI_STREAM`
+/// * `data` - Buffer now owned by the callee; release with `moq_buffer_free(data, len)`
+/// * `len` - Length of `data`
+pub type MoqReceiveCallback = extern "C" fn(
+    user_data: *mut c_void,
+    connection_id: u64,
+    kind: u32,
+    stream_id: u64,
+    data: *mut u8,
+    len: usize,
+);
+
+/// A registered FFI callback and its user data
+pub(crate) struct FfiReceiveCallback {
+    callback: MoqReceiveCallback,
+    user_data: *mut c_void,
+}
+
+// The caller promises `user_data` may be used from any thread when registering
+unsafe impl Send for FfiReceiveCallback {}
+unsafe impl Sync for FfiReceiveCallback {}
+
+impl FfiReceiveCallback {
+    pub(crate) fn new(callback: MoqReceiveCallback, user_data: *mut c_void) -> Self {
+        Self { callback, user_data }
+    }
+
+    fn deliver(&self, connection_id: u64, kind: u32, stream_id: u64, data: &[u8]) {
+        let len = data.len();
+        let ptr = Box::into_raw(Box::<[u8]>::from(data)) as *mut u8;
+        (self.callback)(self.user_data, connection_id, kind, stream_id, ptr, len);
+    }
+}
+
+impl StreamDataCallback for FfiReceiveCallback {
+    fn on_stream_data(&self, session_id: u64, stream_id: u64, data: &[u8]) {
+        self.deliver(session_id, MOQ_RECEIVE_DATA_STREAM, stream_id, data);
+    }
+
+    fn on_bidi_stream_data(&self, session_id: u64, stream_id: u64, data: &[u8]) {
+        self.deliver(session_id, MOQ_RECEIVE_BIDI_STREAM, stream_id, data);
+    }
+
+    fn on_control_data(&self, session_id: u64, data: &[u8]) {
+        self.deliver(session_id, MOQ_RECEIVE_CONTROL, 0, data);
+    }
+
+    fn on_datagram(&self, session_id: u64, data: &[u8]) {
+        self.deliver(session_id, MOQ_RECEIVE_DATAGRAM, 0, data);
+    }
+}
+
+/// Release a buffer handed to a `MoqReceiveCallback`
+///
+/// # Arguments
+/// * `data` - The buffer pointer passed to the callback
+/// * `len` - The length passed alongside it
+#[no_mangle]
+pub extern "C" fn moq_buffer_free(data: *mut u8, len: usize) {
+    if data.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
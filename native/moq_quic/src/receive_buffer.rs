@@ -0,0 +1,264 @@
+// Chunked receive buffer shared by the QUIC, WebTransport and media modules
+//
+// Data is kept as the `Bytes` chunks it arrived in (usually quinn's own
+// allocations), so pushing is O(1) per chunk and reads copy whole slices
+// instead of moving one byte at a time.
+
+use bytes::{Buf, Bytes};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Buffer cap used when the connect options leave it unset (2MB)
+///
+/// Large enough for video streaming without constant flow control backpressure.
+pub(crate) const DEFAULT_MAX_SIZE: usize = 2 * 1024 * 1024;
+
+/// Largest chunk the stream readers take from quinn at a time
+pub(crate) const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// FIFO byte queue made of `Bytes` chunks with a size cap
+pub(crate) struct ReceiveBuffer {
+    chunks: VecDeque<Bytes>,
+    len: usize,
+    max_size: usize,
+    // Signalled whenever bytes are removed, for readers stalled on a full buffer
+    space: Arc<Notify>,
+}
+
+impl ReceiveBuffer {
+    pub(crate) fn new(max_size: usize) -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            len: 0,
+            max_size,
+            space: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Append a chunk without copying, truncating it to the remaining capacity
+    ///
+    /// Returns the number of bytes accepted.
+    pub(crate) fn push_bytes(&mut self, mut bytes: Bytes) -> usize {
+        let available = self.max_size.saturating_sub(self.len);
+        bytes.truncate(available);
+        let accepted = bytes.len();
+        if accepted > 0 {
+            self.len += accepted;
+            self.chunks.push_back(bytes);
+        }
+        accepted
+    }
+
+    /// Append a copy of `bytes`, truncated to the remaining capacity
+    ///
+    /// Returns the number of bytes accepted.
+    #[cfg_attr(not(feature = "media-player"), allow(dead_code))]
+    pub(crate) fn push(&mut self, bytes: &[u8]) -> usize {
+        let available = self.max_size.saturating_sub(self.len);
+        self.push_bytes(Bytes::copy_from_slice(&bytes[..bytes.len().min(available)]))
+    }
+
+    /// Copy up to `buf.len()` bytes out of the buffer
+    pub(crate) fn pop(&mut self, buf: &mut [u8]) -> usize {
+        let mut read = 0;
+        while read < buf.len() {
+            let Some(front) = self.chunks.front_mut() else { break };
+            let n = front.len().min(buf.len() - read);
+            buf[read..read + n].copy_from_slice(&front[..n]);
+            read += n;
+            self.advance_front(n);
+        }
+        read
+    }
+
+    /// The oldest contiguous run of unread bytes, without consuming it
+    ///
+    /// The slice's memory stays put until it is consumed, even if more data
+    /// is pushed in the meantime.
+    pub(crate) fn peek(&self) -> &[u8] {
+        self.chunks.front().map(|chunk| &chunk[..]).unwrap_or(&[])
+    }
+
+    /// Discard up to `n` bytes from the front
+    ///
+    /// Returns the number of bytes discarded.
+    pub(crate) fn consume(&mut self, n: usize) -> usize {
+        let mut consumed = 0;
+        while consumed < n {
+            let Some(front) = self.chunks.front() else { break };
+            let step = front.len().min(n - consumed);
+            self.advance_front(step);
+            consumed += step;
+        }
+        consumed
+    }
+
+    fn advance_front(&mut self, n: usize) {
+        if let Some(front) = self.chunks.front_mut() {
+            front.advance(n);
+            self.len -= n;
+            if front.is_empty() {
+                self.chunks.pop_front();
+            }
+            self.space.notify_waiters();
+        }
+    }
+
+    /// Bytes that can still be pushed before the buffer is full
+    pub(crate) fn available(&self) -> usize {
+        self.max_size.saturating_sub(self.len)
+    }
+
+    #[cfg_attr(not(feature = "media-player"), allow(dead_code))]
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[cfg_attr(not(feature = "media-player"), allow(dead_code))]
+    pub(crate) fn clear(&mut self) {
+        self.chunks.clear();
+        self.len = 0;
+        self.space.notify_waiters();
+    }
+}
+
+/// Wait until a shared buffer has room, for readers using the stall policy
+///
+/// # Returns
+/// * The free space in bytes, or `None` if the connection closed first
+pub(crate) async fn wait_for_space(
+    buffer: &tokio::sync::Mutex<ReceiveBuffer>,
+    connection: &quinn::Connection,
+) -> Option<usize> {
+    let space = buffer.lock().await.space.clone();
+    loop {
+        // Register before checking so a consume in between is not missed
+        let notified = space.notified();
+        let available = buffer.lock().await.available();
+        if available > 0 {
+            return Some(available);
+        }
+        tokio::select! {
+            _ = notified => {}
+            _ = connection.closed() => return None,
+        }
+    }
+}
+
+/// Expose the front chunk of a shared buffer through FFI output parameters
+///
+/// # Returns
+/// * Length of the exposed chunk, 0 if the buffer is empty
+pub(crate) fn peek_into(
+    buffer: &tokio::sync::Mutex<ReceiveBuffer>,
+    out_data: *mut *const u8,
+    out_len: *mut usize,
+) -> i64 {
+    let recv_buf = buffer.blocking_lock();
+    let chunk = recv_buf.peek();
+    unsafe {
+        *out_data = chunk.as_ptr();
+        *out_len = chunk.len();
+    }
+    chunk.len() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer_with(max_size: usize, chunks: &[&'static [u8]]) -> ReceiveBuffer {
+        let mut buffer = ReceiveBuffer::new(max_size);
+        for chunk in chunks {
+            assert_eq!(buffer.push_bytes(Bytes::from_static(chunk)), chunk.len());
+        }
+        buffer
+    }
+
+    #[test]
+    fn pop_reads_across_chunk_boundaries() {
+        let mut buffer = buffer_with(64, &[b"abc", b"de", b"fghij"]);
+        assert_eq!(buffer.len(), 10);
+
+        let mut out = [0u8; 4];
+        assert_eq!(buffer.pop(&mut out), 4);
+        assert_eq!(&out, b"abcd");
+        assert_eq!(buffer.pop(&mut out), 4);
+        assert_eq!(&out, b"efgh");
+        assert_eq!(buffer.pop(&mut out), 2);
+        assert_eq!(&out[..2], b"ij");
+        assert_eq!(buffer.pop(&mut out), 0);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn peek_exposes_the_front_chunk_only() {
+        let mut buffer = buffer_with(64, &[b"abc", b"de"]);
+        assert_eq!(buffer.peek(), b"abc");
+
+        // A partial consume leaves the rest of the chunk in front
+        assert_eq!(buffer.consume(1), 1);
+        assert_eq!(buffer.peek(), b"bc");
+
+        // Consuming past the chunk continues into the next one
+        assert_eq!(buffer.consume(3), 3);
+        assert_eq!(buffer.peek(), b"e");
+        assert_eq!(buffer.len(), 1);
+
+        // Consuming more than is buffered stops at the end
+        assert_eq!(buffer.consume(10), 1);
+        assert_eq!(buffer.peek(), b"");
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn peeked_memory_survives_later_pushes() {
+        let mut buffer = ReceiveBuffer::new(64);
+        buffer.push_bytes(Bytes::from_static(b"first"));
+        let peeked = buffer.peek().as_ptr();
+        for _ in 0..16 {
+            buffer.push_bytes(Bytes::from_static(b"more"));
+        }
+        assert_eq!(buffer.peek().as_ptr(), peeked);
+    }
+
+    #[test]
+    fn pushes_are_truncated_at_the_cap() {
+        let mut buffer = ReceiveBuffer::new(8);
+        assert_eq!(buffer.push_bytes(Bytes::from_static(b"abcde")), 5);
+        assert_eq!(buffer.push_bytes(Bytes::from_static(b"fghij")), 3);
+        assert_eq!(buffer.available(), 0);
+        assert_eq!(buffer.push_bytes(Bytes::from_static(b"k")), 0);
+        assert_eq!(buffer.push(b"k"), 0);
+
+        let mut out = [0u8; 16];
+        assert_eq!(buffer.pop(&mut out), 8);
+        assert_eq!(&out[..8], b"abcdefgh");
+        assert_eq!(buffer.available(), 8);
+        assert_eq!(buffer.push(b"xyz"), 3);
+        assert_eq!(buffer.peek(), b"xyz");
+    }
+
+    #[tokio::test]
+    async fn removing_bytes_signals_space() {
+        let mut buffer = buffer_with(4, &[b"ab", b"cd"]);
+        let space = buffer.space.clone();
+
+        let notified = space.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        buffer.consume(1);
+        tokio::time::timeout(std::time::Duration::from_secs(1), notified).await.unwrap();
+
+        let notified = space.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        buffer.pop(&mut [0u8; 2]);
+        tokio::time::timeout(std::time::Duration::from_secs(1), notified).await.unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+// Transport statistics for `moq_quic_get_stats` and `moq_webtransport_get_stats`
+//
+// Most fields come straight from quinn's ConnectionStats. quinn only exposes
+// the smoothed RTT; its RFC 9002 min_rtt and rttvar are internal, so they are
+// not reported.
+
+use quinn::Connection;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Connection statistics snapshot
+///
+/// Byte and packet counters are cumulative since the connection was established.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MoqConnectionStats {
+    /// Smoothed RTT (microseconds)
+    pub smoothed_rtt_us: u64,
+    /// Congestion window (bytes)
+    pub congestion_window: u64,
+    /// UDP payload bytes sent
+    pub bytes_sent: u64,
+    /// UDP payload bytes received
+    pub bytes_received: u64,
+    /// UDP datagrams sent (each may carry several coalesced QUIC packets)
+    pub udp_datagrams_sent: u64,
+    /// UDP datagrams received (each may carry several coalesced QUIC packets)
+    pub udp_datagrams_received: u64,
+    /// QUIC packets declared lost
+    pub lost_packets: u64,
+    /// Bytes declared lost
+    pub lost_bytes: u64,
+    /// Incoming datagrams discarded because the receive buffer was full
+    pub datagrams_dropped: u64,
+    /// Current path MTU (bytes)
+    pub path_mtu: u32,
+    /// Control and data stream bytes discarded under `MOQ_OVERFLOW_DROP`
+    pub stream_bytes_dropped: u64,
+    /// Datagram sends that discarded older queued datagrams because the send buffer was full
+    pub datagram_send_overflows: u64,
+}
+
+/// Per-connection counters kept alongside quinn's own statistics
+#[derive(Default)]
+pub(crate) struct StatsTracker {
+    datagrams_dropped: AtomicU64,
+    stream_bytes_dropped: AtomicU64,
+    datagram_send_overflows: AtomicU64,
+}
+
+impl StatsTracker {
+    pub(crate) fn datagram_dropped(&self) {
+        self.datagrams_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stream_bytes_dropped(&self, bytes: usize) {
+        self.stream_bytes_dropped.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn datagram_send_overflowed(&self) {
+        self.datagram_send_overflows.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn collect(&self, connection: &Connection) -> MoqConnectionStats {
+        let stats = connection.stats();
+
+        MoqConnectionStats {
+            smoothed_rtt_us: connection.rtt().as_micros() as u64,
+            congestion_window: stats.path.cwnd,
+            bytes_sent: stats.udp_tx.bytes,
+            bytes_received: stats.udp_rx.bytes,
+            udp_datagrams_sent: stats.udp_tx.datagrams,
+            udp_datagrams_received: stats.udp_rx.datagrams,
+            lost_packets: stats.path.lost_packets,
+            lost_bytes: stats.path.lost_bytes,
+            datagrams_dropped: self.datagrams_dropped.load(Ordering::Relaxed),
+            path_mtu: stats.path.current_mtu as u32,
+            stream_bytes_dropped: self.stream_bytes_dropped.load(Ordering::Relaxed),
+            datagram_send_overflows: self.datagram_send_overflows.load(Ordering::Relaxed),
+        }
+    }
+}
+
@@ -0,0 +1,100 @@
+// Per-connection stream handles for `moq_quic_get_stream_info` and
+// `moq_webtransport_get_stream_info`
+//
+// Every stream the FFI hands out, in either direction and on either
+// transport, gets a handle from its connection's allocator. Handles start at
+// 1 (0 stands for the control stream in events) and remember the QUIC stream
+// ID behind them, so they can be matched against qlogs and packet captures.
+
+use dashmap::DashMap;
+use quinn::{Dir, StreamId};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Unidirectional stream
+pub const MOQ_STREAM_DIR_UNI: u32 = 0;
+/// Bidirectional stream
+pub const MOQ_STREAM_DIR_BIDI: u32 = 1;
+
+/// We opened the stream
+pub const MOQ_STREAM_INITIATOR_LOCAL: u32 = 0;
+/// The peer opened the stream
+pub const MOQ_STREAM_INITIATOR_REMOTE: u32 = 1;
+
+/// What a stream handle refers to
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MoqStreamInfo {
+    /// QUIC stream ID as it appears on the wire
+    pub quic_stream_id: u64,
+    /// One of the `MOQ_STREAM_DIR_*` constants
+    pub direction: u32,
+    /// One of the `MOQ_STREAM_INITIATOR_*` constants
+    pub initiator: u32,
+}
+
+/// Handle allocator for one connection or session
+///
+/// Handles are never reused and stay resolvable until the connection is closed.
+pub(crate) struct StreamHandles {
+    next: AtomicU64,
+    streams: DashMap<u64, MoqStreamInfo>,
+}
+
+impl Default for StreamHandles {
+    fn default() -> Self {
+        Self {
+            next: AtomicU64::new(1),
+            streams: DashMap::new(),
+        }
+    }
+}
+
+impl StreamHandles {
+    /// Allocate the handle for a newly opened or accepted stream
+    pub(crate) fn allocate(&self, id: StreamId, local: bool) -> u64 {
+        let handle = self.next.fetch_add(1, Ordering::SeqCst);
+        let info = MoqStreamInfo {
+            quic_stream_id: u64::from(id),
+            direction: match id.dir() {
+                Dir::Uni => MOQ_STREAM_DIR_UNI,
+                Dir::Bi => MOQ_STREAM_DIR_BIDI,
+            },
+            initiator: if local { MOQ_STREAM_INITIATOR_LOCAL } else { MOQ_STREAM_INITIATOR_REMOTE },
+        };
+        self.streams.insert(handle, info);
+        handle
+    }
+
+    pub(crate) fn get(&self, handle: u64) -> Option<MoqStreamInfo> {
+        self.streams.get(&handle).map(|info| *info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quinn::Side;
+
+    #[test]
+    fn handles_count_up_per_connection() {
+        let first = StreamHandles::default();
+        let second = StreamHandles::default();
+        assert_eq!(first.allocate(StreamId::new(Side::Client, Dir::Uni, 0), true), 1);
+        assert_eq!(first.allocate(StreamId::new(Side::Server, Dir::Uni, 0), false), 2);
+        assert_eq!(second.allocate(StreamId::new(Side::Client, Dir::Uni, 0), true), 1);
+        assert!(second.get(2).is_none());
+        assert!(first.get(0).is_none());
+    }
+
+    #[test]
+    fn info_keeps_the_quic_stream_id() {
+        let handles = StreamHandles::default();
+        let local_uni = handles.allocate(StreamId::new(Side::Client, Dir::Uni, 3), true);
+        let remote_bidi = handles.allocate(StreamId::new(Side::Server, Dir::Bi, 1), false);
+
+        let info = handles.get(local_uni).unwrap();
+        assert_eq!((info.quic_stream_id, info.direction, info.initiator), (14, MOQ_STREAM_DIR_UNI, MOQ_STREAM_INITIATOR_LOCAL));
+        let info = handles.get(remote_bidi).unwrap();
+        assert_eq!((info.quic_stream_id, info.direction, info.initiator), (5, MOQ_STREAM_DIR_BIDI, MOQ_STREAM_INITIATOR_REMOTE));
+    }
+}
@@ -0,0 +1,73 @@
+// Final state of incoming data streams for `moq_quic_get_data_stream_state`
+// and `moq_webtransport_get_data_stream_state`
+//
+// Each accepted stream's reader records how the stream ended, and listens
+// for a local request to stop it with STOP_SENDING.
+
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Still receiving data
+pub const MOQ_STREAM_STATE_OPEN: u32 = 0;
+/// The peer finished the stream (FIN); all data has been received
+pub const MOQ_STREAM_STATE_FINISHED: u32 = 1;
+/// The peer reset the stream; the error code holds the RESET_STREAM code
+pub const MOQ_STREAM_STATE_RESET: u32 = 2;
+/// We stopped the stream; the error code holds the STOP_SENDING code we sent
+pub const MOQ_STREAM_STATE_STOPPED: u32 = 3;
+/// Reading failed for another reason, usually because the connection was lost
+pub const MOQ_STREAM_STATE_FAILED: u32 = 4;
+
+/// State shared between an incoming stream's reader and the FFI calls
+///
+/// `C` is the transport's stop code type.
+pub(crate) struct IncomingStream<C> {
+    state: Mutex<(u32, u64)>,
+    stop: Mutex<Option<oneshot::Sender<C>>>,
+}
+
+impl<C> IncomingStream<C> {
+    /// Create the state for a newly accepted stream, plus the receiver its
+    /// reader watches for stop requests
+    pub(crate) fn new() -> (Arc<Self>, oneshot::Receiver<C>) {
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let stream = Self {
+            state: Mutex::new((MOQ_STREAM_STATE_OPEN, 0)),
+            stop: Mutex::new(Some(stop_tx)),
+        };
+        (Arc::new(stream), stop_rx)
+    }
+
+    /// Record how the stream ended
+    pub(crate) fn set_final(&self, state: u32, error_code: u64) {
+        *self.state.lock().unwrap() = (state, error_code);
+        self.stop.lock().unwrap().take();
+    }
+
+    /// Ask the reader to stop the stream
+    ///
+    /// Returns false if the stream already ended.
+    pub(crate) fn stop(&self, code: C) -> bool {
+        match self.stop.lock().unwrap().take() {
+            Some(stop_tx) => stop_tx.send(code).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Whether the reader may still buffer more data
+    pub(crate) fn is_open(&self) -> bool {
+        self.state.lock().unwrap().0 == MOQ_STREAM_STATE_OPEN
+    }
+
+    /// Write the state to FFI output parameters
+    ///
+    /// # Returns
+    /// * One of the `MOQ_STREAM_STATE_*` constants
+    pub(crate) fn write_state(&self, out_error_code: *mut u64) -> i32 {
+        let (state, error_code) = *self.state.lock().unwrap();
+        if !out_error_code.is_null() {
+            unsafe { *out_error_code = error_code; }
+        }
+        state as i32
+    }
+}
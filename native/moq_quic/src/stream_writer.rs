@@ -1,15 +1,71 @@
 // Stream writer module for async QUIC stream writes
 // Uses mpsc channels to buffer write operations from FFI
 
-use quinn::{SendStream as QuinnSendStream, RecvStream as QuinnRecvStream};
-use std::sync::Arc;
+use quinn::{SendStream as QuinnSendStream, RecvStream as QuinnRecvStream, VarInt};
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWrite;
 use tokio::sync::mpsc::{self, Sender};
 
+/// A send stream shared between its writer and out-of-band calls like set_priority
+///
+/// The stream is only locked while a write is being polled, so those calls
+/// never wait behind a write that is blocked on flow control or congestion.
+pub(crate) struct SharedSendStream<S> {
+    stream: Mutex<S>,
+    // Keeps concurrent writes (and finish) in order
+    writing: tokio::sync::Mutex<()>,
+}
+
+impl<S: AsyncWrite + Unpin> SharedSendStream<S> {
+    pub(crate) fn new(stream: S) -> Self {
+        Self {
+            stream: Mutex::new(stream),
+            writing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Write all of `data`, after any writes already in progress
+    pub(crate) async fn write_all(&self, data: &[u8]) -> std::io::Result<()> {
+        let _writing = self.writing.lock().await;
+        let mut written = 0;
+        while written < data.len() {
+            written += poll_fn(|cx| {
+                let mut stream = self.stream.lock().unwrap();
+                Pin::new(&mut *stream).poll_write(cx, &data[written..])
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Run `f` on the stream once writes in progress have completed
+    pub(crate) async fn after_writes<R>(&self, f: impl FnOnce(&mut S) -> R) -> R {
+        let _writing = self.writing.lock().await;
+        f(&mut self.stream.lock().unwrap())
+    }
+
+    /// Run `f` on the stream right away, even while a write is waiting
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut S) -> R) -> R {
+        f(&mut self.stream.lock().unwrap())
+    }
+}
+
+/// The STOP_SENDING code carried by a failed write, if that is why it failed
+pub(crate) fn stopped_code(error: &std::io::Error) -> Option<VarInt> {
+    match error.get_ref()?.downcast_ref::<quinn::WriteError>()? {
+        quinn::WriteError::Stopped(code) => Some(*code),
+        _ => None,
+    }
+}
+
 /// Command for stream writer operations
 #[allow(dead_code)]
 pub enum StreamCommand {
     Write(Vec<u8>),
     Finish,
+    Reset(VarInt),
 }
 
 /// Stream writer that handles async writes via a channel
@@ -17,6 +73,7 @@ pub enum StreamCommand {
 pub struct StreamWriter {
     #[allow(dead_code)]
     tx: Sender<StreamCommand>,
+    stream: Arc<SharedSendStream<QuinnSendStream>>,
 }
 
 impl StreamWriter {
@@ -28,26 +85,35 @@ impl StreamWriter {
         channel_capacity: usize,
     ) -> Self {
         let (tx, mut rx) = mpsc::channel(channel_capacity);
+        let stream = Arc::new(SharedSendStream::new(send_stream));
+        let send_stream = stream.clone();
 
         // Spawn task to process write commands
         tokio::spawn(async move {
-            let mut send_stream = send_stream;
             let mut finished = false;
 
             while !finished {
                 match rx.recv().await {
                     Some(StreamCommand::Write(data)) => {
                         if let Err(e) = send_stream.write_all(&data).await {
-                            log::error!("Failed to write to stream {} session {}: {:?}", stream_id, session_id, e);
+                            match stopped_code(&e) {
+                                Some(code) => log::info!("Stream {} session {} stopped by peer (code {})", stream_id, session_id, code),
+                                None => log::error!("Failed to write to stream {} session {}: {:?}", stream_id, session_id, e),
+                            }
                             break;
                         }
                     }
                     Some(StreamCommand::Finish) => {
-                        if let Err(e) = send_stream.finish() {
+                        if let Err(e) = send_stream.with(|stream| stream.finish()) {
                             log::warn!("Failed to finish stream {} session {}: {:?}", stream_id, session_id, e);
                         }
                         finished = true;
                     }
+                    Some(StreamCommand::Reset(code)) => {
+                        // Usually already reset by StreamWriter::reset
+                        let _ = send_stream.with(|stream| stream.reset(code));
+                        finished = true;
+                    }
                     None => {
                         // Channel closed, clean up
                         break;
@@ -56,7 +122,40 @@ impl StreamWriter {
             }
         });
 
-        Self { tx }
+        Self { tx, stream }
+    }
+
+    /// Abandon the stream with RESET_STREAM, discarding data not yet sent
+    ///
+    /// The reset is applied right away rather than queued behind pending
+    /// writes, so a stream stuck on congestion can still be abandoned.
+    pub fn reset(&self, error_code: VarInt) -> Result<(), quinn::ClosedStream> {
+        let result = self.stream.with(|stream| stream.reset(error_code));
+        // Lets an idle task exit; a busy one stops when its write fails
+        let _ = self.tx.try_send(StreamCommand::Reset(error_code));
+        result
+    }
+
+    /// Resolves with the code if the peer sends STOP_SENDING
+    ///
+    /// Resolves with `None` once the stream is finished and acknowledged,
+    /// reset locally, or the connection is lost.
+    pub fn stopped(&self) -> impl Future<Output = Option<VarInt>> + Send + 'static {
+        let stopped = self.stream.with(|stream| stream.stopped());
+        async move { stopped.await.ok().flatten() }
+    }
+
+    /// Resolves once the writer task has exited, whether the stream was
+    /// finished, reset or failed
+    pub async fn closed(&self) {
+        self.tx.closed().await
+    }
+
+    /// Change the stream's send priority (higher values are sent first)
+    ///
+    /// Takes effect immediately, including for data already queued.
+    pub fn set_priority(&self, priority: i32) -> Result<(), quinn::ClosedStream> {
+        self.stream.with(|stream| stream.set_priority(priority))
     }
 
     /// Try to write data without blocking
@@ -83,9 +182,17 @@ impl StreamWriter {
 }
 
 /// Callback for receiving stream data
-#[allow(dead_code)]
 pub trait StreamDataCallback: Send + Sync {
     fn on_stream_data(&self, session_id: u64, stream_id: u64, data: &[u8]);
+
+    /// Bytes received on the receive side of a bidirectional stream
+    fn on_bidi_stream_data(&self, session_id: u64, stream_id: u64, data: &[u8]);
+
+    /// Bytes received on the control stream
+    fn on_control_data(&self, session_id: u64, data: &[u8]);
+
+    /// A complete datagram
+    fn on_datagram(&self, session_id: u64, data: &[u8]);
 }
 
 /// Handle a unidirectional stream with incremental reading
@@ -116,16 +223,23 @@ pub async fn handle_unidirectional_stream(
     }
 }
 
-/// Handle a bidirectional stream with incremental reading
-#[allow(dead_code)]
-pub async fn handle_bidirectional_stream(
+/// Handle a bidirectional stream: a StreamWriter for the send side, and
+/// `read` spawned as the receive side's task
+///
+/// The two sides are independent; the peer finishing its side leaves ours
+/// open until it is finished or reset through the writer.
+pub fn handle_bidirectional_stream<F, Fut>(
     session_id: u64,
     stream_id: u64,
     send_stream: QuinnSendStream,
-    mut recv_stream: QuinnRecvStream,
-    callback: Arc<dyn StreamDataCallback>,
+    recv_stream: QuinnRecvStream,
+    read: F,
     channel_capacity: usize,
-) -> Arc<StreamWriter> {
+) -> Arc<StreamWriter>
+where
+    F: FnOnce(QuinnRecvStream) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
     // Create stream writer for the send side
     let writer = Arc::new(StreamWriter::new(
         session_id,
@@ -134,35 +248,7 @@ pub async fn handle_bidirectional_stream(
         channel_capacity,
     ));
 
-    // Clone for the receive task
-    let writer_for_cleanup = writer.clone();
-
-    // Spawn receive task
-    tokio::spawn(async move {
-        let mut buffer = vec![0u8; 65536]; // 64KB read buffer
-
-        loop {
-            match recv_stream.read(&mut buffer).await {
-                Ok(Some(len)) => {
-                    log::debug!("Bi stream {} received {} bytes", stream_id, len);
-                    callback.on_stream_data(session_id, stream_id, &buffer[..len]);
-                }
-                Ok(None) => {
-                    log::debug!("Bi stream {} receive side closed", stream_id);
-                    break;
-                }
-                Err(e) => {
-                    log::error!("Error reading from bi stream {}: {:?}", stream_id, e);
-                    break;
-                }
-            }
-        }
-
-        // Clean up writer when receive side closes
-        if let Err(e) = writer_for_cleanup.finish().await {
-            log::warn!("Failed to finish stream writer for stream {}: {:?}", stream_id, e);
-        }
-    });
+    tokio::spawn(read(recv_stream));
 
     writer
 }
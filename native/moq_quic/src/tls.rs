@@ -0,0 +1,838 @@
+// TLS configuration shared by the QUIC and WebTransport connect paths
+//
+// A TLS config is created from Dart with `moq_tls_config_new`, filled in with
+// trust anchors and an optional client certificate, and referenced by ID from `MoqConnectOptions::tls_config_id`.
+// Each connect takes a snapshot, so a config can be reused or freed afterwards.
+// Errors are reported through `moq_quic_get_last_error`.
+
+use dashmap::DashMap;
+use once_cell::sync::OnceCell;
+use rustls::client::WantsClientCert;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::ConfigBuilder;
+use std::ffi::c_char;
+use std::slice;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use x509_parser::public_key::PublicKey;
+
+use crate::set_last_error;
+
+/// Trust custom CA certificates in addition to the system roots (default)
+pub const MOQ_TRUST_SYSTEM_AND_CUSTOM: u32 = 0;
+/// Trust only the custom CA certificates
+pub const MOQ_TRUST_CUSTOM_ONLY: u32 = 1;
+
+/// Length of a SHA-256 certificate hash passed to `moq_tls_config_add_cert_hash`
+pub const MOQ_CERT_HASH_LEN: usize = 32;
+
+// Maximum validity period of a hash-pinned certificate (WebTransport serverCertificateHashes)
+const MAX_PINNED_CERT_VALIDITY_SECS: i64 = 14 * 24 * 60 * 60;
+
+// No certificate verification for testing (DANGER: only use for development!)
+#[derive(Debug)]
+pub(crate) struct NoVerification;
+
+impl rustls::client::danger::ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        vec![
+            rustls::SignatureScheme::RSA_PKCS1_SHA1,
+            rustls::SignatureScheme::ECDSA_SHA1_Legacy,
+            rustls::SignatureScheme::RSA_PKCS1_SHA256,
+            rustls::SignatureScheme::ECDSA_NISTP256_SHA256,
+            rustls::SignatureScheme::ED25519,
+            rustls::SignatureScheme::RSA_PSS_SHA256,
+        ]
+    }
+}
+
+// Certificate hash pinning with WebTransport serverCertificateHashes semantics:
+// the end-entity certificate must match one of the SHA-256 hashes, be valid
+// for at most 14 days, and carry an ECDSA key. The chain is not checked.
+#[derive(Debug)]
+struct CertificateHashVerification {
+    hashes: Vec<[u8; MOQ_CERT_HASH_LEN]>,
+    provider: Arc<CryptoProvider>,
+    pin_mismatch: Arc<AtomicBool>,
+}
+
+impl CertificateHashVerification {
+    fn check_constraints(&self, end_entity: &CertificateDer<'_>, now: UnixTime) -> Result<(), rustls::Error> {
+        let (_, cert) = x509_parser::parse_x509_certificate(end_entity)
+            .map_err(|_| rustls::Error::InvalidCertificate(rustls::CertificateError::BadEncoding))?;
+
+        let not_before = cert.validity().not_before.timestamp();
+        let not_after = cert.validity().not_after.timestamp();
+        if not_after - not_before > MAX_PINNED_CERT_VALIDITY_SECS {
+            log::error!("Pinned certificate is valid for more than 14 days");
+            return Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            ));
+        }
+
+        let now = now.as_secs() as i64;
+        if now < not_before {
+            return Err(rustls::Error::InvalidCertificate(rustls::CertificateError::NotValidYet));
+        }
+        if now > not_after {
+            return Err(rustls::Error::InvalidCertificate(rustls::CertificateError::Expired));
+        }
+
+        match cert.public_key().parsed() {
+            Ok(PublicKey::EC(_)) => Ok(()),
+            _ => {
+                log::error!("Pinned certificate does not use an ECDSA key");
+                Err(rustls::Error::InvalidCertificate(
+                    rustls::CertificateError::ApplicationVerificationFailure,
+                ))
+            }
+        }
+    }
+}
+
+impl rustls::client::danger::ServerCertVerifier for CertificateHashVerification {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        let cert_hash = web_transport_quinn::crypto::sha256(&self.provider, end_entity);
+        if !self.hashes.iter().any(|hash| hash[..] == *cert_hash.as_ref()) {
+            log::error!("Server certificate does not match any pinned hash");
+            self.pin_mismatch.store(true, Ordering::SeqCst);
+            return Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            ));
+        }
+
+        self.check_constraints(end_entity, now)?;
+        Ok(rustls::client::danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+// Client certificate chain and private key presented for mutual TLS
+struct ClientIdentity {
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+}
+
+/// Trust settings collected through the `moq_tls_config_*` calls
+#[derive(Clone, Default)]
+pub(crate) struct TlsConfig {
+    trust_mode: u32,
+    ca_certs: Vec<CertificateDer<'static>>,
+    cert_hashes: Vec<[u8; MOQ_CERT_HASH_LEN]>,
+    client_identity: Option<Arc<ClientIdentity>>,
+}
+
+/// Outcome of certificate pinning for one handshake
+#[derive(Clone, Default)]
+pub(crate) struct PinStatus(Arc<AtomicBool>);
+
+impl PinStatus {
+    /// Whether the server certificate was rejected for not matching a pinned hash
+    pub(crate) fn mismatched(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// Global registry of TLS configs (config_id -> config)
+static TLS_CONFIGS: OnceCell<DashMap<u64, Arc<Mutex<TlsConfig>>>> = OnceCell::new();
+
+// Next TLS config ID counter (0 means "no config" in MoqConnectOptions)
+static NEXT_TLS_CONFIG_ID: AtomicU64 = AtomicU64::new(1);
+
+fn tls_configs() -> &'static DashMap<u64, Arc<Mutex<TlsConfig>>> {
+    TLS_CONFIGS.get_or_init(DashMap::new)
+}
+
+/// Snapshot a TLS config for a connect call
+///
+/// # Returns
+/// * `Ok(None)` for config ID 0, `Err` if the ID is unknown
+pub(crate) fn lookup(config_id: u64) -> Result<Option<TlsConfig>, String> {
+    if config_id == 0 {
+        return Ok(None);
+    }
+    match tls_configs().get(&config_id) {
+        Some(config) => Ok(Some(config.lock().unwrap().clone())),
+        None => Err(format!("TLS config {} not found", config_id)),
+    }
+}
+
+/// Build the rustls client config for a connect call
+///
+/// Certificate hashes in `config` take precedence over trust anchors.
+///
+/// # Arguments
+/// * `insecure` - Skip certificate verification entirely (for testing only)
+/// * `config` - Optional trust settings from `lookup`
+/// * `label` - Transport name used in log messages
+pub(crate) fn client_crypto(
+    insecure: bool,
+    config: Option<&TlsConfig>,
+    label: &str,
+) -> Result<(rustls::ClientConfig, PinStatus), String> {
+    let pin_status = PinStatus::default();
+
+    if insecure {
+        // Disable certificate verification for testing
+        let builder = rustls::ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerification));
+        return Ok((with_client_auth(builder, config)?, pin_status));
+    }
+
+    if let Some(config) = config.filter(|c| !c.cert_hashes.is_empty()) {
+        log::info!("Pinning {} certificate hashes for {}", config.cert_hashes.len(), label);
+        let verifier = CertificateHashVerification {
+            hashes: config.cert_hashes.clone(),
+            provider: web_transport_quinn::crypto::default_provider(),
+            pin_mismatch: pin_status.0.clone(),
+        };
+        let builder = rustls::ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier));
+        return Ok((with_client_auth(builder, Some(config))?, pin_status));
+    }
+
+    let mut certs = rustls::RootCertStore::empty();
+    let trust_mode = config.map_or(MOQ_TRUST_SYSTEM_AND_CUSTOM, |c| c.trust_mode);
+
+    if trust_mode != MOQ_TRUST_CUSTOM_ONLY {
+        // Load system root certificates for TLS validation
+        let native_certs_result = rustls_native_certs::load_native_certs();
+        if let Some(e) = native_certs_result.errors.first() {
+            log::warn!("Error loading some native certs: {:?}", e);
+        }
+        for cert in native_certs_result.certs {
+            if let Err(e) = certs.add(cert) {
+                log::warn!("Failed to add native cert: {:?}", e);
+            }
+        }
+        log::info!("Loaded {} system root certificates for {}", certs.len(), label);
+    }
+
+    if let Some(config) = config {
+        let (added, ignored) = certs.add_parsable_certificates(config.ca_certs.iter().cloned());
+        if ignored > 0 {
+            log::warn!("Ignored {} unparsable custom CA certificates", ignored);
+        }
+        log::info!("Added {} custom CA certificates for {}", added, label);
+    }
+
+    if certs.is_empty() {
+        return Err(format!("No trust anchors available for {}", label));
+    }
+
+    let builder = rustls::ClientConfig::builder().with_root_certificates(certs);
+    Ok((with_client_auth(builder, config)?, pin_status))
+}
+
+/// Finish a client config, presenting the client certificate if one is set
+fn with_client_auth(
+    builder: ConfigBuilder<rustls::ClientConfig, WantsClientCert>,
+    config: Option<&TlsConfig>,
+) -> Result<rustls::ClientConfig, String> {
+    match config.and_then(|c| c.client_identity.as_ref()) {
+        Some(identity) => {
+            log::info!("Presenting client certificate ({} certs in chain)", identity.cert_chain.len());
+            builder
+                .with_client_auth_cert(identity.cert_chain.clone(), identity.key.clone_key())
+                .map_err(|e| format!("Invalid client certificate: {}", e))
+        }
+        None => Ok(builder.with_no_client_auth()),
+    }
+}
+
+/// Describe the TLS alert behind a failed or closed connection, if any
+///
+/// Walks the error's source chain, so errors wrapping a `quinn::ConnectionError`
+/// (e.g. WebTransport client errors) are recognised too.
+pub(crate) fn tls_alert_reason(err: &(dyn std::error::Error + 'static)) -> Option<String> {
+    let mut current = Some(err);
+    while let Some(e) = current {
+        if let Some(conn_err) = e.downcast_ref::<quinn::ConnectionError>() {
+            return connection_alert_reason(conn_err);
+        }
+        current = e.source();
+    }
+    None
+}
+
+/// Describe the TLS alert carried by a QUIC CRYPTO_ERROR (0x0100-0x01ff), if any
+pub(crate) fn connection_alert_reason(err: &quinn::ConnectionError) -> Option<String> {
+    let (code, reason, origin) = match err {
+        quinn::ConnectionError::ConnectionClosed(close) => (
+            u64::from(close.error_code),
+            String::from_utf8_lossy(&close.reason).into_owned(),
+            "peer",
+        ),
+        quinn::ConnectionError::TransportError(e) => (u64::from(e.code), e.reason.clone(), "local"),
+        _ => return None,
+    };
+    if !(0x100..0x200).contains(&code) {
+        return None;
+    }
+    let alert = (code - 0x100) as u8;
+    Some(format!(
+        "TLS alert from {}: {:?} ({}) {}",
+        origin,
+        rustls::AlertDescription::from(alert),
+        alert,
+        reason
+    ))
+}
+
+/// Parse every CERTIFICATE block in a PEM bundle
+fn parse_pem_certs(pem: &[u8]) -> Result<Vec<CertificateDer<'static>>, String> {
+    let mut reader = pem;
+    let certs = rustls_pemfile::certs(&mut reader)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid PEM data: {}", e))?;
+    if certs.is_empty() {
+        return Err("No certificates found in PEM data".to_string());
+    }
+    Ok(certs)
+}
+
+/// Parse a client certificate chain and private key from PEM data
+fn parse_client_identity(cert_pem: &[u8], key_pem: &[u8]) -> Result<ClientIdentity, String> {
+    let cert_chain = parse_pem_certs(cert_pem)?;
+    let mut reader = key_pem;
+    let key = rustls_pemfile::private_key(&mut reader)
+        .map_err(|e| format!("Invalid PEM key data: {}", e))?
+        .ok_or_else(|| "No private key found in PEM data".to_string())?;
+
+    // Check that the key is usable before any connect relies on it
+    let provider = web_transport_quinn::crypto::default_provider();
+    provider
+        .key_provider
+        .load_private_key(key.clone_key())
+        .map_err(|e| format!("Unsupported client private key: {}", e))?;
+
+    Ok(ClientIdentity { cert_chain, key })
+}
+
+/// Set the client certificate of a TLS config
+fn set_client_identity(config_id: u64, cert_pem: &[u8], key_pem: &[u8]) -> i32 {
+    let config = match tls_configs().get(&config_id) {
+        Some(c) => c.clone(),
+        None => {
+            set_last_error(&format!("TLS config {} not found", config_id));
+            return -1;
+        }
+    };
+
+    match parse_client_identity(cert_pem, key_pem) {
+        Ok(identity) => {
+            config.lock().unwrap().client_identity = Some(Arc::new(identity));
+            log::debug!("Set client certificate on TLS config {}", config_id);
+            0
+        }
+        Err(err_msg) => {
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            -3
+        }
+    }
+}
+
+/// Append CA certificates to a TLS config
+fn add_ca_certs(config_id: u64, pem: &[u8]) -> i32 {
+    let config = match tls_configs().get(&config_id) {
+        Some(c) => c.clone(),
+        None => {
+            set_last_error(&format!("TLS config {} not found", config_id));
+            return -1;
+        }
+    };
+
+    match parse_pem_certs(pem) {
+        Ok(certs) => {
+            let count = certs.len();
+            config.lock().unwrap().ca_certs.extend(certs);
+            log::debug!("Added {} CA certificates to TLS config {}", count, config_id);
+            count as i32
+        }
+        Err(err_msg) => {
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            -3
+        }
+    }
+}
+
+/// Create an empty TLS config
+///
+/// # Returns
+/// * The new config ID (never 0)
+#[no_mangle]
+pub extern "C" fn moq_tls_config_new() -> u64 {
+    let config_id = NEXT_TLS_CONFIG_ID.fetch_add(1, Ordering::SeqCst);
+    tls_configs().insert(config_id, Arc::new(Mutex::new(TlsConfig::default())));
+    log::debug!("Created TLS config {}", config_id);
+    config_id
+}
+
+/// Add CA certificates from a PEM file to a TLS config
+///
+/// # Arguments
+/// * `config_id` - The TLS config ID
+/// * `path` - Path to a PEM bundle (must be null-terminated)
+///
+/// # Returns
+/// * Number of certificates added on success, negative error code on failure
+///   (-1 unknown config, -2 invalid path or I/O error, -3 no valid certificates)
+#[no_mangle]
+pub extern "C" fn moq_tls_config_add_ca_pem_file(
+    config_id: u64,
+    path: *const c_char,
+) -> i32 {
+    let path_str = unsafe {
+        if path.is_null() {
+            return -2;
+        }
+        match std::ffi::CStr::from_ptr(path).to_str() {
+            Ok(s) => s.to_string(),
+            Err(_) => return -2,
+        }
+    };
+
+    match std::fs::read(&path_str) {
+        Ok(pem) => add_ca_certs(config_id, &pem),
+        Err(e) => {
+            let err_msg = format!("Failed to read CA file {}: {}", path_str, e);
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            -2
+        }
+    }
+}
+
+/// Add CA certificates from an in-memory PEM bundle to a TLS config
+///
+/// # Arguments
+/// * `config_id` - The TLS config ID
+/// * `data` - Pointer to PEM data
+/// * `len` - Length of data
+///
+/// # Returns
+/// * Number of certificates added on success, negative error code on failure
+///   (-1 unknown config, -2 invalid buffer, -3 no valid certificates)
+#[no_mangle]
+pub extern "C" fn moq_tls_config_add_ca_pem(
+    config_id: u64,
+    data: *const u8,
+    len: usize,
+) -> i32 {
+    if data.is_null() || len == 0 {
+        return -2;
+    }
+    let pem = unsafe { slice::from_raw_parts(data, len) };
+    add_ca_certs(config_id, pem)
+}
+
+/// Choose whether custom CAs extend or replace the system roots
+///
+/// # Arguments
+/// * `config_id` - The TLS config ID
+/// * `trust_mode` - `MOQ_TRUST_SYSTEM_AND_CUSTOM` or `MOQ_TRUST_CUSTOM_ONLY`
+///
+/// # Returns
+/// * 0 on success, -1 unknown config, -2 invalid trust mode
+#[no_mangle]
+pub extern "C" fn moq_tls_config_set_trust_mode(
+    config_id: u64,
+    trust_mode: u32,
+) -> i32 {
+    if trust_mode != MOQ_TRUST_SYSTEM_AND_CUSTOM && trust_mode != MOQ_TRUST_CUSTOM_ONLY {
+        set_last_error(&format!("Invalid trust mode {}", trust_mode));
+        return -2;
+    }
+    match tls_configs().get(&config_id) {
+        Some(config) => {
+            config.lock().unwrap().trust_mode = trust_mode;
+            0
+        }
+        None => {
+            set_last_error(&format!("TLS config {} not found", config_id));
+            -1
+        }
+    }
+}
+
+/// Pin a server certificate by its SHA-256 hash
+///
+/// Once a config has at least one hash, connections using it accept only
+/// certificates matching a pinned hash, valid for at most 14 days and with an
+/// ECDSA key (WebTransport serverCertificateHashes rules). Trust anchors are
+/// ignored. A hash mismatch fails the connect with -9.
+///
+/// # Arguments
+/// * `config_id` - The TLS config ID
+/// * `hash` - Pointer to the SHA-256 hash of the DER certificate
+/// * `len` - Length of hash, must be `MOQ_CERT_HASH_LEN`
+///
+/// # Returns
+/// * 0 on success, -1 unknown config, -2 invalid hash
+#[no_mangle]
+pub extern "C" fn moq_tls_config_add_cert_hash(
+    config_id: u64,
+    hash: *const u8,
+    len: usize,
+) -> i32 {
+    if hash.is_null() || len != MOQ_CERT_HASH_LEN {
+        set_last_error(&format!("Certificate hash must be {} bytes", MOQ_CERT_HASH_LEN));
+        return -2;
+    }
+    let mut pinned = [0u8; MOQ_CERT_HASH_LEN];
+    pinned.copy_from_slice(unsafe { slice::from_raw_parts(hash, len) });
+
+    match tls_configs().get(&config_id) {
+        Some(config) => {
+            config.lock().unwrap().cert_hashes.push(pinned);
+            0
+        }
+        None => {
+            set_last_error(&format!("TLS config {} not found", config_id));
+            -1
+        }
+    }
+}
+
+/// Present a client certificate from PEM files for mutual TLS
+///
+/// # Arguments
+/// * `config_id` - The TLS config ID
+/// * `cert_path` - Path to the PEM certificate chain, leaf first (must be null-terminated)
+/// * `key_path` - Path to the PEM private key (must be null-terminated)
+///
+/// # Returns
+/// * 0 on success, negative error code on failure
+///   (-1 unknown config, -2 invalid path or I/O error, -3 invalid certificate or key)
+#[no_mangle]
+pub extern "C" fn moq_tls_config_set_client_cert_pem_files(
+    config_id: u64,
+    cert_path: *const c_char,
+    key_path: *const c_char,
+) -> i32 {
+    let mut pems = Vec::with_capacity(2);
+    for path in [cert_path, key_path] {
+        let path_str = unsafe {
+            if path.is_null() {
+                return -2;
+            }
+            match std::ffi::CStr::from_ptr(path).to_str() {
+                Ok(s) => s.to_string(),
+                Err(_) => return -2,
+            }
+        };
+        match std::fs::read(&path_str) {
+            Ok(pem) => pems.push(pem),
+            Err(e) => {
+                let err_msg = format!("Failed to read {}: {}", path_str, e);
+                log::error!("{}", err_msg);
+                set_last_error(&err_msg);
+                return -2;
+            }
+        }
+    }
+
+    set_client_identity(config_id, &pems[0], &pems[1])
+}
+
+/// Present a client certificate from in-memory PEM data for mutual TLS
+///
+/// # Arguments
+/// * `config_id` - The TLS config ID
+/// * `cert_data` - Pointer to the PEM certificate chain, leaf first
+/// * `cert_len` - Length of cert_data
+/// * `key_data` - Pointer to the PEM private key
+/// * `key_len` - Length of key_data
+///
+/// # Returns
+/// * 0 on success, negative error code on failure
+///   (-1 unknown config, -2 invalid buffer, -3 invalid certificate or key)
+#[no_mangle]
+pub extern "C" fn moq_tls_config_set_client_cert_pem(
+    config_id: u64,
+    cert_data: *const u8,
+    cert_len: usize,
+    key_data: *const u8,
+    key_len: usize,
+) -> i32 {
+    if cert_data.is_null() || cert_len == 0 || key_data.is_null() || key_len == 0 {
+        return -2;
+    }
+    let cert_pem = unsafe { slice::from_raw_parts(cert_data, cert_len) };
+    let key_pem = unsafe { slice::from_raw_parts(key_data, key_len) };
+    set_client_identity(config_id, cert_pem, key_pem)
+}
+
+/// Free a TLS config
+///
+/// Connections already established with it are not affected.
+///
+/// # Returns
+/// * 0 on success, -1 if the config was not found
+#[no_mangle]
+pub extern "C" fn moq_tls_config_free(config_id: u64) -> i32 {
+    match tls_configs().remove(&config_id) {
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::client::danger::ServerCertVerifier;
+    use std::time::Duration;
+
+    const DAY: u64 = 24 * 60 * 60;
+    // Fixed "now" for the validity checks: 2025-01-01T00:00:00Z
+    const NOW: u64 = 1_735_689_600;
+
+    /// Self-signed certificate valid from `NOW + from_days` to `NOW + to_days`
+    fn certificate(alg: &'static rcgen::SignatureAlgorithm, from_days: u64, to_days: u64) -> CertificateDer<'static> {
+        let at = |days: u64| rcgen::date_time_ymd(2025, 1, 1) + Duration::from_secs(days * DAY);
+        let mut params = rcgen::CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        params.not_before = at(from_days);
+        params.not_after = at(to_days);
+        let key = rcgen::KeyPair::generate_for(alg).unwrap();
+        params.self_signed(&key).unwrap().der().clone()
+    }
+
+    fn verifier(pinned: &[&CertificateDer<'_>]) -> CertificateHashVerification {
+        let provider = web_transport_quinn::crypto::default_provider();
+        let hashes = pinned
+            .iter()
+            .map(|cert| {
+                let mut hash = [0u8; MOQ_CERT_HASH_LEN];
+                hash.copy_from_slice(web_transport_quinn::crypto::sha256(&provider, cert).as_ref());
+                hash
+            })
+            .collect();
+        CertificateHashVerification { hashes, provider, pin_mismatch: Arc::default() }
+    }
+
+    fn verify(verifier: &CertificateHashVerification, cert: &CertificateDer<'_>, now: u64) -> Result<(), rustls::Error> {
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let now = UnixTime::since_unix_epoch(Duration::from_secs(now));
+        verifier.verify_server_cert(cert, &[], &server_name, &[], now).map(|_| ())
+    }
+
+    #[test]
+    fn pinned_short_lived_ecdsa_certificate_is_accepted() {
+        let cert = certificate(&rcgen::PKCS_ECDSA_P256_SHA256, 0, 10);
+        let verifier = verifier(&[&cert]);
+        assert!(verify(&verifier, &cert, NOW + DAY).is_ok());
+        assert!(!verifier.pin_mismatch.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn unpinned_certificate_is_a_pin_mismatch() {
+        let pinned = certificate(&rcgen::PKCS_ECDSA_P256_SHA256, 0, 10);
+        let served = certificate(&rcgen::PKCS_ECDSA_P256_SHA256, 0, 10);
+        let verifier = verifier(&[&pinned]);
+        assert!(verify(&verifier, &served, NOW + DAY).is_err());
+        // Connects map this flag to -9
+        assert!(verifier.pin_mismatch.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn validity_over_14_days_is_rejected() {
+        let cert = certificate(&rcgen::PKCS_ECDSA_P256_SHA256, 0, 15);
+        let verifier = verifier(&[&cert]);
+        assert_eq!(
+            verify(&verifier, &cert, NOW + DAY),
+            Err(rustls::Error::InvalidCertificate(rustls::CertificateError::ApplicationVerificationFailure))
+        );
+        assert!(!verifier.pin_mismatch.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn non_ecdsa_key_is_rejected() {
+        let cert = certificate(&rcgen::PKCS_ED25519, 0, 10);
+        let verifier = verifier(&[&cert]);
+        assert_eq!(
+            verify(&verifier, &cert, NOW + DAY),
+            Err(rustls::Error::InvalidCertificate(rustls::CertificateError::ApplicationVerificationFailure))
+        );
+    }
+
+    #[test]
+    fn expired_certificate_is_rejected() {
+        let cert = certificate(&rcgen::PKCS_ECDSA_P256_SHA256, 0, 10);
+        let verifier = verifier(&[&cert]);
+        assert_eq!(
+            verify(&verifier, &cert, NOW + 11 * DAY),
+            Err(rustls::Error::InvalidCertificate(rustls::CertificateError::Expired))
+        );
+    }
+
+    #[test]
+    fn not_yet_valid_certificate_is_rejected() {
+        let cert = certificate(&rcgen::PKCS_ECDSA_P256_SHA256, 2, 10);
+        let verifier = verifier(&[&cert]);
+        assert_eq!(
+            verify(&verifier, &cert, NOW + DAY),
+            Err(rustls::Error::InvalidCertificate(rustls::CertificateError::NotValidYet))
+        );
+    }
+
+    /// CA certificate (PEM) plus a `localhost` server config with a leaf it signed
+    fn ca_and_server(ca_name: &str) -> (String, rustls::ServerConfig) {
+        let mut ca_params = rcgen::CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.distinguished_name.push(rcgen::DnType::CommonName, ca_name);
+        ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        let ca = rcgen::CertifiedIssuer::self_signed(ca_params, rcgen::KeyPair::generate().unwrap()).unwrap();
+
+        let leaf_key = rcgen::KeyPair::generate().unwrap();
+        let leaf_params = rcgen::CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        let leaf = leaf_params.signed_by(&leaf_key, &ca).unwrap();
+
+        let key = PrivateKeyDer::Pkcs8(leaf_key.serialize_der().into());
+        let server = rustls::ServerConfig::builder_with_provider(web_transport_quinn::crypto::default_provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![leaf.der().clone()], key)
+            .unwrap();
+        (ca.pem(), server)
+    }
+
+    /// Run a TLS handshake in memory, returning the first error either side hits
+    fn handshake(client: rustls::ClientConfig, server: rustls::ServerConfig) -> Result<(), rustls::Error> {
+        let mut client = rustls::ClientConnection::new(Arc::new(client), ServerName::try_from("localhost").unwrap())?;
+        let mut server = rustls::ServerConnection::new(Arc::new(server))?;
+        while client.is_handshaking() || server.is_handshaking() {
+            let mut flight = Vec::new();
+            while client.wants_write() {
+                client.write_tls(&mut flight).unwrap();
+            }
+            let mut data = &flight[..];
+            while !data.is_empty() {
+                server.read_tls(&mut data).unwrap();
+                server.process_new_packets()?;
+            }
+
+            let mut flight = Vec::new();
+            while server.wants_write() {
+                server.write_tls(&mut flight).unwrap();
+            }
+            let mut data = &flight[..];
+            while !data.is_empty() {
+                client.read_tls(&mut data).unwrap();
+                client.process_new_packets()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Client config trusting only the PEM bundles given
+    fn custom_only_client(ca_pems: &[&str]) -> Result<rustls::ClientConfig, String> {
+        let _ = CryptoProvider::install_default((*web_transport_quinn::crypto::default_provider()).clone());
+        let config_id = moq_tls_config_new();
+        assert_eq!(moq_tls_config_set_trust_mode(config_id, MOQ_TRUST_CUSTOM_ONLY), 0);
+        for pem in ca_pems {
+            assert_eq!(moq_tls_config_add_ca_pem(config_id, pem.as_ptr(), pem.len()), 1);
+        }
+        let config = lookup(config_id).unwrap();
+        assert_eq!(moq_tls_config_free(config_id), 0);
+        client_crypto(false, config.as_ref(), "test").map(|(client, _)| client)
+    }
+
+    #[test]
+    fn custom_ca_is_trusted() {
+        let (ca_pem, server) = ca_and_server("Test CA");
+        let client = custom_only_client(&[&ca_pem]).unwrap();
+        assert!(handshake(client, server).is_ok());
+    }
+
+    #[test]
+    fn custom_only_trust_rejects_other_issuers() {
+        let (_, server) = ca_and_server("Test CA");
+        let (other_ca_pem, _) = ca_and_server("Other CA");
+        let client = custom_only_client(&[&other_ca_pem]).unwrap();
+        assert_eq!(
+            handshake(client, server),
+            Err(rustls::Error::InvalidCertificate(rustls::CertificateError::UnknownIssuer))
+        );
+    }
+
+    #[test]
+    fn custom_only_trust_needs_a_ca() {
+        assert!(custom_only_client(&[]).is_err());
+    }
+
+    #[test]
+    fn invalid_pem_is_rejected() {
+        let config_id = moq_tls_config_new();
+        let garbage = b"-----BEGIN CERTIFICATE-----\nnot base64\n-----END CERTIFICATE-----\n";
+        assert_eq!(moq_tls_config_add_ca_pem(config_id, garbage.as_ptr(), garbage.len()), -3);
+        let no_certs = b"just text";
+        assert_eq!(moq_tls_config_add_ca_pem(config_id, no_certs.as_ptr(), no_certs.len()), -3);
+        assert_eq!(moq_tls_config_add_ca_pem(u64::MAX, no_certs.as_ptr(), no_certs.len()), -1);
+        assert_eq!(moq_tls_config_set_trust_mode(config_id, 7), -2);
+        assert_eq!(moq_tls_config_free(config_id), 0);
+        assert_eq!(moq_tls_config_free(config_id), -1);
+        assert!(lookup(config_id).is_err());
+    }
+}
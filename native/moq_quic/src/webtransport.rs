@@ -1,37 +1,48 @@
 // MoQ WebTransport support
 // Uses web-transport-quinn crate for WebTransport over HTTP/3
 
-use web_transport_quinn::{Session, Client as WebTransportClient, SendStream};
+use web_transport_quinn::{Session, SendStream};
 use web_transport_quinn::proto::ConnectRequest;
 use quinn::{Endpoint, ClientConfig, TokioRuntime, EndpointConfig, TransportConfig};
 use quinn::crypto::rustls::QuicClientConfig;
-use rustls::pki_types::{ServerName, CertificateDer, UnixTime};
 use dashmap::DashMap;
 use once_cell::sync::OnceCell;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::runtime::Runtime;
+use tokio::sync::oneshot;
 use std::slice;
 use std::ffi::c_char;
 use std::collections::VecDeque;
 use std::sync::Mutex;
 use log;
-use std::fs::OpenOptions;
-use std::io::Write;
-
-/// Write debug message to log file
-fn debug_log(msg: &str) {
-    if let Ok(mut file) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("/tmp/moq_webtransport.log")
-    {
-        let _ = writeln!(file, "{}", msg);
-    }
-}
-
-// Maximum receive buffer size per session
-const MAX_RECV_BUFFER_SIZE: usize = 64 * 1024; // 64KB
+use crate::close::{self, MoqCloseInfo, MOQ_CLOSE_KIND_APPLICATION};
+use crate::connect_request::{self, MoqConnectRequest};
+use crate::events::{
+    EventQueue, MoqEvent, MOQ_BUFFER_BIDI_STREAM, MOQ_BUFFER_CONTROL, MOQ_BUFFER_DATAGRAM, MOQ_BUFFER_DATA_STREAM,
+    MOQ_EVENT_BIDI_STREAM_FINISHED, MOQ_EVENT_BIDI_STREAM_OPENED, MOQ_EVENT_BIDI_STREAM_RESET,
+    MOQ_EVENT_CONTROL_STREAM_FINISHED, MOQ_EVENT_CONTROL_STREAM_READY, MOQ_EVENT_CONTROL_STREAM_RESET,
+    MOQ_EVENT_DATAGRAM_AVAILABLE, MOQ_EVENT_DATA_STREAM_FINISHED, MOQ_EVENT_DATA_STREAM_OPENED,
+    MOQ_EVENT_DATA_STREAM_RESET, MOQ_EVENT_STREAM_STOPPED,
+};
+use crate::datagram::{self, DatagramQueue};
+use crate::ffi_text;
+use crate::happy_eyeballs;
+use crate::options::{self, MoqConnectOptions};
+use crate::push::{self, MoqReceiveCallback};
+use crate::receive_buffer::{self, ReceiveBuffer};
+use crate::stats::{MoqConnectionStats, StatsTracker};
+use crate::stream_handles::{MoqStreamInfo, StreamHandles};
+use crate::stream_state::{
+    IncomingStream, MOQ_STREAM_STATE_FAILED, MOQ_STREAM_STATE_FINISHED, MOQ_STREAM_STATE_RESET,
+    MOQ_STREAM_STATE_STOPPED,
+};
+use crate::stream_writer::{self, SharedSendStream, StreamDataCallback};
+use crate::pending_connect::{PendingConnect, MOQ_CONNECT_CONNECTED, MOQ_CONNECT_FAILED};
+use crate::tls;
+
+// Default cap on received datagrams waiting for recv_datagram
+const MAX_QUEUED_DATAGRAMS: usize = 1000;
 // Maximum error message length
 const MAX_ERROR_LEN: usize = 512;
 
@@ -43,50 +54,22 @@ struct ControlStream {
     send: SendStream,
 }
 
-// Data stream storage for unidirectional streams
-static WT_DATA_STREAMS: OnceCell<DashMap<(u64, u64), Arc<tokio::sync::Mutex<SendStream>>>> = OnceCell::new();
-static WT_NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+// Shared handles to per-stream and per-session state
+type SharedDataStream = Arc<SharedSendStream<SendStream>>;
+type SharedDatagramBuffer = Arc<tokio::sync::Mutex<DatagramQueue>>;
+type SharedStreamState = Arc<IncomingStream<u32>>;
+type SharedReceiveBuffer = Arc<tokio::sync::Mutex<ReceiveBuffer>>;
 
-// Receive buffer for incoming data
-struct ReceiveBuffer {
-    data: VecDeque<u8>,
-    max_size: usize,
+// Receive half of a bidirectional stream
+struct BidiRecvStream {
+    buffer: SharedReceiveBuffer,
+    state: SharedStreamState,
 }
 
-impl ReceiveBuffer {
-    fn new(max_size: usize) -> Self {
-        Self {
-            data: VecDeque::with_capacity(1024),
-            max_size,
-        }
-    }
-
-    fn push(&mut self, bytes: &[u8]) -> usize {
-        let available = self.max_size - self.data.len();
-        let to_copy = bytes.len().min(available);
-        for &byte in &bytes[..to_copy] {
-            self.data.push_back(byte);
-        }
-        to_copy
-    }
-
-    fn pop(&mut self, buf: &mut [u8]) -> usize {
-        let to_read = buf.len().min(self.data.len());
-        for i in 0..to_read {
-            buf[i] = self.data.pop_front().unwrap();
-        }
-        to_read
-    }
-
-    #[allow(dead_code)]
-    fn len(&self) -> usize {
-        self.data.len()
-    }
-
-    fn is_empty(&self) -> bool {
-        self.data.is_empty()
-    }
-}
+// Data stream storage for unidirectional streams
+static WT_DATA_STREAMS: OnceCell<DashMap<(u64, u64), SharedDataStream>> = OnceCell::new();
+// Stream handle allocators (session_id -> handles)
+static WT_STREAM_HANDLES: OnceCell<DashMap<u64, Arc<StreamHandles>>> = OnceCell::new();
 
 // Data chunk with stream metadata for incoming unidirectional streams
 #[repr(C)]
@@ -96,92 +79,46 @@ pub struct DataStreamChunk {
     pub is_complete: bool,  // true if stream was closed after this data
 }
 
-// Queue of received data stream chunks (each entry is stream_id + data)
-struct DataStreamQueue {
-    chunks: VecDeque<(u64, Vec<u8>, bool)>,  // (stream_id, data, is_complete)
-    max_chunks: usize,
-}
-
-impl DataStreamQueue {
-    fn new(max_chunks: usize) -> Self {
-        Self {
-            chunks: VecDeque::with_capacity(32),
-            max_chunks,
-        }
-    }
-
-    fn push(&mut self, stream_id: u64, data: Vec<u8>, is_complete: bool) -> bool {
-        if self.chunks.len() >= self.max_chunks {
-            return false;
-        }
-        self.chunks.push_back((stream_id, data, is_complete));
-        true
-    }
-
-    fn pop(&mut self) -> Option<(u64, Vec<u8>, bool)> {
-        self.chunks.pop_front()
-    }
-
-    fn is_empty(&self) -> bool {
-        self.chunks.is_empty()
-    }
+// Incoming unidirectional streams of a session, in arrival order
+#[derive(Default)]
+struct ActiveDataStreams {
+    stream_ids: Vec<u64>,
+    // Where moq_webtransport_recv_data resumes, so every stream gets its turn
+    next: usize,
 }
 
 // Global registry for WebTransport sessions
 static WT_SESSIONS: OnceCell<DashMap<u64, Arc<Session>>> = OnceCell::new();
 static WT_ENDPOINTS: OnceCell<DashMap<u64, Arc<Endpoint>>> = OnceCell::new();
 static WT_RECV_BUFFERS: OnceCell<DashMap<u64, Arc<tokio::sync::Mutex<ReceiveBuffer>>>> = OnceCell::new();
-static WT_DATA_QUEUES: OnceCell<DashMap<u64, Arc<tokio::sync::Mutex<DataStreamQueue>>>> = OnceCell::new();
 static WT_CONTROL_STREAMS: OnceCell<DashMap<u64, Arc<tokio::sync::Mutex<Option<ControlStream>>>>> = OnceCell::new();
 // Global registry of datagram receive buffers (session_id -> buffer of complete datagrams)
-static WT_DATAGRAM_BUFFERS: OnceCell<DashMap<u64, Arc<tokio::sync::Mutex<VecDeque<Vec<u8>>>>>> = OnceCell::new();
+static WT_DATAGRAM_BUFFERS: OnceCell<DashMap<u64, SharedDatagramBuffer>> = OnceCell::new();
+// Global registry of the addresses tried to establish each session (session_id -> report)
+static WT_CONNECT_ATTEMPTS: OnceCell<DashMap<u64, String>> = OnceCell::new();
 static WT_RUNTIME: OnceCell<Runtime> = OnceCell::new();
 static WT_NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
-static WT_NEXT_INCOMING_STREAM_ID: AtomicU64 = AtomicU64::new(1);
-
-/// No certificate verification for testing
-#[derive(Debug)]
-struct NoVerification;
-
-impl rustls::client::danger::ServerCertVerifier for NoVerification {
-    fn verify_server_cert(
-        &self,
-        _end_entity: &CertificateDer<'_>,
-        _intermediates: &[CertificateDer<'_>],
-        _server_name: &ServerName<'_>,
-        _ocsp_response: &[u8],
-        _now: UnixTime,
-    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
-        Ok(rustls::client::danger::ServerCertVerified::assertion())
-    }
-
-    fn verify_tls12_signature(
-        &self,
-        _message: &[u8],
-        _cert: &CertificateDer<'_>,
-        _dss: &rustls::DigitallySignedStruct,
-    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
-        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
-    }
-
-    fn verify_tls13_signature(
-        &self,
-        _message: &[u8],
-        _cert: &CertificateDer<'_>,
-        _dss: &rustls::DigitallySignedStruct,
-    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
-        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
-    }
-
-    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
-        vec![
-            rustls::SignatureScheme::RSA_PKCS1_SHA256,
-            rustls::SignatureScheme::ECDSA_NISTP256_SHA256,
-            rustls::SignatureScheme::ED25519,
-            rustls::SignatureScheme::RSA_PSS_SHA256,
-        ]
-    }
-}
+// Receive buffers of incoming unidirectional streams ((session_id, stream_id) -> buffer)
+static WT_DATA_STREAM_BUFFERS: OnceCell<DashMap<(u64, u64), SharedReceiveBuffer>> = OnceCell::new();
+// Incoming unidirectional streams not yet closed (session_id -> streams)
+static WT_ACTIVE_DATA_STREAMS: OnceCell<DashMap<u64, Arc<Mutex<ActiveDataStreams>>>> = OnceCell::new();
+// Final state of incoming unidirectional streams ((session_id, stream_id) -> state)
+static WT_DATA_STREAM_STATES: OnceCell<DashMap<(u64, u64), SharedStreamState>> = OnceCell::new();
+// Receive halves of bidirectional streams ((session_id, stream_id) -> stream)
+static WT_BIDI_RECV_STREAMS: OnceCell<DashMap<(u64, u64), BidiRecvStream>> = OnceCell::new();
+// Peer-opened bidirectional streams not yet accepted (session_id -> stream_ids)
+static WT_PENDING_BIDI_STREAMS: OnceCell<DashMap<u64, Arc<Mutex<VecDeque<u64>>>>> = OnceCell::new();
+// Connect options (session_id -> options), for streams opened later
+static WT_SESSION_OPTIONS: OnceCell<DashMap<u64, MoqConnectOptions>> = OnceCell::new();
+// Global registry of push delivery callbacks (session_id -> callback)
+static WT_RECEIVE_CALLBACKS: OnceCell<DashMap<u64, Arc<dyn StreamDataCallback>>> = OnceCell::new();
+// Global registry of lifecycle event queues (session_id -> queue)
+static WT_EVENT_QUEUES: OnceCell<DashMap<u64, Arc<EventQueue>>> = OnceCell::new();
+// Global registry of per-session statistics (session_id -> tracker)
+static WT_SESSION_STATS: OnceCell<DashMap<u64, Arc<StatsTracker>>> = OnceCell::new();
+// Global registry of connects started with moq_webtransport_connect_start (handle -> progress)
+static WT_PENDING_CONNECTS: OnceCell<DashMap<u64, Arc<PendingConnect>>> = OnceCell::new();
+static WT_NEXT_PENDING_CONNECT_ID: AtomicU64 = AtomicU64::new(1);
 
 /// Get the global Tokio runtime
 fn get_runtime() -> &'static Runtime {
@@ -203,8 +140,14 @@ pub extern "C" fn moq_webtransport_init() {
     if WT_RECV_BUFFERS.set(DashMap::new()).is_err() {
         log::warn!("WebTransport receive buffers registry already initialized");
     }
-    if WT_DATA_QUEUES.set(DashMap::new()).is_err() {
-        log::warn!("WebTransport data queues registry already initialized");
+    if WT_DATA_STREAM_BUFFERS.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport data stream buffers registry already initialized");
+    }
+    if WT_ACTIVE_DATA_STREAMS.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport active data streams registry already initialized");
+    }
+    if WT_DATA_STREAM_STATES.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport data stream states registry already initialized");
     }
     if WT_CONTROL_STREAMS.set(DashMap::new()).is_err() {
         log::warn!("WebTransport control streams registry already initialized");
@@ -215,6 +158,33 @@ pub extern "C" fn moq_webtransport_init() {
     if WT_DATAGRAM_BUFFERS.set(DashMap::new()).is_err() {
         log::warn!("WebTransport datagram buffers registry already initialized");
     }
+    if WT_BIDI_RECV_STREAMS.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport bidirectional streams registry already initialized");
+    }
+    if WT_PENDING_BIDI_STREAMS.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport pending bidirectional streams registry already initialized");
+    }
+    if WT_STREAM_HANDLES.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport stream handles registry already initialized");
+    }
+    if WT_SESSION_OPTIONS.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport session options registry already initialized");
+    }
+    if WT_RECEIVE_CALLBACKS.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport receive callbacks registry already initialized");
+    }
+    if WT_EVENT_QUEUES.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport event queues registry already initialized");
+    }
+    if WT_SESSION_STATS.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport session statistics registry already initialized");
+    }
+    if WT_CONNECT_ATTEMPTS.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport connect attempts registry already initialized");
+    }
+    if WT_PENDING_CONNECTS.set(DashMap::new()).is_err() {
+        log::warn!("WebTransport pending connects registry already initialized");
+    }
     if LAST_ERROR.set(Mutex::new(Vec::new())).is_err() {
         log::warn!("WebTransport last error buffer already initialized");
     }
@@ -232,7 +202,19 @@ fn set_last_error(msg: &str) {
     }
 }
 
+/// Get the push delivery callback for a session, if one is registered
+fn receive_callback(session_id: u64) -> Option<Arc<dyn StreamDataCallback>> {
+    WT_RECEIVE_CALLBACKS.get()?.get(&session_id).map(|cb| cb.clone())
+}
+
+/// Describe the TLS alert that closed a session's QUIC connection, if any
+fn session_alert_reason(session: &Session) -> Option<String> {
+    let connection: &quinn::Connection = session;
+    connection.close_reason().and_then(|e| tls::connection_alert_reason(&e))
+}
+
 /// Set the runtime for WebTransport (shared with main module)
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_webtransport_set_runtime(runtime_ptr: *const Runtime) {
     if !runtime_ptr.is_null() {
@@ -245,10 +227,16 @@ pub extern "C" fn moq_webtransport_set_runtime(runtime_ptr: *const Runtime) {
 
 /// Connect to a WebTransport server
 ///
+/// As with `moq_quic_connect`, every resolved address is raced (RFC 8305);
+/// see `moq_webtransport_get_connect_attempts`. If the connect fails, the
+/// attempts are listed in the last error instead.
+///
 /// # Arguments
 /// * `host` - The hostname to connect to (must be null-terminated)
 /// * `port` - The port to connect to
 /// * `path` - The URL path for WebTransport (e.g., "/moq") (must be null-terminated)
+/// * `protocol` - WebTransport subprotocol to request; use
+///   `moq_webtransport_connect_url` to offer several
 /// * `insecure` - If non-zero, skip certificate verification
 /// * `out_session_id` - Output parameter for the session ID
 ///
@@ -263,235 +251,769 @@ pub extern "C" fn moq_webtransport_connect(
     insecure: u8,
     out_session_id: *mut u64,
 ) -> i32 {
-    let host_str = unsafe {
-        if host.is_null() {
-            return -1;
-        }
-        match std::ffi::CStr::from_ptr(host).to_str() {
-            Ok(s) => s.to_string(),
-            Err(_) => return -2,
-        }
-    };
+    moq_webtransport_connect_with_options(host, port, path, protocol, insecure, std::ptr::null(), out_session_id)
+}
 
-    let path_str = unsafe {
-        if path.is_null() {
-            return -1;
-        }
-        match std::ffi::CStr::from_ptr(path).to_str() {
-            Ok(s) => s.to_string(),
-            Err(_) => return -2,
-        }
+/// Connect to a WebTransport server with caller-supplied transport parameters
+///
+/// Same as `moq_webtransport_connect`, but the QUIC transport parameters and
+/// congestion controller can be overridden. `data_recv_buffer_size` sizes
+/// the receive buffer of each incoming unidirectional stream.
+///
+/// # Arguments
+/// * `options` - Optional pointer to connect options (null or zeroed fields keep the defaults)
+///
+/// # Returns
+/// * 0 on success, negative error code on failure (-3 for invalid options,
+///   -9 if the server certificate does not match a pinned hash)
+#[no_mangle]
+pub extern "C" fn moq_webtransport_connect_with_options(
+    host: *const c_char,
+    port: u16,
+    path: *const c_char,
+    protocol: *const c_char,
+    insecure: u8,
+    options: *const MoqConnectOptions,
+    out_session_id: *mut u64,
+) -> i32 {
+    let (host_str, path_str, protocol_str) = match parse_connect_args(host, path, protocol) {
+        Ok(args) => args,
+        Err(e) => return e,
     };
-
-    let protocol_str = unsafe {
-        if protocol.is_null() {
-            return -1;
-        }
-        match std::ffi::CStr::from_ptr(protocol).to_str() {
-            Ok(s) => s.to_string(),
-            Err(_) => return -2,
-        }
+    let target = match ConnectTarget::from_parts(&host_str, port, &path_str) {
+        Ok(target) => target,
+        Err(e) => return e,
     };
 
-    // Create runtime if not exists
-    if WT_RUNTIME.get().is_none() {
-        WT_RUNTIME.set(Runtime::new().expect("Failed to create Tokio runtime"))
-            .expect("Failed to set runtime");
-    }
+    connect_blocking(target, vec![protocol_str], Vec::new(), insecure, options, out_session_id)
+}
 
-    let runtime = get_runtime();
+/// Connect to a WebTransport server given the full session URL
+///
+/// The URL is sent as is in the CONNECT request, query string included,
+/// together with the request's extra header fields. Header names are sent
+/// lowercased, and surrounding spaces and tabs are trimmed from values. The
+/// server's response headers can be read with
+/// `moq_webtransport_get_response_headers`.
+///
+/// The subprotocols are offered in `wt-available-protocols` in the order
+/// given, and the server's pick is reported by
+/// `moq_webtransport_get_protocol`. The session comes up without a protocol
+/// if the server supports none of them.
+///
+/// # Arguments
+/// * `request` - The URL, optional SNI and address overrides, subprotocols
+///   and header fields
+/// * `insecure` - Skip certificate verification if non-zero
+/// * `options` - Optional pointer to connect options (null or zeroed fields keep the defaults)
+/// * `out_session_id` - Output parameter for the session ID
+///
+/// # Returns
+/// * 0 on success, negative error code on failure (same codes as
+///   `moq_webtransport_connect_with_options`; -8 if the URL or address is
+///   invalid; -10 if a header field or protocol name is rejected, see
+///   `MoqConnectRequest`)
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_webtransport_connect_url(
+    request: *const MoqConnectRequest,
+    insecure: u8,
+    options: *const MoqConnectOptions,
+    out_session_id: *mut u64,
+) -> i32 {
+    let SessionRequest { target, protocols, headers } = match read_request(request) {
+        Ok(request) => request,
+        Err(e) => return e,
+    };
 
-    let result = runtime.block_on(async {
-        // Build URL for WebTransport
-        let url = format!("https://{}:{}{}", host_str, port, path_str);
-        log::info!("Connecting to WebTransport: {}", url);
+    connect_blocking(target, protocols, headers, insecure, options, out_session_id)
+}
 
-        let parsed_url: url::Url = match url.parse() {
-            Ok(u) => u,
-            Err(e) => {
-                log::error!("Failed to parse URL: {:?}", e);
-                return Err(-8);
-            }
-        };
+/// A validated `MoqConnectRequest`
+struct SessionRequest {
+    target: ConnectTarget,
+    // Subprotocols to offer, most preferred first
+    protocols: Vec<String>,
+    // Extra CONNECT header fields
+    headers: Vec<(String, String)>,
+}
 
-        // Create client configuration with cert verification.
-        // WebTransport itself negotiates via its ALPN, while MoQ draft
-        // selection is carried as a WebTransport subprotocol.
-        let mut crypto = if insecure != 0 {
-            rustls::ClientConfig::builder()
-                .dangerous()
-                .with_custom_certificate_verifier(Arc::new(NoVerification))
-                .with_no_client_auth()
-        } else {
-            // Load system root certificates for TLS validation
-            let mut certs = rustls::RootCertStore::empty();
-            let native_certs_result = rustls_native_certs::load_native_certs();
-            if let Some(ref e) = native_certs_result.errors.first() {
-                log::warn!("Error loading some native certs: {:?}", e);
-            }
-            for cert in native_certs_result.certs {
-                if let Err(e) = certs.add(cert) {
-                    log::warn!("Failed to add native cert: {:?}", e);
-                }
-            }
-            log::info!("Loaded {} system root certificates for WebTransport", certs.len());
-            rustls::ClientConfig::builder()
-                .with_root_certificates(certs)
-                .with_no_client_auth()
-        };
-        crypto.alpn_protocols = vec![web_transport_quinn::ALPN.as_bytes().to_vec()];
+/// Read a connect request passed over FFI
+///
+/// # Returns
+/// * The FFI error code if the request is null or invalid
+fn read_request(request: *const MoqConnectRequest) -> Result<SessionRequest, i32> {
+    if request.is_null() {
+        return Err(-1);
+    }
+    let request = unsafe { &*request };
+    let target = url_target(request.url, request.sni, request.address)?;
+    let protocols =
+        connect_request::parse_protocols(request.protocols, request.protocol_count).map_err(|e| invalid_request(&e))?;
+    let headers = connect_request::parse(request.headers, request.header_count).map_err(|e| invalid_request(&e))?;
+    Ok(SessionRequest { target, protocols, headers })
+}
 
-        let quic_crypto = match QuicClientConfig::try_from(crypto.clone()) {
-            Ok(c) => c,
-            Err(e) => {
-                let err_msg = format!("QuicClientConfig error: {}", e);
-                log::error!("{}", err_msg);
-                set_last_error(&err_msg);
-                return Err(-6);
-            }
-        };
+/// Read the URL and the optional SNI and address overrides passed over FFI
+fn url_target(url: *const c_char, sni: *const c_char, address: *const c_char) -> Result<ConnectTarget, i32> {
+    let url_str = parse_c_str(url)?;
+    let optional = |ptr: *const c_char| if ptr.is_null() { Ok(None) } else { parse_c_str(ptr).map(Some) };
+    let sni_str = optional(sni)?.filter(|s| !s.is_empty());
+    let address_str = optional(address)?.filter(|a| !a.is_empty());
+    ConnectTarget::from_url(&url_str, sni_str, address_str.as_deref())
+}
 
-        // Build transport config with datagram support (RFC 9221)
-        let mut transport = TransportConfig::default();
-        transport.datagram_receive_buffer_size(Some(65536));
-        transport.datagram_send_buffer_size(65536);
+/// Report invalid request headers or subprotocols
+///
+/// # Returns
+/// * The FFI error code, -10
+fn invalid_request(err_msg: &str) -> i32 {
+    log::error!("{}", err_msg);
+    set_last_error(err_msg);
+    -10
+}
 
-        let mut client_config = ClientConfig::new(Arc::new(quic_crypto));
-        client_config.transport_config(Arc::new(transport));
+/// Establish and register a session, blocking the caller until it is ready
+fn connect_blocking(
+    target: ConnectTarget,
+    protocols: Vec<String>,
+    headers: Vec<(String, String)>,
+    insecure: u8,
+    options: *const MoqConnectOptions,
+    out_session_id: *mut u64,
+) -> i32 {
+    let options = MoqConnectOptions::from_ptr(options);
 
-        // Create endpoint
-        let socket = match std::net::UdpSocket::bind("0.0.0.0:0") {
-            Ok(s) => s,
-            Err(e) => {
-                let err_msg = format!("UDP bind error: {}", e);
-                log::error!("{}", err_msg);
-                set_last_error(&err_msg);
-                return Err(-5);
-            }
-        };
+    let runtime = init_runtime();
 
-        let mut endpoint = match Endpoint::new(
-            EndpointConfig::default(),
-            None,
-            socket,
-            Arc::new(TokioRuntime),
-        ) {
-            Ok(e) => e,
-            Err(e) => {
-                let err_msg = format!("Endpoint creation error: {}", e);
-                log::error!("{}", err_msg);
-                set_last_error(&err_msg);
-                return Err(-6);
-            }
-        };
+    let result = runtime.block_on(establish_session(
+        target,
+        protocols,
+        headers,
+        insecure != 0,
+        options,
+        None,
+    ));
 
-        endpoint.set_default_client_config(client_config.clone());
+    let (session, endpoint, attempts) = match result {
+        Ok(established) => established,
+        Err(e) => return e,
+    };
 
-        // Connect using WebTransport
-        let client = WebTransportClient::new(endpoint.clone(), client_config);
+    let session_id = register_session(session, endpoint, attempts, &options);
 
-        let request = ConnectRequest::new(parsed_url).with_protocol(protocol_str);
+    unsafe {
+        *out_session_id = session_id;
+    }
 
-        match client.connect(request).await {
-            Ok(session) => {
-                log::info!("WebTransport session established");
-                Ok((session, endpoint))
-            }
-            Err(e) => {
-                let err_msg = format!("WebTransport connection failed: {} (URL: {})", e, url);
-                log::error!("{}", err_msg);
-                set_last_error(&err_msg);
-                Err(-7)
+    0
+}
+
+/// Start a WebTransport session without blocking the caller
+///
+/// Takes the same arguments as `moq_webtransport_connect_url`, but returns
+/// as soon as the connect task is spawned. Poll the handle with
+/// `moq_webtransport_connect_poll` and release it with
+/// `moq_webtransport_connect_free`.
+///
+/// # Arguments
+/// * `out_handle` - Output parameter for the pending connect handle
+///
+/// # Returns
+/// * 0 on success, negative error code if the arguments are invalid
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_webtransport_connect_start(
+    request: *const MoqConnectRequest,
+    insecure: u8,
+    options: *const MoqConnectOptions,
+    out_handle: *mut u64,
+) -> i32 {
+    if out_handle.is_null() {
+        return -1;
+    }
+    let SessionRequest { target, protocols, headers } = match read_request(request) {
+        Ok(request) => request,
+        Err(e) => return e,
+    };
+    let options = MoqConnectOptions::from_ptr(options);
+
+    let runtime = init_runtime();
+    let pending_connects = WT_PENDING_CONNECTS.get().expect("Pending connects not initialized");
+
+    let handle = WT_NEXT_PENDING_CONNECT_ID.fetch_add(1, Ordering::SeqCst);
+    let progress = Arc::new(PendingConnect::new());
+    pending_connects.insert(handle, progress.clone());
+
+    let progress_for_task = progress.clone();
+    let task = runtime.spawn(async move {
+        let result = establish_session(
+            target,
+            protocols,
+            headers,
+            insecure != 0,
+            options,
+            Some(&progress_for_task),
+        )
+        .await;
+
+        match result {
+            Ok((session, endpoint, attempts)) => {
+                let session_for_cancel = session.clone();
+                let connected =
+                    progress_for_task.complete(|| register_session(session, endpoint, attempts, &options));
+                if !connected {
+                    log::info!("Pending connect {} was cancelled, closing session", handle);
+                    session_for_cancel.close(0, b"cancelled");
+                }
             }
+            Err(e) => progress_for_task.fail(e),
         }
     });
+    progress.set_task(task.abort_handle());
 
-    let (session, endpoint) = match result {
-        Ok((s, e)) => (s, e),
-        Err(e) => return e,
+    unsafe {
+        *out_handle = handle;
+    }
+    0
+}
+
+/// Poll a connect started with `moq_webtransport_connect_start`
+///
+/// # Arguments
+/// * `handle` - The pending connect handle
+/// * `out_session_id` - Set to the session ID once connected (may be null)
+/// * `out_error` - Set to the connect error code once failed (may be null)
+///
+/// # Returns
+/// * One of the `MOQ_CONNECT_*` states, or -1 if the handle is not found
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_webtransport_connect_poll(
+    handle: u64,
+    out_session_id: *mut u64,
+    out_error: *mut i32,
+) -> i32 {
+    let pending_connects = WT_PENDING_CONNECTS.get().expect("Pending connects not initialized");
+
+    let progress = match pending_connects.get(&handle) {
+        Some(p) => p.clone(),
+        None => return -1,
     };
 
-    // Allocate session ID
-    let session_id = WT_NEXT_SESSION_ID.fetch_add(1, Ordering::SeqCst);
+    let (state, result) = progress.poll();
+    unsafe {
+        if state == MOQ_CONNECT_CONNECTED && !out_session_id.is_null() {
+            *out_session_id = result as u64;
+        }
+        if state == MOQ_CONNECT_FAILED && !out_error.is_null() {
+            *out_error = result as i32;
+        }
+    }
+    state
+}
 
-    // Store session and endpoint
-    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
-    let endpoints = WT_ENDPOINTS.get().expect("Endpoints not initialized");
-    let recv_buffers = WT_RECV_BUFFERS.get().expect("Receive buffers not initialized");
-    let data_queues = WT_DATA_QUEUES.get().expect("Data queues not initialized");
-    let control_streams = WT_CONTROL_STREAMS.get().expect("Control streams not initialized");
+/// Cancel a connect started with `moq_webtransport_connect_start`
+///
+/// A connect that already completed is left alone; if it connected, the
+/// session still belongs to the caller and must be closed normally.
+///
+/// # Returns
+/// * The state the connect was in before the call, or -1 if the handle is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_connect_cancel(handle: u64) -> i32 {
+    let pending_connects = WT_PENDING_CONNECTS.get().expect("Pending connects not initialized");
 
-    let session_arc = Arc::new(session);
-    let endpoint_arc = Arc::new(endpoint);
-    let recv_buffer = Arc::new(tokio::sync::Mutex::new(ReceiveBuffer::new(MAX_RECV_BUFFER_SIZE)));
-    let data_queue = Arc::new(tokio::sync::Mutex::new(DataStreamQueue::new(1024)));  // Queue up to 1024 chunks
+    match pending_connects.get(&handle) {
+        Some(progress) => progress.cancel(),
+        None => -1,
+    }
+}
 
-    sessions.insert(session_id, session_arc.clone());
-    endpoints.insert(session_id, endpoint_arc);
-    recv_buffers.insert(session_id, recv_buffer.clone());
-    data_queues.insert(session_id, data_queue.clone());
-    control_streams.insert(session_id, Arc::new(tokio::sync::Mutex::new(None)));
+/// Get the addresses tried by a connect started with `moq_webtransport_connect_start`
+///
+/// Same as `moq_quic_connect_get_attempts`.
+///
+/// # Returns
+/// * Number of bytes written (truncated to fit), 0 if no address has been
+///   tried yet, -1 if the handle is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_connect_get_attempts(
+    handle: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i32 {
+    let pending_connects = WT_PENDING_CONNECTS.get().expect("Pending connects not initialized");
 
-    // Open bidirectional control stream (required by MoQ spec)
-    let control_stream_for_opening = session_arc.clone();
-    let recv_buffer_for_control = recv_buffer.clone();
-    let runtime = get_runtime();
-    runtime.spawn(async move {
-        log::info!("Opening bidirectional control stream for session {}", session_id);
-        match control_stream_for_opening.open_bi().await {
-            Ok((send, mut recv)) => {
-                log::info!("Bidirectional control stream opened for session {}", session_id);
-                let control_streams = WT_CONTROL_STREAMS.get().expect("Control streams not initialized");
+    match pending_connects.get(&handle) {
+        Some(progress) => ffi_text::copy_text(&progress.attempts(), buffer, buffer_len),
+        None => -1,
+    }
+}
 
-                // Store the send stream for sending control messages
-                if let Some(ctrl_stream_mutex) = control_streams.get(&session_id) {
-                    *ctrl_stream_mutex.lock().await = Some(ControlStream { send });
-                }
+/// Release a pending connect handle, cancelling the connect if still in flight
+///
+/// # Returns
+/// * 0 on success, -1 if the handle is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_connect_free(handle: u64) -> i32 {
+    let pending_connects = WT_PENDING_CONNECTS.get().expect("Pending connects not initialized");
 
-                // Start reading from the control stream's receive side
-                let mut buffer = vec![0u8; 4096];
-                loop {
-                    match recv.read(&mut buffer).await {
-                        Ok(None) => {
-                            log::debug!("Control stream closed for session {}", session_id);
-                            break;
-                        }
-                        Ok(Some(n)) => {
-                            // Add data to receive buffer (control messages don't have stream type prefix)
-                            let mut recv_buf = recv_buffer_for_control.lock().await;
-                            let pushed = recv_buf.push(&buffer[..n]);
-                            if pushed < n {
-                                log::warn!("Receive buffer full, dropped {} bytes", n - pushed);
-                            }
-                            log::trace!("Received {} bytes on control stream for session {}", n, session_id);
-                        }
-                        Err(e) => {
-                            log::error!("Error reading from control stream: {:?}", e);
-                            break;
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                log::error!("Failed to open control stream for session {}: {:?}", session_id, e);
-            }
+    match pending_connects.remove(&handle) {
+        Some((_, progress)) => {
+            progress.cancel();
+            0
         }
-    });
+        None => -1,
+    }
+}
 
-    // Log datagram capability after H3 SETTINGS exchange
-    let wt_max_dgram = session_arc.max_datagram_size();
-    if wt_max_dgram > 0 {
-        log::info!("WebTransport datagrams supported, max payload: {} bytes (after H3 framing overhead)", wt_max_dgram);
+/// Create the WebTransport runtime on first use
+fn init_runtime() -> &'static Runtime {
+    WT_RUNTIME.get_or_init(|| Runtime::new().expect("Failed to create Tokio runtime"))
+}
+
+/// Read a required string argument passed over FFI
+///
+/// # Returns
+/// * -1 if the pointer is null, -2 if the string is not valid UTF-8
+fn parse_c_str(ptr: *const c_char) -> Result<String, i32> {
+    if ptr.is_null() {
+        return Err(-1);
+    }
+    match unsafe { std::ffi::CStr::from_ptr(ptr) }.to_str() {
+        Ok(s) => Ok(s.to_string()),
+        Err(_) => Err(-2),
+    }
+}
+
+/// Validate the host, path and subprotocol passed over FFI
+fn parse_connect_args(
+    host: *const c_char,
+    path: *const c_char,
+    protocol: *const c_char,
+) -> Result<(String, String, String), i32> {
+    Ok((parse_c_str(host)?, parse_c_str(path)?, parse_c_str(protocol)?))
+}
+
+/// Where a session connects to
+struct ConnectTarget {
+    // URL sent in the CONNECT request
+    url: url::Url,
+    // TLS server name
+    server_name: String,
+    // Host name or IP address resolved for the QUIC handshake, and its port
+    host: String,
+    port: u16,
+}
+
+impl ConnectTarget {
+    /// Target for the separate host, port and path of `moq_webtransport_connect`
+    fn from_parts(host: &str, port: u16, path: &str) -> Result<Self, i32> {
+        // IPv6 literals need brackets in the URL, but not for resolving or SNI
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let url = match host.parse::<std::net::Ipv6Addr>() {
+            Ok(_) => format!("https://[{}]:{}{}", host, port, path),
+            Err(_) => format!("https://{}:{}{}", host, port, path),
+        };
+        let url = parse_url(&url)?;
+        Ok(Self { url, server_name: host.to_string(), host: host.to_string(), port })
+    }
+
+    /// Target for `moq_webtransport_connect_url`
+    fn from_url(url: &str, sni: Option<String>, address: Option<&str>) -> Result<Self, i32> {
+        let url = parse_url(url)?;
+        if url.scheme() != "https" {
+            return Err(invalid_target(&format!("WebTransport URL must use https: {}", url)));
+        }
+        let url_host = match url.host() {
+            Some(url::Host::Domain(domain)) => domain.to_string(),
+            Some(url::Host::Ipv4(ip)) => ip.to_string(),
+            Some(url::Host::Ipv6(ip)) => ip.to_string(),
+            None => return Err(invalid_target(&format!("WebTransport URL has no host: {}", url))),
+        };
+        let url_port = url.port_or_known_default().unwrap_or(443);
+
+        let (host, port) = match address {
+            Some(address) => parse_address(address, url_port)?,
+            None => (url_host.clone(), url_port),
+        };
+        let server_name = sni.unwrap_or(url_host);
+        Ok(Self { url, server_name, host, port })
+    }
+}
+
+/// Split an address override into host and port
+///
+/// Accepts a bare host name or IP address (using `default_port`), `host:port`,
+/// `[ipv6]` and `[ipv6]:port`.
+fn parse_address(address: &str, default_port: u16) -> Result<(String, u16), i32> {
+    if let Ok(ip) = address.parse::<std::net::IpAddr>() {
+        return Ok((ip.to_string(), default_port));
+    }
+    if let Ok(addr) = address.parse::<std::net::SocketAddr>() {
+        return Ok((addr.ip().to_string(), addr.port()));
+    }
+    let invalid = || invalid_target(&format!("Invalid connect address: {}", address));
+    if let Some(bracketed) = address.strip_prefix('[') {
+        // "[ipv6]" without a port; "[ipv6]:port" parsed as a SocketAddr above
+        return match bracketed.strip_suffix(']').map(str::parse::<std::net::Ipv6Addr>) {
+            Some(Ok(ip)) => Ok((ip.to_string(), default_port)),
+            _ => Err(invalid()),
+        };
+    }
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && !host.contains(':') => match port.parse() {
+            Ok(port) => Ok((host.to_string(), port)),
+            Err(_) => Err(invalid()),
+        },
+        Some(_) => Err(invalid()),
+        None => Ok((address.to_string(), default_port)),
+    }
+}
+
+fn parse_url(url: &str) -> Result<url::Url, i32> {
+    url.parse().map_err(|e| invalid_target(&format!("Failed to parse URL {}: {}", url, e)))
+}
+
+/// Report an invalid connect URL or address
+///
+/// # Returns
+/// * The FFI error code, -8
+fn invalid_target(err_msg: &str) -> i32 {
+    log::error!("{}", err_msg);
+    set_last_error(err_msg);
+    -8
+}
+
+/// Resolve, race the QUIC handshake and perform the WebTransport CONNECT
+///
+/// Sets the last error and returns the FFI error code on failure.
+async fn establish_session(
+    target: ConnectTarget,
+    protocols: Vec<String>,
+    headers: Vec<(String, String)>,
+    insecure: bool,
+    options: MoqConnectOptions,
+    progress: Option<&PendingConnect>,
+) -> Result<(Session, Endpoint, String), i32> {
+    let ConnectTarget { url: parsed_url, server_name, host: host_str, port } = target;
+    let url = parsed_url.to_string();
+    if host_str == server_name {
+        log::info!("Connecting to WebTransport: {}", url);
+    } else {
+        log::info!("Connecting to WebTransport: {} via {}:{} (SNI {})", url, host_str, port, server_name);
+    }
+
+    // Resolve the hostname; every address is raced below
+    let addrs = match tokio::net::lookup_host((host_str.as_str(), port)).await {
+        Ok(addrs) => addrs,
+        Err(e) => {
+            let err_msg = format!("DNS resolution error for {}:{}: {}", host_str, port, e);
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-4);
+        }
+    };
+    if let Some(progress) = progress {
+        progress.handshaking();
+    }
+
+    // Resolve the TLS trust settings for this session
+    let tls_config = match tls::lookup(options.tls_config_id) {
+        Ok(c) => c,
+        Err(err_msg) => {
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-3);
+        }
+    };
+
+    // Create client configuration with cert verification.
+    // WebTransport itself negotiates via its ALPN, while MoQ draft
+    // selection is carried as a WebTransport subprotocol.
+    let (mut crypto, pin_status) = match tls::client_crypto(insecure, tls_config.as_ref(), "WebTransport") {
+        Ok((c, p)) => (c, p),
+        Err(err_msg) => {
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-6);
+        }
+    };
+    crypto.alpn_protocols = vec![web_transport_quinn::ALPN.as_bytes().to_vec()];
+
+    let quic_crypto = match QuicClientConfig::try_from(crypto.clone()) {
+        Ok(c) => c,
+        Err(e) => {
+            let err_msg = format!("QuicClientConfig error: {}", e);
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-6);
+        }
+    };
+
+    // Build transport config with datagram support (RFC 9221)
+    let mut transport = TransportConfig::default();
+    transport.datagram_receive_buffer_size(Some(65536));
+    transport.datagram_send_buffer_size(datagram::DEFAULT_SEND_BUFFER_SIZE);
+    if let Err(err_msg) = options.apply(&mut transport) {
+        log::error!("{}", err_msg);
+        set_last_error(&err_msg);
+        return Err(-3);
+    }
+
+    let mut client_config = ClientConfig::new(Arc::new(quic_crypto));
+    client_config.transport_config(Arc::new(transport));
+
+    // Create endpoint on a dual-stack socket
+    let socket = match happy_eyeballs::bind_dual_stack_socket() {
+        Ok(s) => s,
+        Err(e) => {
+            let err_msg = format!("UDP bind error: {}", e);
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-5);
+        }
+    };
+
+    let endpoint = match Endpoint::new(
+        EndpointConfig::default(),
+        None,
+        socket,
+        Arc::new(TokioRuntime),
+    ) {
+        Ok(e) => e,
+        Err(e) => {
+            let err_msg = format!("Endpoint creation error: {}", e);
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-6);
+        }
+    };
+
+    // Race the QUIC handshake across every resolved address (RFC 8305)
+    let ipv6_capable = endpoint.local_addr().map(|a| a.is_ipv6()).unwrap_or(false);
+    let addrs = happy_eyeballs::sort_addresses(addrs, ipv6_capable);
+    if addrs.is_empty() {
+        set_last_error("No usable addresses resolved");
+        return Err(-4);
+    }
+
+    let (winner, attempts) = happy_eyeballs::race(&endpoint, client_config, &addrs, &server_name).await;
+    let report = happy_eyeballs::format_attempts(&attempts);
+    if let Some(progress) = progress {
+        progress.set_attempts(report.clone());
+    }
+
+    let connection = match winner {
+        Some((conn, addr)) => {
+            log::info!("QUIC connected to {} after {} attempt(s)", happy_eyeballs::canonical_address(addr), attempts.len());
+            conn
+        }
+        None if pin_status.mismatched() => {
+            set_last_error("Server certificate does not match any pinned hash");
+            return Err(-9);
+        }
+        None => {
+            let err_msg = format!(
+                "WebTransport connection failed: all {} attempt(s) failed: {} (URL: {})",
+                attempts.len(),
+                report.replace('\n', "; "),
+                url,
+            );
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            return Err(-7);
+        }
+    };
+
+    // Perform the HTTP/3 CONNECT on the winning connection
+    let mut request = ConnectRequest::new(parsed_url).with_protocols(protocols);
+    request.headers = headers;
+
+    match Session::connect(connection, request).await {
+        Ok(session) => {
+            log::info!("WebTransport session established (protocol: {:?})", session.response().protocol);
+            Ok((session, endpoint, report))
+        }
+        Err(_) if pin_status.mismatched() => {
+            set_last_error("Server certificate does not match any pinned hash");
+            Err(-9)
+        }
+        Err(e) => {
+            let err_msg = match tls::tls_alert_reason(&e) {
+                Some(alert) => format!("WebTransport connection failed: {} (URL: {})", alert, url),
+                None => format!("WebTransport connection failed: {} (URL: {})", e, url),
+            };
+            log::error!("{}", err_msg);
+            set_last_error(&err_msg);
+            Err(-7)
+        }
+    }
+}
+
+/// Register a freshly established session and start its background tasks
+///
+/// # Returns
+/// * The new session ID
+fn register_session(session: Session, endpoint: Endpoint, attempts: String, options: &MoqConnectOptions) -> u64 {
+    // Allocate session ID
+    let session_id = WT_NEXT_SESSION_ID.fetch_add(1, Ordering::SeqCst);
+
+    // Store session and endpoint
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+    let endpoints = WT_ENDPOINTS.get().expect("Endpoints not initialized");
+    let recv_buffers = WT_RECV_BUFFERS.get().expect("Receive buffers not initialized");
+    let control_streams = WT_CONTROL_STREAMS.get().expect("Control streams not initialized");
+
+    let session_arc = Arc::new(session);
+    let endpoint_arc = Arc::new(endpoint);
+    let recv_buffer = Arc::new(tokio::sync::Mutex::new(ReceiveBuffer::new(
+        options.control_recv_buffer_size(),
+    )));
+    let active_data_streams = Arc::new(Mutex::new(ActiveDataStreams::default()));
+
+    sessions.insert(session_id, session_arc.clone());
+    endpoints.insert(session_id, endpoint_arc);
+    recv_buffers.insert(session_id, recv_buffer.clone());
+    WT_ACTIVE_DATA_STREAMS.get().expect("Active data streams not initialized")
+        .insert(session_id, active_data_streams.clone());
+    control_streams.insert(session_id, Arc::new(tokio::sync::Mutex::new(None)));
+    WT_PENDING_BIDI_STREAMS.get().expect("Pending bidirectional streams not initialized")
+        .insert(session_id, Arc::new(Mutex::new(VecDeque::new())));
+    WT_SESSION_OPTIONS.get().expect("Session options not initialized")
+        .insert(session_id, *options);
+    let stream_handles = Arc::new(StreamHandles::default());
+    WT_STREAM_HANDLES.get().expect("Stream handles not initialized")
+        .insert(session_id, stream_handles.clone());
+    WT_CONNECT_ATTEMPTS.get().expect("Connect attempts not initialized")
+        .insert(session_id, attempts);
+
+    let runtime = get_runtime();
+
+    // Lifecycle events for moq_webtransport_poll_event
+    let events = Arc::new(EventQueue::default());
+    WT_EVENT_QUEUES.get().expect("Event queues not initialized")
+        .insert(session_id, events.clone());
+
+    let session_for_close = session_arc.clone();
+    let events_for_close = events.clone();
+    runtime.spawn(async move {
+        let connection: &quinn::Connection = &session_for_close;
+        let error = connection.closed().await;
+        log::info!("WebTransport session {} closed: {}", session_id, error);
+        let (info, reason) = session_close_info(&error);
+        events_for_close.push_closed(info, reason);
+    });
+
+    // Track statistics quinn does not keep itself
+    let stats_tracker = Arc::new(StatsTracker::default());
+    WT_SESSION_STATS.get().expect("Session statistics not initialized")
+        .insert(session_id, stats_tracker.clone());
+
+    let stall_control = options.control_stalls_when_full();
+    let stall_data = options.data_stalls_when_full();
+    let data_recv_buffer_size = options.data_recv_buffer_size();
+
+    // Open bidirectional control stream (required by MoQ spec)
+    let control_stream_for_opening = session_arc.clone();
+    let recv_buffer_for_control = recv_buffer.clone();
+    let events_for_control = events.clone();
+    let stats_for_control = stats_tracker.clone();
+    runtime.spawn(async move {
+        log::info!("Opening bidirectional control stream for session {}", session_id);
+        match control_stream_for_opening.open_bi().await {
+            Ok((send, mut recv)) => {
+                log::info!("Bidirectional control stream opened for session {}", session_id);
+                let control_streams = WT_CONTROL_STREAMS.get().expect("Control streams not initialized");
+
+                // Store the send stream for sending control messages
+                if let Some(ctrl_stream_mutex) = control_streams.get(&session_id) {
+                    *ctrl_stream_mutex.lock().await = Some(ControlStream { send });
+                }
+                events_for_control.push(MOQ_EVENT_CONTROL_STREAM_READY);
+
+                // Start reading from the control stream's receive side
+                // Chunks are kept as quinn hands them over, up to 64KB each
+                let connection_for_control: &quinn::Connection = &control_stream_for_opening;
+                loop {
+                    // When stalling, only read what fits so the rest stays under flow control
+                    let max_len = if stall_control && receive_callback(session_id).is_none() {
+                        match receive_buffer::wait_for_space(&recv_buffer_for_control, connection_for_control).await {
+                            Some(space) => space.min(receive_buffer::READ_CHUNK_SIZE),
+                            None => break,
+                        }
+                    } else {
+                        receive_buffer::READ_CHUNK_SIZE
+                    };
+                    match recv.read_chunk(max_len, true).await {
+                        Ok(None) => {
+                            log::debug!("Control stream closed for session {}", session_id);
+                            events_for_control.push(MOQ_EVENT_CONTROL_STREAM_FINISHED);
+                            break;
+                        }
+                        Ok(Some(chunk)) => {
+                            let n = chunk.bytes.len();
+                            if let Some(callback) = receive_callback(session_id) {
+                                callback.on_control_data(session_id, &chunk.bytes);
+                                continue;
+                            }
+
+                            // Add data to receive buffer (control messages don't have stream type prefix)
+                            let mut recv_buf = recv_buffer_for_control.lock().await;
+                            let pushed = recv_buf.push_bytes(chunk.bytes);
+                            if pushed < n {
+                                log::warn!("Receive buffer full, dropped {} bytes", n - pushed);
+                                stats_for_control.stream_bytes_dropped(n - pushed);
+                                events_for_control.push_overflow(MOQ_BUFFER_CONTROL, 0, (n - pushed) as u64);
+                            }
+                            log::trace!("Received {} bytes on control stream for session {}", n, session_id);
+                        }
+                        Err(web_transport_quinn::ReadError::Reset(code)) => {
+                            log::warn!("Control stream reset by peer for session {} (code {})", session_id, code);
+                            events_for_control.push_stream(MOQ_EVENT_CONTROL_STREAM_RESET, 0, code.into());
+                            break;
+                        }
+                        Err(e) => {
+                            log::error!("Error reading from control stream: {:?}", e);
+                            // A rejected client certificate only shows up once the server closes
+                            if let Some(alert) = session_alert_reason(&control_stream_for_opening) {
+                                set_last_error(&alert);
+                            }
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to open control stream for session {}: {:?}", session_id, e);
+                if let Some(alert) = session_alert_reason(&control_stream_for_opening) {
+                    set_last_error(&alert);
+                }
+            }
+        }
+    });
+
+    // Log datagram capability after H3 SETTINGS exchange
+    let wt_max_dgram = session_arc.max_datagram_size();
+    if wt_max_dgram > 0 {
+        log::info!("WebTransport datagrams supported, max payload: {} bytes (after H3 framing overhead)", wt_max_dgram);
     } else {
         log::warn!("WebTransport datagrams NOT supported (H3 SETTINGS_H3_DATAGRAM not negotiated or max_datagram_frame_size=0)");
     }
 
     // Initialize datagram buffer for this session
     let datagram_buffers = WT_DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
-    datagram_buffers.insert(session_id, Arc::new(tokio::sync::Mutex::new(VecDeque::new())));
+    let datagram_queue = DatagramQueue::new(
+        options.datagram_queue_capacity(MAX_QUEUED_DATAGRAMS),
+        options.datagram_drops_oldest(),
+    );
+    datagram_buffers.insert(session_id, Arc::new(tokio::sync::Mutex::new(datagram_queue)));
 
     // Start background task to receive datagrams
     let session_for_datagrams = session_arc.clone();
+    let stats_for_datagrams = stats_tracker.clone();
+    let events_for_datagrams = events.clone();
     runtime.spawn(async move {
         log::info!("Starting WebTransport datagram receiver for session {}", session_id);
         let datagram_buffers = WT_DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
@@ -501,12 +1023,20 @@ pub extern "C" fn moq_webtransport_connect(
                 Ok(datagram) => {
                     log::trace!("Received datagram ({} bytes) on WebTransport session {}", datagram.len(), session_id);
 
+                    if let Some(callback) = receive_callback(session_id) {
+                        callback.on_datagram(session_id, &datagram);
+                        continue;
+                    }
+
                     if let Some(buffer) = datagram_buffers.get(&session_id) {
                         let mut buf = buffer.lock().await;
-                        if buf.len() < 1000 {
-                            buf.push_back(datagram.to_vec());
-                        } else {
+                        if !buf.push(datagram) {
                             log::warn!("WebTransport datagram buffer full, dropping datagram");
+                            stats_for_datagrams.datagram_dropped();
+                            events_for_datagrams.push_overflow(MOQ_BUFFER_DATAGRAM, 0, 1);
+                        }
+                        if buf.len() == 1 {
+                            events_for_datagrams.push(MOQ_EVENT_DATAGRAM_AVAILABLE);
                         }
                     }
                 }
@@ -520,62 +1050,42 @@ pub extern "C" fn moq_webtransport_connect(
     });
 
     // Start background task to accept incoming unidirectional streams (data streams)
+    // Each stream gets its own reader, so a long-lived stream does not hold up the others
     let session_for_task = session_arc.clone();
-    let data_queue_for_task = data_queue.clone();
+    let active_streams_for_task = active_data_streams.clone();
+    let events_for_streams = events.clone();
+    let stats_for_streams = stats_tracker.clone();
+    let handles_for_streams = stream_handles.clone();
     runtime.spawn(async move {
         log::info!("Starting WebTransport data stream acceptor for session {}", session_id);
         loop {
             match session_for_task.accept_uni().await {
-                Ok(mut recv_stream) => {
-                    // Each incoming unidirectional stream gets a unique ID
-                    let stream_id = WT_NEXT_INCOMING_STREAM_ID.fetch_add(1, Ordering::SeqCst);
+                Ok(recv_stream) => {
+                    let stream_id = handles_for_streams.allocate(recv_stream.quic_id(), false);
                     log::debug!("Accepted incoming unidirectional stream {} on session {}", stream_id, session_id);
-
-                    // Collect all data from this stream, then push as a complete chunk
-                    let mut stream_data = Vec::new();
-                    let mut buffer = vec![0u8; 8192];  // Larger buffer for data streams
-                    let mut is_complete = false;
-
-                    loop {
-                        match recv_stream.read(&mut buffer).await {
-                            Ok(None) => {
-                                // Stream closed - mark as complete
-                                is_complete = true;
-                                log::debug!("Incoming stream {} closed on session {} ({} bytes total)",
-                                    stream_id, session_id, stream_data.len());
-                                break;
-                            }
-                            Ok(Some(n)) => {
-                                // Accumulate data from this stream
-                                stream_data.extend_from_slice(&buffer[..n]);
-                                log::trace!("Received {} bytes on stream {} session {} (total: {})",
-                                    n, stream_id, session_id, stream_data.len());
-
-                                // Push intermediate chunks if we have enough data
-                                // This allows processing to start while stream is still open
-                                if stream_data.len() >= 4096 {
-                                    let mut queue = data_queue_for_task.lock().await;
-                                    let chunk = std::mem::take(&mut stream_data);
-                                    if !queue.push(stream_id, chunk, false) {
-                                        log::warn!("Data queue full for session {}, dropping chunk", session_id);
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                log::error!("Error reading from stream {}: {:?}", stream_id, e);
-                                is_complete = true;  // Consider stream done on error
-                                break;
-                            }
-                        }
+                    let stream_buffer = Arc::new(tokio::sync::Mutex::new(ReceiveBuffer::new(data_recv_buffer_size)));
+                    if let Some(buffers) = WT_DATA_STREAM_BUFFERS.get() {
+                        buffers.insert((session_id, stream_id), stream_buffer.clone());
                     }
-
-                    // Push final chunk with remaining data
-                    if !stream_data.is_empty() || is_complete {
-                        let mut queue = data_queue_for_task.lock().await;
-                        if !queue.push(stream_id, stream_data, is_complete) {
-                            log::warn!("Data queue full for session {}, dropping final chunk", session_id);
-                        }
+                    active_streams_for_task.lock().unwrap().stream_ids.push(stream_id);
+                    let (stream_state, stop_rx) = IncomingStream::new();
+                    if let Some(stream_states) = WT_DATA_STREAM_STATES.get() {
+                        stream_states.insert((session_id, stream_id), stream_state.clone());
                     }
+                    events_for_streams.push_stream(MOQ_EVENT_DATA_STREAM_OPENED, stream_id, 0);
+
+                    let reader = IncomingStreamReader {
+                        session_id,
+                        stream_id,
+                        bidi: false,
+                        session: session_for_task.clone(),
+                        buffer: stream_buffer,
+                        state: stream_state,
+                        events: events_for_streams.clone(),
+                        stats: stats_for_streams.clone(),
+                        stall: stall_data,
+                    };
+                    tokio::spawn(reader.run(recv_stream, stop_rx));
                 }
                 Err(e) => {
                     log::error!("Error accepting incoming stream: {:?}", e);
@@ -587,12 +1097,174 @@ pub extern "C" fn moq_webtransport_connect(
         log::info!("WebTransport stream acceptor stopped for session {}", session_id);
     });
 
-    unsafe {
-        *out_session_id = session_id;
-    }
+    // Start accepting bidirectional streams opened by the peer
+    let session_for_bidi = session_arc.clone();
+    let events_for_bidi = events.clone();
+    let handles_for_bidi = stream_handles.clone();
+    runtime.spawn(async move {
+        loop {
+            match session_for_bidi.accept_bi().await {
+                Ok((send_stream, recv_stream)) => {
+                    let stream_id = handles_for_bidi.allocate(send_stream.quic_id(), false);
+                    log::info!("Accepted bidirectional stream {} on session {}", stream_id, session_id);
+                    if !start_bidi_stream(session_id, stream_id, session_for_bidi.clone(), send_stream, recv_stream) {
+                        break;
+                    }
+                    if let Some(pending) = WT_PENDING_BIDI_STREAMS.get().and_then(|p| p.get(&session_id).map(|l| l.clone())) {
+                        pending.lock().unwrap().push_back(stream_id);
+                    }
+                    events_for_bidi.push_stream(MOQ_EVENT_BIDI_STREAM_OPENED, stream_id, 0);
+                }
+                Err(e) => {
+                    log::debug!("Bidirectional stream acceptor stopped for session {}: {}", session_id, e);
+                    break;
+                }
+            }
+        }
+    });
 
     log::info!("WebTransport session created (ID: {})", session_id);
-    0
+    session_id
+}
+
+/// Reads one incoming stream, unidirectional or the receive side of a
+/// bidirectional one, into its receive buffer
+struct IncomingStreamReader {
+    session_id: u64,
+    stream_id: u64,
+    bidi: bool,
+    session: Arc<Session>,
+    buffer: SharedReceiveBuffer,
+    state: SharedStreamState,
+    events: Arc<EventQueue>,
+    stats: Arc<StatsTracker>,
+    stall: bool,
+}
+
+impl IncomingStreamReader {
+    /// Read until the stream ends or is stopped through `stop_rx`
+    ///
+    /// Chunks of up to 64KB are buffered without copying as soon as they arrive.
+    async fn run(self, mut recv_stream: web_transport_quinn::RecvStream, mut stop_rx: oneshot::Receiver<u32>) {
+        let Self { session_id, stream_id, bidi, .. } = self;
+        let (finished_event, reset_event, overflow_buffer) = if bidi {
+            (MOQ_EVENT_BIDI_STREAM_FINISHED, MOQ_EVENT_BIDI_STREAM_RESET, MOQ_BUFFER_BIDI_STREAM)
+        } else {
+            (MOQ_EVENT_DATA_STREAM_FINISHED, MOQ_EVENT_DATA_STREAM_RESET, MOQ_BUFFER_DATA_STREAM)
+        };
+        let connection: &quinn::Connection = &self.session;
+
+        loop {
+            let read = async {
+                let max_len = if self.stall && receive_callback(session_id).is_none() {
+                    receive_buffer::wait_for_space(&self.buffer, connection).await?.min(receive_buffer::READ_CHUNK_SIZE)
+                } else {
+                    receive_buffer::READ_CHUNK_SIZE
+                };
+                Some(recv_stream.read_chunk(max_len, true).await)
+            };
+            // Waiting for buffer space or data both give way to a stop request
+            let result = tokio::select! {
+                result = read => result,
+                Ok(code) = &mut stop_rx, if !stop_rx.is_terminated() => {
+                    let _ = recv_stream.stop(code);
+                    log::debug!("Stopped stream {} on session {} (code {})", stream_id, session_id, code);
+                    self.state.set_final(MOQ_STREAM_STATE_STOPPED, code.into());
+                    break;
+                }
+            };
+            let Some(result) = result else {
+                // Session closed while waiting for buffer space
+                self.state.set_final(MOQ_STREAM_STATE_FAILED, 0);
+                break;
+            };
+            match result {
+                Ok(None) => {
+                    log::debug!("Incoming stream {} finished on session {}", stream_id, session_id);
+                    self.state.set_final(MOQ_STREAM_STATE_FINISHED, 0);
+                    self.events.push_stream(finished_event, stream_id, 0);
+                    break;
+                }
+                Ok(Some(chunk)) => {
+                    let n = chunk.bytes.len();
+                    if let Some(callback) = receive_callback(session_id) {
+                        if bidi {
+                            callback.on_bidi_stream_data(session_id, stream_id, &chunk.bytes);
+                        } else {
+                            callback.on_stream_data(session_id, stream_id, &chunk.bytes);
+                        }
+                        continue;
+                    }
+
+                    let mut recv_buf = self.buffer.lock().await;
+                    let pushed = recv_buf.push_bytes(chunk.bytes);
+                    if pushed < n {
+                        log::warn!("Stream {} buffer full, dropped {} bytes", stream_id, n - pushed);
+                        self.stats.stream_bytes_dropped(n - pushed);
+                        self.events.push_overflow(overflow_buffer, stream_id, (n - pushed) as u64);
+                    }
+                    log::trace!("Received {} bytes on stream {} session {}", n, stream_id, session_id);
+                }
+                Err(web_transport_quinn::ReadError::Reset(code)) => {
+                    log::warn!("Stream {} reset by peer (code {})", stream_id, code);
+                    self.state.set_final(MOQ_STREAM_STATE_RESET, code.into());
+                    self.events.push_stream(reset_event, stream_id, code.into());
+                    break;
+                }
+                Err(e) => {
+                    log::error!("Error reading from stream {}: {:?}", stream_id, e);
+                    self.state.set_final(MOQ_STREAM_STATE_FAILED, 0);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Register both sides of a bidirectional stream under `stream_id`
+///
+/// The send side joins the outgoing streams used through the
+/// `moq_webtransport_stream_*` calls; the receive side is buffered for
+/// `moq_webtransport_bidi_recv`.
+///
+/// # Returns
+/// * false if the session is already gone
+fn start_bidi_stream(
+    session_id: u64,
+    stream_id: u64,
+    session: Arc<Session>,
+    send_stream: SendStream,
+    recv_stream: web_transport_quinn::RecvStream,
+) -> bool {
+    let events = WT_EVENT_QUEUES.get().and_then(|queues| queues.get(&session_id).map(|q| q.clone()));
+    let stats = WT_SESSION_STATS.get().and_then(|stats| stats.get(&session_id).map(|s| s.clone()));
+    let options = WT_SESSION_OPTIONS.get().and_then(|options| options.get(&session_id).map(|o| *o));
+    let (Some(events), Some(stats), Some(options)) = (events, stats, options) else {
+        return false;
+    };
+
+    let buffer = Arc::new(tokio::sync::Mutex::new(ReceiveBuffer::new(
+        options.data_recv_buffer_size(),
+    )));
+    let (state, stop_rx) = IncomingStream::new();
+    WT_BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized")
+        .insert((session_id, stream_id), BidiRecvStream { buffer: buffer.clone(), state: state.clone() });
+    WT_DATA_STREAMS.get().expect("Data streams not initialized")
+        .insert((session_id, stream_id), Arc::new(SharedSendStream::new(send_stream)));
+
+    let reader = IncomingStreamReader {
+        session_id,
+        stream_id,
+        bidi: true,
+        session,
+        buffer,
+        state,
+        events,
+        stats,
+        stall: options.data_stalls_when_full(),
+    };
+    tokio::spawn(reader.run(recv_stream, stop_rx));
+    true
 }
 
 /// Send data over a WebTransport control stream
@@ -607,6 +1279,7 @@ pub extern "C" fn moq_webtransport_connect(
 ///
 /// # Returns
 /// * Number of bytes sent on success, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_webtransport_send(
     session_id: u64,
@@ -671,10 +1344,9 @@ pub extern "C" fn moq_webtransport_send(
 #[no_mangle]
 pub extern "C" fn moq_webtransport_is_connected(session_id: u64) -> i32 {
     let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
-    if sessions.contains_key(&session_id) {
-        1
-    } else {
-        0
+    match sessions.get(&session_id) {
+        Some(session) if session.close_reason().is_none() => 1,
+        _ => 0,
     }
 }
 
@@ -687,6 +1359,7 @@ pub extern "C" fn moq_webtransport_is_connected(session_id: u64) -> i32 {
 ///
 /// # Returns
 /// * Number of bytes received on success, 0 if no data available, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_webtransport_recv(
     session_id: u64,
@@ -728,6 +1401,15 @@ pub extern "C" fn moq_webtransport_recv(
 ///
 /// This returns data from incoming unidirectional streams, separate from the
 /// bidirectional control stream. Each chunk includes the stream ID and completion status.
+/// Streams with data are served in turn, so a busy stream cannot hold back
+/// the others; data that does not fit in the buffer stays buffered for the
+/// next call. A completed stream's data is released once its last chunk is
+/// returned, but its final state stays readable with
+/// `moq_webtransport_get_data_stream_state`: call
+/// `moq_webtransport_close_data_stream` for every stream once done with it,
+/// or the state is only freed when the session closes.
+/// Use `moq_webtransport_get_data_streams` and
+/// `moq_webtransport_recv_stream_data` to read streams individually instead.
 ///
 /// # Arguments
 /// * `session_id` - The session ID
@@ -738,6 +1420,7 @@ pub extern "C" fn moq_webtransport_recv(
 ///
 /// # Returns
 /// * Number of bytes received on success, 0 if no data available, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_webtransport_recv_data(
     session_id: u64,
@@ -746,10 +1429,10 @@ pub extern "C" fn moq_webtransport_recv_data(
     buffer_len: usize,
     out_is_complete: *mut i32,
 ) -> i64 {
-    let data_queues = WT_DATA_QUEUES.get().expect("Data queues not initialized");
+    let active_data_streams = WT_ACTIVE_DATA_STREAMS.get().expect("Active data streams not initialized");
 
-    let data_queue = match data_queues.get(&session_id) {
-        Some(dq) => dq.clone(),
+    let active_streams = match active_data_streams.get(&session_id) {
+        Some(streams) => streams.clone(),
         None => {
             log::error!("Session {} not found for recv_data", session_id);
             return -1;
@@ -760,332 +1443,1041 @@ pub extern "C" fn moq_webtransport_recv_data(
         return 0;
     }
 
-    let runtime = get_runtime();
-
-    let result = runtime.block_on(async {
-        let mut queue = data_queue.lock().await;
-
-        match queue.pop() {
-            Some((stream_id, data, is_complete)) => {
-                // Set output stream ID
-                if !out_stream_id.is_null() {
-                    unsafe { *out_stream_id = stream_id; }
-                }
-
-                // Set completion flag
-                if !out_is_complete.is_null() {
-                    unsafe { *out_is_complete = if is_complete { 1 } else { 0 }; }
-                }
-
-                // Copy data to output buffer
-                let to_copy = data.len().min(buffer_len);
-                if to_copy > 0 {
-                    unsafe {
-                        let dst = slice::from_raw_parts_mut(buffer, to_copy);
-                        dst.copy_from_slice(&data[..to_copy]);
-                    }
-                }
+    let data_stream_buffers = WT_DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
+    let stream_states = WT_DATA_STREAM_STATES.get().expect("Data stream states not initialized");
 
-                if to_copy < data.len() {
-                    log::warn!("Data truncated: {} bytes available but only {} buffer space",
-                        data.len(), buffer_len);
-                }
+    let mut active = active_streams.lock().unwrap();
+    let count = active.stream_ids.len();
+    for offset in 0..count {
+        let index = (active.next + offset) % count;
+        let stream_id = active.stream_ids[index];
+        let Some(stream_buffer) = data_stream_buffers.get(&(session_id, stream_id)).map(|b| b.clone()) else {
+            continue;
+        };
+        // Checked before reading: once a stream has ended, all of its data is buffered
+        let ended = stream_states.get(&(session_id, stream_id)).is_none_or(|state| !state.is_open());
 
-                log::trace!("recv_data: stream {} returned {} bytes (complete: {})",
-                    stream_id, to_copy, is_complete);
+        let mut recv_buf = stream_buffer.blocking_lock();
+        if recv_buf.is_empty() && !ended {
+            continue;
+        }
+        let output_buf = unsafe { slice::from_raw_parts_mut(buffer, buffer_len) };
+        let bytes_read = recv_buf.pop(output_buf);
+        let is_complete = ended && recv_buf.is_empty();
+        drop(recv_buf);
+
+        if is_complete {
+            active.stream_ids.remove(index);
+            data_stream_buffers.remove(&(session_id, stream_id));
+            active.next = index;
+        } else {
+            active.next = index + 1;
+        }
 
-                to_copy as i64
+        unsafe {
+            if !out_stream_id.is_null() {
+                *out_stream_id = stream_id;
+            }
+            if !out_is_complete.is_null() {
+                *out_is_complete = if is_complete { 1 } else { 0 };
             }
-            None => 0,
         }
-    });
 
-    result
-}
+        log::trace!("recv_data: stream {} returned {} bytes (complete: {})",
+            stream_id, bytes_read, is_complete);
+        return bytes_read as i64;
+    }
 
-/// Open a unidirectional stream for sending data
+    0
+}
+
+/// Get list of active incoming data streams for a session
+///
+/// Streams are listed in the order they were accepted and stay listed until
+/// `moq_webtransport_close_data_stream` (or until `moq_webtransport_recv_data`
+/// returns their last chunk).
 ///
 /// # Arguments
 /// * `session_id` - The session ID
-/// * `out_stream_id` - Output parameter for the stream ID
+/// * `out_stream_ids` - Output array for stream IDs
+/// * `max_streams` - Maximum number of stream IDs to return
 ///
 /// # Returns
-/// * 0 on success, negative error code on failure
+/// * Number of stream IDs written on success, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
-pub extern "C" fn moq_webtransport_open_uni_stream(
+pub extern "C" fn moq_webtransport_get_data_streams(
     session_id: u64,
-    out_stream_id: *mut u64,
+    out_stream_ids: *mut u64,
+    max_streams: usize,
 ) -> i32 {
-    debug_log(&format!("[WT-DEBUG] open_uni_stream called for session {}", session_id));
-
-    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
-    let data_streams = WT_DATA_STREAMS.get().expect("Data streams not initialized");
+    let active_data_streams = WT_ACTIVE_DATA_STREAMS.get().expect("Active data streams not initialized");
 
-    debug_log(&format!("[WT-DEBUG] Looking up session {}", session_id));
-    let session = match sessions.get(&session_id) {
-        Some(s) => s.clone(),
+    let active_streams = match active_data_streams.get(&session_id) {
+        Some(streams) => streams.clone(),
         None => {
-            debug_log(&format!("[WT-ERROR] Session {} not found for open_uni_stream", session_id));
+            log::error!("Session {} not found for get_data_streams", session_id);
             return -1;
         }
     };
-    debug_log(&format!("[WT-DEBUG] Session {} found", session_id));
-
-    let runtime = get_runtime();
-    debug_log("[WT-DEBUG] Got runtime, calling block_on for open_uni");
 
-    let result = runtime.block_on(async {
-        debug_log("[WT-DEBUG] Inside async block, calling session.open_uni()");
-        match session.open_uni().await {
-            Ok(send_stream) => {
-                let stream_id = WT_NEXT_STREAM_ID.fetch_add(1, Ordering::SeqCst);
-                data_streams.insert((session_id, stream_id), Arc::new(tokio::sync::Mutex::new(send_stream)));
-                log::debug!("Opened unidirectional stream {} for session {}", stream_id, session_id);
-                Ok(stream_id)
-            }
-            Err(e) => {
-                let err_msg = format!("Failed to open uni stream: {}", e);
-                log::error!("{}", err_msg);
-                set_last_error(&err_msg);
-                Err(-2)
-            }
-        }
-    });
+    if out_stream_ids.is_null() || max_streams == 0 {
+        return 0;
+    }
 
-    match result {
-        Ok(stream_id) => {
-            if !out_stream_id.is_null() {
-                unsafe { *out_stream_id = stream_id; }
-            }
-            0
-        }
-        Err(e) => e,
+    let active = active_streams.lock().unwrap();
+    let count = active.stream_ids.len().min(max_streams);
+    unsafe {
+        let output = slice::from_raw_parts_mut(out_stream_ids, count);
+        output.copy_from_slice(&active.stream_ids[..count]);
     }
+    count as i32
 }
 
-/// Write data to a unidirectional stream
+/// Receive data from a specific data stream (non-blocking poll)
+///
+/// Once the stream has ended (see `moq_webtransport_get_data_stream_state`)
+/// and this returns 0, all of its data has been read.
 ///
 /// # Arguments
 /// * `session_id` - The session ID
-/// * `stream_id` - The stream ID (from open_uni_stream)
-/// * `data` - Pointer to data to send
-/// * `len` - Length of data
+/// * `stream_id` - The data stream ID
+/// * `buffer` - Pointer to buffer to store received data
+/// * `buffer_len` - Length of buffer
 ///
 /// # Returns
-/// * Number of bytes written on success, negative error code on failure
+/// * Number of bytes received on success, 0 if no data available, -1 if the stream is not found
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
-pub extern "C" fn moq_webtransport_stream_write(
+pub extern "C" fn moq_webtransport_recv_stream_data(
     session_id: u64,
     stream_id: u64,
-    data: *const u8,
-    len: usize,
+    buffer: *mut u8,
+    buffer_len: usize,
 ) -> i64 {
-    let data_streams = WT_DATA_STREAMS.get().expect("Data streams not initialized");
+    let data_stream_buffers = WT_DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
 
-    let stream_mutex = match data_streams.get(&(session_id, stream_id)) {
-        Some(s) => s.clone(),
+    let stream_buffer = match data_stream_buffers.get(&(session_id, stream_id)) {
+        Some(rb) => rb.clone(),
         None => {
-            log::error!("Stream {} not found for session {}", stream_id, session_id);
+            log::trace!("Data stream {} not found for session {}", stream_id, session_id);
             return -1;
         }
     };
 
-    let data_bytes = unsafe { slice::from_raw_parts(data, len) };
-    let data_to_send = data_bytes.to_vec();
+    if buffer.is_null() || buffer_len == 0 {
+        return 0;
+    }
 
-    let runtime = get_runtime();
+    let output_buf = unsafe { slice::from_raw_parts_mut(buffer, buffer_len) };
+    let bytes_read = stream_buffer.blocking_lock().pop(output_buf);
+    bytes_read as i64
+}
 
-    let result = runtime.block_on(async {
-        let mut stream = stream_mutex.lock().await;
-        match stream.write_all(&data_to_send).await {
-            Ok(_) => {
-                log::trace!("Wrote {} bytes to stream {} on session {}", len, stream_id, session_id);
-                len as i64
-            }
-            Err(e) => {
-                log::error!("Failed to write to stream {}: {:?}", stream_id, e);
-                -2
-            }
-        }
-    });
+/// Look at buffered data stream data without copying it
+///
+/// Same contract as `moq_webtransport_recv_peek`, for one incoming data
+/// stream. The pointer is invalidated by `moq_webtransport_data_consume`,
+/// `moq_webtransport_recv_stream_data`, and by the calls that free the
+/// stream's buffer: `moq_webtransport_close_data_stream`,
+/// `moq_webtransport_recv_data` once it reads the stream to its end,
+/// `moq_webtransport_close*` and `moq_webtransport_cleanup`.
+///
+/// # Returns
+/// * Chunk length, or -1 if the stream is not found or an output pointer is null
+#[no_mangle]
+pub extern "C" fn moq_webtransport_data_peek(
+    session_id: u64,
+    stream_id: u64,
+    out_data: *mut *const u8,
+    out_len: *mut usize,
+) -> i64 {
+    if out_data.is_null() || out_len.is_null() {
+        return -1;
+    }
 
-    result
+    let data_stream_buffers = WT_DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
+
+    let stream_buffer = match data_stream_buffers.get(&(session_id, stream_id)) {
+        Some(rb) => rb.clone(),
+        None => return -1,
+    };
+
+    receive_buffer::peek_into(&stream_buffer, out_data, out_len)
 }
 
-/// Finish (close) a unidirectional stream
+/// Release data stream data exposed by `moq_webtransport_data_peek`
+///
+/// # Returns
+/// * Number of bytes discarded, or -1 if the stream is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_data_consume(session_id: u64, stream_id: u64, len: usize) -> i64 {
+    let data_stream_buffers = WT_DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
+
+    let stream_buffer = match data_stream_buffers.get(&(session_id, stream_id)) {
+        Some(rb) => rb.clone(),
+        None => return -1,
+    };
+
+    let consumed = stream_buffer.blocking_lock().consume(len);
+    consumed as i64
+}
+
+/// Close and clean up a data stream
+///
+/// Call this after the stream has been fully processed to free resources.
+/// A stream that is still open is stopped with error code 0.
 ///
 /// # Arguments
 /// * `session_id` - The session ID
-/// * `stream_id` - The stream ID
+/// * `stream_id` - The data stream ID
 ///
 /// # Returns
 /// * 0 on success, negative error code on failure
 #[no_mangle]
-pub extern "C" fn moq_webtransport_stream_finish(
+pub extern "C" fn moq_webtransport_close_data_stream(
     session_id: u64,
     stream_id: u64,
 ) -> i32 {
-    let data_streams = WT_DATA_STREAMS.get().expect("Data streams not initialized");
+    let data_stream_buffers = WT_DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
+    let stream_states = WT_DATA_STREAM_STATES.get().expect("Data stream states not initialized");
+    let active_data_streams = WT_ACTIVE_DATA_STREAMS.get().expect("Active data streams not initialized");
 
-    let stream_mutex = match data_streams.remove(&(session_id, stream_id)) {
-        Some((_, s)) => s,
-        None => {
-            log::warn!("Stream {} not found for session {} during finish", stream_id, session_id);
-            return -1;
-        }
-    };
+    data_stream_buffers.remove(&(session_id, stream_id));
 
-    let runtime = get_runtime();
+    // Stop the reader too, or it would keep filling (or stall on) the removed buffer
+    if let Some((_, state)) = stream_states.remove(&(session_id, stream_id)) {
+        state.stop(0);
+    }
 
-    let result = runtime.block_on(async {
-        let mut stream = stream_mutex.lock().await;
-        match stream.finish() {
-            Ok(_) => {
-                log::debug!("Finished stream {} on session {}", stream_id, session_id);
-                0
-            }
-            Err(e) => {
-                log::error!("Failed to finish stream {}: {:?}", stream_id, e);
-                -2
-            }
-        }
-    });
+    if let Some(active_streams) = active_data_streams.get(&session_id) {
+        active_streams.lock().unwrap().stream_ids.retain(|&id| id != stream_id);
+    }
 
-    result
+    log::debug!("Closed data stream {} for session {}", stream_id, session_id);
+    0
 }
 
-/// Send a datagram (unreliable, unordered)
+/// Get the state of an incoming unidirectional stream
 ///
-/// Datagrams are used for low-latency data that doesn't require
-/// reliable delivery (e.g., audio frames in MoQ).
+/// States are kept until the stream is closed with
+/// `moq_webtransport_close_data_stream` or the session is closed, so the
+/// final state can be read after the stream's last chunk was received. Every
+/// incoming stream therefore needs a `moq_webtransport_close_data_stream`
+/// call, including streams fully read with `moq_webtransport_recv_data`.
 ///
 /// # Arguments
 /// * `session_id` - The session ID
-/// * `data` - Pointer to data to send
-/// * `len` - Length of data
+/// * `stream_id` - The stream ID reported by `moq_webtransport_recv_data` or `moq_webtransport_get_data_streams`
+/// * `out_error_code` - Set to the reset or stop code (0 otherwise); may be null
 ///
 /// # Returns
-/// * Number of bytes sent on success, negative error code on failure
+/// * One of the `MOQ_STREAM_STATE_*` constants, or -1 if the stream is not found
 #[no_mangle]
-pub extern "C" fn moq_webtransport_send_datagram(
+pub extern "C" fn moq_webtransport_get_data_stream_state(
     session_id: u64,
-    data: *const u8,
-    len: usize,
-) -> i64 {
-    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+    stream_id: u64,
+    out_error_code: *mut u64,
+) -> i32 {
+    let stream_states = WT_DATA_STREAM_STATES.get().expect("Data stream states not initialized");
 
-    let session = match sessions.get(&session_id) {
-        Some(s) => s.clone(),
+    match stream_states.get(&(session_id, stream_id)) {
+        Some(state) => state.write_state(out_error_code),
+        None => -1,
+    }
+}
+
+/// Ask the peer to stop sending an incoming unidirectional stream (STOP_SENDING)
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `stream_id` - The stream ID reported by `moq_webtransport_recv_data`
+/// * `error_code` - WebTransport application error code
+///
+/// # Returns
+/// * 0 on success
+/// * -1 if the stream is not found
+/// * -3 if the stream already ended
+#[no_mangle]
+pub extern "C" fn moq_webtransport_stop_data_stream(
+    session_id: u64,
+    stream_id: u64,
+    error_code: u32,
+) -> i32 {
+    let stream_states = WT_DATA_STREAM_STATES.get().expect("Data stream states not initialized");
+
+    let state = match stream_states.get(&(session_id, stream_id)) {
+        Some(state) => state.clone(),
         None => {
-            log::error!("Session {} not found for send_datagram", session_id);
+            log::warn!("Stream {} not found for stop on session {}", stream_id, session_id);
             return -1;
         }
     };
 
-    let data_bytes = unsafe { slice::from_raw_parts(data, len) };
-
-    match session.send_datagram(bytes::Bytes::copy_from_slice(data_bytes)) {
-        Ok(()) => {
-            log::trace!("Sent datagram ({} bytes) on WebTransport session {}", len, session_id);
-            len as i64
-        }
-        Err(e) => {
-            log::error!("Failed to send WebTransport datagram: {:?}", e);
-            -2
-        }
+    if state.stop(error_code) {
+        0
+    } else {
+        -3
     }
 }
 
-/// Receive a datagram (non-blocking poll)
+/// Open a unidirectional stream for sending data
 ///
-/// Returns the next complete datagram from the buffer, if available.
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `out_stream_id` - Output parameter for the stream ID
+///
+/// # Returns
+/// * 0 on success, negative error code on failure
+#[no_mangle]
+pub extern "C" fn moq_webtransport_open_uni_stream(
+    session_id: u64,
+    out_stream_id: *mut u64,
+) -> i32 {
+    moq_webtransport_open_uni_stream_with_priority(session_id, 0, out_stream_id)
+}
+
+/// Open a unidirectional stream with a send priority
+///
+/// Same semantics as `moq_quic_open_stream_with_priority`: higher priority
+/// streams are sent first when the session is congested.
 ///
 /// # Arguments
 /// * `session_id` - The session ID
-/// * `buffer` - Pointer to buffer to store received datagram
-/// * `buffer_len` - Length of buffer
+/// * `priority` - Send priority, higher is sent first (0 is the default)
+/// * `out_stream_id` - Output parameter for the stream ID
 ///
 /// # Returns
-/// * Number of bytes received on success, 0 if no datagram available, negative error code on failure
+/// * 0 on success, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
-pub extern "C" fn moq_webtransport_recv_datagram(
+pub extern "C" fn moq_webtransport_open_uni_stream_with_priority(
     session_id: u64,
-    buffer: *mut u8,
-    buffer_len: usize,
-) -> i64 {
-    let datagram_buffers = WT_DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
+    priority: i32,
+    out_stream_id: *mut u64,
+) -> i32 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+    let data_streams = WT_DATA_STREAMS.get().expect("Data streams not initialized");
+    let stream_handles = WT_STREAM_HANDLES.get().expect("Stream handles not initialized");
 
-    let datagram_buffer = match datagram_buffers.get(&session_id) {
-        Some(buf) => buf.clone(),
-        None => {
-            log::error!("Session {} not found for recv_datagram", session_id);
+    let (session, handles) = match (sessions.get(&session_id), stream_handles.get(&session_id)) {
+        (Some(s), Some(handles)) => (s.clone(), handles.clone()),
+        _ => {
+            log::error!("Session {} not found for open_uni_stream", session_id);
             return -1;
         }
     };
 
-    if buffer.is_null() || buffer_len == 0 {
-        return 0;
-    }
-
     let runtime = get_runtime();
 
     let result = runtime.block_on(async {
-        let mut buf = datagram_buffer.lock().await;
-
-        if let Some(datagram) = buf.pop_front() {
-            let copy_len = datagram.len().min(buffer_len);
-            unsafe {
-                let output = slice::from_raw_parts_mut(buffer, copy_len);
-                output.copy_from_slice(&datagram[..copy_len]);
+        match session.open_uni().await {
+            Ok(send_stream) => {
+                let stream_id = handles.allocate(send_stream.quic_id(), true);
+                // Only fails once the stream is closed, which a new stream is not
+                let _ = send_stream.set_priority(priority);
+                data_streams.insert((session_id, stream_id), Arc::new(SharedSendStream::new(send_stream)));
+                log::debug!("Opened unidirectional stream {} for session {}", stream_id, session_id);
+                Ok(stream_id)
             }
-            if datagram.len() > buffer_len {
-                log::warn!("WebTransport datagram truncated: {} bytes available, {} bytes buffer", datagram.len(), buffer_len);
+            Err(e) => {
+                let err_msg = format!("Failed to open uni stream: {}", e);
+                log::error!("{}", err_msg);
+                set_last_error(&err_msg);
+                Err(-2)
             }
-            copy_len as i64
-        } else {
-            0
         }
     });
 
-    result
+    match result {
+        Ok(stream_id) => {
+            if !out_stream_id.is_null() {
+                unsafe { *out_stream_id = stream_id; }
+            }
+            0
+        }
+        Err(e) => e,
+    }
 }
 
-/// Close a WebTransport session
+/// Open an additional bidirectional stream
+///
+/// The send side is used like a unidirectional stream
+/// (`moq_webtransport_stream_write`, `moq_webtransport_stream_finish`,
+/// `moq_webtransport_stream_reset`, `moq_webtransport_stream_set_priority`);
+/// replies arrive on the receive side, read with `moq_webtransport_bidi_recv`.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `out_stream_id` - Output parameter for the stream ID
+///
+/// # Returns
+/// * 0 on success, negative error code on failure
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
-pub extern "C" fn moq_webtransport_close(session_id: u64) -> i32 {
+pub extern "C" fn moq_webtransport_open_bidi_stream(
+    session_id: u64,
+    out_stream_id: *mut u64,
+) -> i32 {
     let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
-    let endpoints = WT_ENDPOINTS.get().expect("Endpoints not initialized");
-    let recv_buffers = WT_RECV_BUFFERS.get().expect("Receive buffers not initialized");
-    let data_queues = WT_DATA_QUEUES.get().expect("Data queues not initialized");
-    let control_streams = WT_CONTROL_STREAMS.get().expect("Control streams not initialized");
-    let data_streams = WT_DATA_STREAMS.get().expect("Data streams not initialized");
+    let stream_handles = WT_STREAM_HANDLES.get().expect("Stream handles not initialized");
 
-    let (_, _) = match sessions.remove(&session_id) {
-        Some(s) => s,
-        None => {
-            log::warn!("Session {} not found for close", session_id);
+    let (session, handles) = match (sessions.get(&session_id), stream_handles.get(&session_id)) {
+        (Some(s), Some(handles)) => (s.clone(), handles.clone()),
+        _ => {
+            log::error!("Session {} not found for open_bidi_stream", session_id);
             return -1;
         }
     };
 
-    let (_, _) = match endpoints.remove(&session_id) {
-        Some(e) => e,
-        None => {
-            log::warn!("Endpoint {} not found for close", session_id);
-            return -1;
-        }
-    };
+    let runtime = get_runtime();
+
+    let result = runtime.block_on(async {
+        match session.open_bi().await {
+            Ok((send_stream, recv_stream)) => {
+                let stream_id = handles.allocate(send_stream.quic_id(), true);
+                if !start_bidi_stream(session_id, stream_id, session, send_stream, recv_stream) {
+                    return Err(-1);
+                }
+                log::debug!("Opened bidirectional stream {} for session {}", stream_id, session_id);
+                Ok(stream_id)
+            }
+            Err(e) => {
+                let err_msg = format!("Failed to open bidi stream: {}", e);
+                log::error!("{}", err_msg);
+                set_last_error(&err_msg);
+                Err(-2)
+            }
+        }
+    });
+
+    match result {
+        Ok(stream_id) => {
+            if !out_stream_id.is_null() {
+                unsafe { *out_stream_id = stream_id; }
+            }
+            0
+        }
+        Err(e) => e,
+    }
+}
+
+/// Take the next bidirectional stream opened by the peer (non-blocking poll)
+///
+/// Each new stream is also announced with `MOQ_EVENT_BIDI_STREAM_OPENED`.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `out_stream_id` - Output parameter for the stream ID
+///
+/// # Returns
+/// * 1 if a stream was returned, 0 if none is pending, -1 if the session is not found
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_webtransport_accept_bidi_stream(
+    session_id: u64,
+    out_stream_id: *mut u64,
+) -> i32 {
+    let pending_bidi_streams = WT_PENDING_BIDI_STREAMS.get().expect("Pending bidirectional streams not initialized");
+
+    let pending = match pending_bidi_streams.get(&session_id) {
+        Some(pending) => pending.clone(),
+        None => return -1,
+    };
+
+    let next = pending.lock().unwrap().pop_front();
+    match next {
+        Some(stream_id) => {
+            if !out_stream_id.is_null() {
+                unsafe { *out_stream_id = stream_id; }
+            }
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Receive data from the receive side of a bidirectional stream (non-blocking poll)
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `stream_id` - The bidirectional stream ID
+/// * `buffer` - Pointer to buffer to store received data
+/// * `buffer_len` - Length of buffer
+///
+/// # Returns
+/// * Number of bytes received on success, 0 if no data available, -1 if the stream is not found
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_webtransport_bidi_recv(
+    session_id: u64,
+    stream_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i64 {
+    let bidi_recv_streams = WT_BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized");
+
+    let stream_buffer = match bidi_recv_streams.get(&(session_id, stream_id)) {
+        Some(stream) => stream.buffer.clone(),
+        None => return -1,
+    };
+
+    if buffer.is_null() || buffer_len == 0 {
+        return 0;
+    }
+
+    let mut recv_buf = stream_buffer.blocking_lock();
+    let output_buf = unsafe { slice::from_raw_parts_mut(buffer, buffer_len) };
+    recv_buf.pop(output_buf) as i64
+}
+
+/// Get how the receive side of a bidirectional stream ended
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `stream_id` - The bidirectional stream ID
+/// * `out_error_code` - Set to the reset or stop code (0 otherwise); may be null
+///
+/// # Returns
+/// * One of the `MOQ_STREAM_STATE_*` constants, or -1 if the stream is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_get_bidi_stream_state(
+    session_id: u64,
+    stream_id: u64,
+    out_error_code: *mut u64,
+) -> i32 {
+    let bidi_recv_streams = WT_BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized");
+
+    match bidi_recv_streams.get(&(session_id, stream_id)) {
+        Some(stream) => stream.state.write_state(out_error_code),
+        None => -1,
+    }
+}
+
+/// Close and clean up the receive side of a bidirectional stream
+///
+/// A receive side that is still open is stopped with error code 0. The send
+/// side is unaffected; finish or reset it separately.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `stream_id` - The bidirectional stream ID
+///
+/// # Returns
+/// * 0 on success, -1 if the stream is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_close_bidi_stream(
+    session_id: u64,
+    stream_id: u64,
+) -> i32 {
+    let bidi_recv_streams = WT_BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized");
+
+    match bidi_recv_streams.remove(&(session_id, stream_id)) {
+        Some((_, stream)) => {
+            stream.state.stop(0);
+            log::debug!("Closed bidirectional stream {} for session {}", stream_id, session_id);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Write data to a unidirectional stream
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `stream_id` - The stream ID (from open_uni_stream)
+/// * `data` - Pointer to data to send
+/// * `len` - Length of data
+///
+/// A stream that fails is dropped. If the peer sent STOP_SENDING, a
+/// `MOQ_EVENT_STREAM_STOPPED` event carries its code; WebTransport only
+/// learns of it on the next write.
+///
+/// # Returns
+/// * Number of bytes written on success
+/// * -1 if the stream is not found
+/// * -2 if the write failed
+/// * -3 if the peer stopped the stream
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_webtransport_stream_write(
+    session_id: u64,
+    stream_id: u64,
+    data: *const u8,
+    len: usize,
+) -> i64 {
+    let data_streams = WT_DATA_STREAMS.get().expect("Data streams not initialized");
+
+    let stream = match data_streams.get(&(session_id, stream_id)) {
+        Some(s) => s.clone(),
+        None => {
+            log::error!("Stream {} not found for session {}", stream_id, session_id);
+            return -1;
+        }
+    };
+
+    let data_bytes = unsafe { slice::from_raw_parts(data, len) };
+    let data_to_send = data_bytes.to_vec();
+
+    let runtime = get_runtime();
+
+    let result = runtime.block_on(async {
+        match stream.write_all(&data_to_send).await {
+            Ok(_) => {
+                log::trace!("Wrote {} bytes to stream {} on session {}", len, stream_id, session_id);
+                len as i64
+            }
+            Err(e) => {
+                data_streams.remove(&(session_id, stream_id));
+                // Codes outside the WebTransport range are reported as 0
+                if let Some(code) = stream_writer::stopped_code(&e) {
+                    let code = web_transport_quinn::proto::error_from_http3(code.into_inner()).unwrap_or(0);
+                    log::info!("Peer stopped stream {} on session {} (code {})", stream_id, session_id, code);
+                    if let Some(events) = WT_EVENT_QUEUES.get().and_then(|queues| queues.get(&session_id).map(|q| q.clone())) {
+                        events.push_stream(MOQ_EVENT_STREAM_STOPPED, stream_id, code.into());
+                    }
+                    return -3;
+                }
+                log::error!("Failed to write to stream {}: {:?}", stream_id, e);
+                -2
+            }
+        }
+    });
+
+    result
+}
+
+/// Abandon a unidirectional stream with RESET_STREAM
+///
+/// Data not yet sent is discarded, even if a write is currently waiting on
+/// the stream, and the peer sees the stream end with `error_code`.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `stream_id` - The stream ID (from open_uni_stream)
+/// * `error_code` - WebTransport application error code
+///
+/// # Returns
+/// * 0 on success
+/// * -1 if the stream is not found
+/// * -3 if the stream was already closed
+#[no_mangle]
+pub extern "C" fn moq_webtransport_stream_reset(
+    session_id: u64,
+    stream_id: u64,
+    error_code: u32,
+) -> i32 {
+    let data_streams = WT_DATA_STREAMS.get().expect("Data streams not initialized");
+
+    let stream = match data_streams.remove(&(session_id, stream_id)) {
+        Some((_, s)) => s,
+        None => {
+            log::warn!("Stream {} not found for session {} during reset", stream_id, session_id);
+            return -1;
+        }
+    };
+
+    match stream.with(|stream| stream.reset(error_code)) {
+        Ok(()) => {
+            log::debug!("Reset stream {} on session {} (code {})", stream_id, session_id, error_code);
+            0
+        }
+        Err(e) => {
+            log::warn!("Failed to reset stream {}: {:?}", stream_id, e);
+            -3
+        }
+    }
+}
+
+/// Change the send priority of an open unidirectional stream
+///
+/// Applies immediately, even while a write on the stream is waiting.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `stream_id` - The stream ID (from open_uni_stream)
+/// * `priority` - Send priority, higher is sent first
+///
+/// # Returns
+/// * 0 on success
+/// * -1 if the stream is not found
+/// * -2 if the stream is already closed
+#[no_mangle]
+pub extern "C" fn moq_webtransport_stream_set_priority(
+    session_id: u64,
+    stream_id: u64,
+    priority: i32,
+) -> i32 {
+    let data_streams = WT_DATA_STREAMS.get().expect("Data streams not initialized");
+
+    let stream = match data_streams.get(&(session_id, stream_id)) {
+        Some(s) => s.clone(),
+        None => {
+            log::error!("Stream {} not found for session {}", stream_id, session_id);
+            return -1;
+        }
+    };
+
+    match stream.with(|stream| stream.set_priority(priority)) {
+        Ok(()) => {
+            log::debug!("Set priority of stream {} on session {} to {}", stream_id, session_id, priority);
+            0
+        }
+        Err(e) => {
+            log::warn!("Failed to set priority of stream {}: {:?}", stream_id, e);
+            -2
+        }
+    }
+}
+
+/// Finish (close) a unidirectional stream
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `stream_id` - The stream ID
+///
+/// # Returns
+/// * 0 on success, negative error code on failure
+#[no_mangle]
+pub extern "C" fn moq_webtransport_stream_finish(
+    session_id: u64,
+    stream_id: u64,
+) -> i32 {
+    let data_streams = WT_DATA_STREAMS.get().expect("Data streams not initialized");
+
+    let stream = match data_streams.remove(&(session_id, stream_id)) {
+        Some((_, s)) => s,
+        None => {
+            log::warn!("Stream {} not found for session {} during finish", stream_id, session_id);
+            return -1;
+        }
+    };
+
+    let runtime = get_runtime();
+
+    let result = runtime.block_on(async {
+        match stream.after_writes(|stream| stream.finish()).await {
+            Ok(_) => {
+                log::debug!("Finished stream {} on session {}", stream_id, session_id);
+                0
+            }
+            Err(e) => {
+                log::error!("Failed to finish stream {}: {:?}", stream_id, e);
+                -2
+            }
+        }
+    });
+
+    result
+}
+
+/// Map a WebTransport datagram send failure to the FFI error code
+fn datagram_error_code(error: &web_transport_quinn::SessionError) -> i64 {
+    match error {
+        web_transport_quinn::SessionError::SendDatagramError(e) => datagram::send_error_code(e),
+        // The session or its connection is gone
+        _ => -5,
+    }
+}
+
+/// Send a datagram (unreliable, unordered)
+///
+/// Datagrams are used for low-latency data that doesn't require
+/// reliable delivery (e.g., audio frames in MoQ).
+///
+/// If the send buffer is full, the oldest queued datagrams are discarded to
+/// make room; this is counted in `datagram_send_overflows` of the stats.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `data` - Pointer to data to send
+/// * `len` - Length of data
+///
+/// # Returns
+/// * Number of bytes sent on success
+/// * -1 if the session is not found
+/// * -2 if the peer does not accept datagrams
+/// * -3 if the datagram is larger than `moq_webtransport_max_datagram_size`
+/// * -4 if datagrams are disabled locally
+/// * -5 if the session was lost
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_webtransport_send_datagram(
+    session_id: u64,
+    data: *const u8,
+    len: usize,
+) -> i64 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+
+    let session = match sessions.get(&session_id) {
+        Some(s) => s.clone(),
+        None => {
+            log::error!("Session {} not found for send_datagram", session_id);
+            return -1;
+        }
+    };
+
+    let data_bytes = unsafe { slice::from_raw_parts(data, len) };
+    let overflows = session.datagram_send_buffer_space() < len;
+
+    match session.send_datagram(bytes::Bytes::copy_from_slice(data_bytes)) {
+        Ok(()) => {
+            log::trace!("Sent datagram ({} bytes) on WebTransport session {}", len, session_id);
+            if overflows {
+                log::debug!("Datagram send buffer full on session {}, discarded older datagrams", session_id);
+                if let Some(stats) = WT_SESSION_STATS.get().and_then(|stats| stats.get(&session_id).map(|s| s.clone())) {
+                    stats.datagram_send_overflowed();
+                }
+            }
+            len as i64
+        }
+        Err(e) => {
+            log::error!("Failed to send WebTransport datagram: {:?}", e);
+            datagram_error_code(&e)
+        }
+    }
+}
+
+/// Send a datagram, waiting for room in the send buffer instead of
+/// discarding older datagrams
+///
+/// Same as `moq_quic_send_datagram_wait`: blocks the calling thread while the
+/// session is congested, so queued datagrams are sent in order and none are
+/// discarded.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `data` - Pointer to data to send
+/// * `len` - Length of data
+/// * `timeout_ms` - Give up after this long (0 waits until there is room)
+///
+/// # Returns
+/// * Number of bytes sent on success
+/// * -1 to -5 as for `moq_webtransport_send_datagram`
+/// * -6 if the timeout expired first; the datagram was not queued
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_webtransport_send_datagram_wait(
+    session_id: u64,
+    data: *const u8,
+    len: usize,
+    timeout_ms: u64,
+) -> i64 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+
+    let session = match sessions.get(&session_id) {
+        Some(s) => s.clone(),
+        None => {
+            log::error!("Session {} not found for send_datagram_wait", session_id);
+            return -1;
+        }
+    };
+
+    let data_bytes = unsafe { slice::from_raw_parts(data, len) };
+    let send = session.send_datagram_wait(bytes::Bytes::copy_from_slice(data_bytes));
+
+    let result = get_runtime().block_on(async {
+        match timeout_ms {
+            0 => Some(send.await),
+            ms => tokio::time::timeout(std::time::Duration::from_millis(ms), send).await.ok(),
+        }
+    });
+
+    match result {
+        Some(Ok(())) => {
+            log::trace!("Sent datagram ({} bytes) on WebTransport session {}", len, session_id);
+            len as i64
+        }
+        Some(Err(e)) => {
+            log::error!("Failed to send WebTransport datagram: {:?}", e);
+            datagram_error_code(&e)
+        }
+        None => {
+            log::debug!("Timed out waiting for datagram send buffer on session {}", session_id);
+            -6
+        }
+    }
+}
+
+/// Get how many bytes are queued in the datagram send buffer
+///
+/// # Arguments
+/// * `session_id` - The session ID
+///
+/// # Returns
+/// * Bytes waiting to be sent, or -1 if the session is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_datagram_send_buffered(session_id: u64) -> i64 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+    let session_options = WT_SESSION_OPTIONS.get().expect("Session options not initialized");
+
+    let (session, options) = match (sessions.get(&session_id), session_options.get(&session_id)) {
+        (Some(s), Some(options)) => (s.clone(), *options),
+        _ => return -1,
+    };
+
+    let capacity = datagram::send_buffer_size(options.datagram_send_buffer_size);
+    capacity.saturating_sub(session.datagram_send_buffer_space()) as i64
+}
+
+/// Receive a datagram (non-blocking poll)
+///
+/// Returns the next complete datagram from the buffer, if available.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `buffer` - Pointer to buffer to store received datagram
+/// * `buffer_len` - Length of buffer
+///
+/// # Returns
+/// * Number of bytes received on success, 0 if no datagram available, negative error code on failure
+#[no_mangle]
+pub extern "C" fn moq_webtransport_recv_datagram(
+    session_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i64 {
+    moq_webtransport_recv_datagram_with_timestamp(session_id, buffer, buffer_len, std::ptr::null_mut())
+}
+
+/// Receive a datagram together with the time it arrived (non-blocking poll)
+///
+/// Same as `moq_quic_recv_datagram_with_timestamp`.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `buffer` - Pointer to buffer to store received datagram
+/// * `buffer_len` - Length of buffer
+/// * `out_timestamp_us` - Set to the receive time from `moq_monotonic_time_us`'s clock; may be null
+///
+/// # Returns
+/// * Number of bytes received on success, 0 if no datagram available, negative error code on failure
+#[no_mangle]
+pub extern "C" fn moq_webtransport_recv_datagram_with_timestamp(
+    session_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+    out_timestamp_us: *mut u64,
+) -> i64 {
+    let datagram_buffers = WT_DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
+
+    let datagram_buffer = match datagram_buffers.get(&session_id) {
+        Some(buf) => buf.clone(),
+        None => {
+            log::error!("Session {} not found for recv_datagram", session_id);
+            return -1;
+        }
+    };
+
+    if buffer.is_null() || buffer_len == 0 {
+        return 0;
+    }
+
+    let mut queue = datagram_buffer.blocking_lock();
+    queue.pop_into(buffer, buffer_len, out_timestamp_us)
+}
+
+/// Close a WebTransport session
+#[no_mangle]
+pub extern "C" fn moq_webtransport_close(session_id: u64) -> i32 {
+    moq_webtransport_close_with_reason(session_id, 0, std::ptr::null(), 0)
+}
+
+/// Close a WebTransport session with an application error code and reason phrase
+///
+/// As with `moq_quic_close_with_reason`, the event queue is kept until it is
+/// drained or released with `moq_webtransport_release`.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `error_code` - WebTransport application error code sent to the peer
+/// * `reason` - Pointer to the reason phrase (may be null)
+/// * `reason_len` - Length of the reason phrase
+///
+/// # Returns
+/// * 0 on success, -1 if the session is not found
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_webtransport_close_with_reason(
+    session_id: u64,
+    error_code: u32,
+    reason: *const u8,
+    reason_len: usize,
+) -> i32 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+    let endpoints = WT_ENDPOINTS.get().expect("Endpoints not initialized");
+    let recv_buffers = WT_RECV_BUFFERS.get().expect("Receive buffers not initialized");
+    let control_streams = WT_CONTROL_STREAMS.get().expect("Control streams not initialized");
+    let data_streams = WT_DATA_STREAMS.get().expect("Data streams not initialized");
+
+    let reason = if reason.is_null() || reason_len == 0 {
+        &[][..]
+    } else {
+        unsafe { slice::from_raw_parts(reason, reason_len) }
+    };
+
+    let (_, session) = match sessions.remove(&session_id) {
+        Some(s) => s,
+        None => {
+            log::warn!("Session {} not found for close", session_id);
+            return -1;
+        }
+    };
+
+    let (_, endpoint) = match endpoints.remove(&session_id) {
+        Some(e) => e,
+        None => {
+            log::warn!("Endpoint {} not found for close", session_id);
+            return -1;
+        }
+    };
 
     recv_buffers.remove(&session_id);
-    data_queues.remove(&session_id);
+    let data_stream_buffers = WT_DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
+    data_stream_buffers.retain(|(sid, _), _| *sid != session_id);
+    let active_data_streams = WT_ACTIVE_DATA_STREAMS.get().expect("Active data streams not initialized");
+    active_data_streams.remove(&session_id);
+    let stream_states = WT_DATA_STREAM_STATES.get().expect("Data stream states not initialized");
+    stream_states.retain(|(sid, _), _| *sid != session_id);
     control_streams.remove(&session_id);
 
     // Clean up datagram buffer
     let datagram_buffers = WT_DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
     datagram_buffers.remove(&session_id);
 
+    let session_stats = WT_SESSION_STATS.get().expect("Session statistics not initialized");
+    session_stats.remove(&session_id);
+
+    let connect_attempts = WT_CONNECT_ATTEMPTS.get().expect("Connect attempts not initialized");
+    connect_attempts.remove(&session_id);
+
+    let receive_callbacks = WT_RECEIVE_CALLBACKS.get().expect("Receive callbacks not initialized");
+    receive_callbacks.remove(&session_id);
+
+    // The event queue outlives the session so the app can still read the close
+    let event_queues = WT_EVENT_QUEUES.get().expect("Event queues not initialized");
+    if let Some(events) = event_queues.get(&session_id) {
+        let (info, reason) = close::local_close_info(error_code.into(), reason);
+        events.push_closed(info, reason);
+    }
+
     // Clean up any data streams for this session
     data_streams.retain(|(sid, _), _| *sid != session_id);
+    let bidi_recv_streams = WT_BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized");
+    bidi_recv_streams.retain(|(sid, _), _| *sid != session_id);
+    let pending_bidi_streams = WT_PENDING_BIDI_STREAMS.get().expect("Pending bidirectional streams not initialized");
+    pending_bidi_streams.remove(&session_id);
+    let session_options = WT_SESSION_OPTIONS.get().expect("Session options not initialized");
+    session_options.remove(&session_id);
+    let stream_handles = WT_STREAM_HANDLES.get().expect("Stream handles not initialized");
+    stream_handles.remove(&session_id);
+
+    // Send the close to the peer and let the endpoint drain
+    get_runtime().block_on(async {
+        session.close(error_code, reason);
+        endpoint.wait_idle().await;
+    });
 
     log::info!("WebTransport session {} closed", session_id);
     0
@@ -1094,23 +2486,57 @@ pub extern "C" fn moq_webtransport_close(session_id: u64) -> i32 {
 /// Cleanup the WebTransport module
 #[no_mangle]
 pub extern "C" fn moq_webtransport_cleanup() {
+    let pending_connects = WT_PENDING_CONNECTS.get().expect("Pending connects not initialized");
+    for entry in pending_connects.iter() {
+        entry.value().cancel();
+    }
+    pending_connects.clear();
+
     let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
     let endpoints = WT_ENDPOINTS.get().expect("Endpoints not initialized");
     let recv_buffers = WT_RECV_BUFFERS.get().expect("Receive buffers not initialized");
-    let data_queues = WT_DATA_QUEUES.get().expect("Data queues not initialized");
     let control_streams = WT_CONTROL_STREAMS.get().expect("Control streams not initialized");
     let data_streams = WT_DATA_STREAMS.get().expect("Data streams not initialized");
 
     sessions.clear();
-    data_queues.clear();
+    let data_stream_buffers = WT_DATA_STREAM_BUFFERS.get().expect("Data stream buffers not initialized");
+    data_stream_buffers.clear();
+    let active_data_streams = WT_ACTIVE_DATA_STREAMS.get().expect("Active data streams not initialized");
+    active_data_streams.clear();
+    let stream_states = WT_DATA_STREAM_STATES.get().expect("Data stream states not initialized");
+    stream_states.clear();
     endpoints.clear();
     recv_buffers.clear();
     control_streams.clear();
     data_streams.clear();
 
+    let bidi_recv_streams = WT_BIDI_RECV_STREAMS.get().expect("Bidirectional streams not initialized");
+    bidi_recv_streams.clear();
+
+    let pending_bidi_streams = WT_PENDING_BIDI_STREAMS.get().expect("Pending bidirectional streams not initialized");
+    pending_bidi_streams.clear();
+
+    let session_options = WT_SESSION_OPTIONS.get().expect("Session options not initialized");
+    session_options.clear();
+
+    let stream_handles = WT_STREAM_HANDLES.get().expect("Stream handles not initialized");
+    stream_handles.clear();
+
     let datagram_buffers = WT_DATAGRAM_BUFFERS.get().expect("Datagram buffers not initialized");
     datagram_buffers.clear();
 
+    let connect_attempts = WT_CONNECT_ATTEMPTS.get().expect("Connect attempts not initialized");
+    connect_attempts.clear();
+
+    let session_stats = WT_SESSION_STATS.get().expect("Session statistics not initialized");
+    session_stats.clear();
+
+    let event_queues = WT_EVENT_QUEUES.get().expect("Event queues not initialized");
+    event_queues.clear();
+
+    let receive_callbacks = WT_RECEIVE_CALLBACKS.get().expect("Receive callbacks not initialized");
+    receive_callbacks.clear();
+
     log::info!("MoQ WebTransport cleanup complete");
 }
 
@@ -1122,6 +2548,7 @@ pub extern "C" fn moq_webtransport_cleanup() {
 ///
 /// # Returns
 /// * Number of bytes written to buffer on success, 0 if no error
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn moq_webtransport_get_last_error(
     buffer: *mut u8,
@@ -1172,3 +2599,459 @@ pub extern "C" fn moq_webtransport_max_datagram_size(
 
     session.max_datagram_size() as i64
 }
+
+/// Query the congestion controller in use on a WebTransport session
+///
+/// # Returns
+/// * One of the `MOQ_CONGESTION_*` constants (never `MOQ_CONGESTION_DEFAULT`),
+///   -1 if the session is not found, -2 if the controller is not recognised
+#[no_mangle]
+pub extern "C" fn moq_webtransport_get_congestion_controller(
+    session_id: u64,
+) -> i32 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+
+    let session = match sessions.get(&session_id) {
+        Some(s) => s.clone(),
+        None => {
+            log::error!("Session {} not found for get_congestion_controller", session_id);
+            return -1;
+        }
+    };
+
+    match options::congestion_controller_of(&session) {
+        -1 => -2,
+        controller => controller,
+    }
+}
+
+/// Get the remote address a session is using
+///
+/// With several resolved addresses this is the one that won the race.
+///
+/// # Arguments
+/// * `buffer` - Receives the address as text, e.g. `192.0.2.1:443` or `[2001:db8::1]:443`
+/// * `buffer_len` - Length of buffer
+///
+/// # Returns
+/// * Number of bytes written (truncated to fit), -1 if the session is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_get_remote_address(
+    session_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i32 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+
+    let session = match sessions.get(&session_id) {
+        Some(s) => s.clone(),
+        None => return -1,
+    };
+
+    let addr = happy_eyeballs::canonical_address(session.remote_address());
+    ffi_text::copy_text(&addr.to_string(), buffer, buffer_len)
+}
+
+/// Get the HTTP status the server answered the WebTransport CONNECT with
+///
+/// The response header fields are read with `moq_webtransport_get_response_headers`.
+///
+/// # Returns
+/// * The status code (2xx for an established session), -1 if the session is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_get_response_status(session_id: u64) -> i32 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+
+    match sessions.get(&session_id) {
+        Some(session) => session.response().status.as_u16() as i32,
+        None => -1,
+    }
+}
+
+/// Get the header fields of the server's CONNECT response
+///
+/// Pseudo-headers are left out. `wt-protocol` is included as sent, i.e. as
+/// a quoted structured field string; `moq_webtransport_get_protocol` returns
+/// it decoded.
+///
+/// # Arguments
+/// * `buffer` - Receives one `name: value` line per field, sorted by name
+/// * `buffer_len` - Length of buffer
+///
+/// # Returns
+/// * Number of bytes written (truncated to fit), -1 if the session is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_get_response_headers(
+    session_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i32 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+
+    let session = match sessions.get(&session_id) {
+        Some(s) => s.clone(),
+        None => return -1,
+    };
+
+    let lines = session
+        .response()
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+    ffi_text::copy_text(&lines, buffer, buffer_len)
+}
+
+/// Get the value of one header field of the server's CONNECT response
+///
+/// # Arguments
+/// * `name` - Field name, matched case-insensitively (must be null-terminated)
+/// * `buffer` - Receives the value
+/// * `buffer_len` - Length of buffer
+///
+/// # Returns
+/// * Number of bytes written (truncated to fit), -1 if the session is not
+///   found or `name` is null or not UTF-8, -2 if the response has no such field
+#[no_mangle]
+pub extern "C" fn moq_webtransport_get_response_header(
+    session_id: u64,
+    name: *const c_char,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i32 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+
+    let session = match sessions.get(&session_id) {
+        Some(s) => s.clone(),
+        None => return -1,
+    };
+    let name = match parse_c_str(name) {
+        Ok(name) => name.to_ascii_lowercase(),
+        Err(_) => return -1,
+    };
+
+    match session.response().headers.iter().find(|(field, _)| *field == name) {
+        Some((_, value)) => ffi_text::copy_text(value, buffer, buffer_len),
+        None => -2,
+    }
+}
+
+/// Get the subprotocol the server selected in its `wt-protocol` response header
+///
+/// The server picks one of the protocols offered in the
+/// `MoqConnectRequest`, or the single `protocol` of the host/port/path
+/// connect calls.
+///
+/// # Arguments
+/// * `buffer` - Receives the protocol name
+/// * `buffer_len` - Length of buffer
+///
+/// # Returns
+/// * Number of bytes written (truncated to fit), 0 if the server did not
+///   select a protocol, -1 if the session is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_get_protocol(
+    session_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i32 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+
+    let session = match sessions.get(&session_id) {
+        Some(s) => s.clone(),
+        None => return -1,
+    };
+
+    match &session.response().protocol {
+        Some(protocol) => ffi_text::copy_text(protocol, buffer, buffer_len),
+        None => 0,
+    }
+}
+
+/// Get the addresses tried while establishing a session
+///
+/// Same format as `moq_quic_get_connect_attempts`: one `address: outcome`
+/// line per attempt.
+///
+/// # Returns
+/// * Number of bytes written (truncated to fit), -1 if the session is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_get_connect_attempts(
+    session_id: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i32 {
+    let connect_attempts = WT_CONNECT_ATTEMPTS.get().expect("Connect attempts not initialized");
+
+    match connect_attempts.get(&session_id) {
+        Some(attempts) => ffi_text::copy_text(&attempts, buffer, buffer_len),
+        None => -1,
+    }
+}
+
+/// Look up what a stream handle refers to
+///
+/// Same as `moq_quic_get_stream_info`. The QUIC stream ID is that of the
+/// underlying HTTP/3 connection.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `stream_id` - A stream handle from any open, accept or data stream call
+/// * `out_info` - Output parameter for the stream's direction, initiator and QUIC stream ID
+///
+/// # Returns
+/// * 0 on success, -1 if the stream is not found or `out_info` is null
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_webtransport_get_stream_info(
+    session_id: u64,
+    stream_id: u64,
+    out_info: *mut MoqStreamInfo,
+) -> i32 {
+    if out_info.is_null() {
+        return -1;
+    }
+
+    let stream_handles = WT_STREAM_HANDLES.get().expect("Stream handles not initialized");
+
+    let info = match stream_handles.get(&session_id).and_then(|handles| handles.get(stream_id)) {
+        Some(info) => info,
+        None => {
+            log::warn!("Stream {} not found for get_stream_info on session {}", stream_id, session_id);
+            return -1;
+        }
+    };
+
+    unsafe {
+        *out_info = info;
+    }
+    0
+}
+
+/// Get transport statistics for a session's QUIC connection
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `out_stats` - Output parameter for the statistics snapshot
+///
+/// # Returns
+/// * 0 on success, -1 if the session is not found or `out_stats` is null
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn moq_webtransport_get_stats(
+    session_id: u64,
+    out_stats: *mut MoqConnectionStats,
+) -> i32 {
+    if out_stats.is_null() {
+        return -1;
+    }
+
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+    let session_stats = WT_SESSION_STATS.get().expect("Session statistics not initialized");
+
+    let (session, tracker) = match (sessions.get(&session_id), session_stats.get(&session_id)) {
+        (Some(s), Some(tracker)) => (s.clone(), tracker.clone()),
+        _ => return -1,
+    };
+
+    unsafe {
+        *out_stats = tracker.collect(&session);
+    }
+    0
+}
+
+/// Get why a session was closed
+///
+/// After `moq_webtransport_close`, this reports the code and reason that were
+/// sent until the event queue is drained or released.
+///
+/// Application close codes inside the WebTransport range are translated back
+/// from their HTTP/3 encoding; other codes are reported unchanged. A close
+/// caused by a TLS alert (e.g. a rejected client certificate) has the alert
+/// described in its reason phrase.
+///
+/// # Arguments
+/// * `session_id` - The session ID
+/// * `out_info` - Output parameter for the close source, kind, code and reason length
+/// * `reason_buf` - Buffer for the reason phrase (may be null; truncated to fit)
+/// * `reason_buf_len` - Length of `reason_buf`
+///
+/// # Returns
+/// * 1 if the session is closed, 0 if it is still open,
+///   -1 if the session is not found or `out_info` is null
+#[no_mangle]
+pub extern "C" fn moq_webtransport_get_close_reason(
+    session_id: u64,
+    out_info: *mut MoqCloseInfo,
+    reason_buf: *mut u8,
+    reason_buf_len: usize,
+) -> i32 {
+    if out_info.is_null() {
+        return -1;
+    }
+
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+
+    let session = match sessions.get(&session_id) {
+        Some(s) => s.clone(),
+        None => {
+            // Closed locally; the event queue remembers what was sent
+            let event_queues = WT_EVENT_QUEUES.get().expect("Event queues not initialized");
+            return match event_queues.get(&session_id).and_then(|events| events.close_info()) {
+                Some((info, reason)) => close::write_close_info(info, &reason, out_info, reason_buf, reason_buf_len),
+                None => -1,
+            };
+        }
+    };
+
+    let connection: &quinn::Connection = &session;
+    match connection.close_reason() {
+        Some(error) => {
+            let (info, reason) = session_close_info(&error);
+            close::write_close_info(info, &reason, out_info, reason_buf, reason_buf_len)
+        }
+        None => 0,
+    }
+}
+
+/// Like `close::close_info`, but with WebTransport application codes decoded from HTTP/3
+fn session_close_info(error: &quinn::ConnectionError) -> (MoqCloseInfo, Vec<u8>) {
+    let (mut info, reason) = close::close_info(error);
+    if info.kind == MOQ_CLOSE_KIND_APPLICATION {
+        if let Some(code) = web_transport_quinn::proto::error_from_http3(info.error_code) {
+            info.error_code = code.into();
+        }
+    }
+    (info, reason)
+}
+
+/// Take the next lifecycle event for a session
+///
+/// Same events and semantics as `moq_quic_poll_event`; data stream IDs are
+/// the incoming stream IDs used by `moq_webtransport_recv_data`.
+///
+/// # Returns
+/// * 1 if an event was written, 0 if there are none,
+///   -1 if the session is not found or `out_event` is null
+#[no_mangle]
+pub extern "C" fn moq_webtransport_poll_event(
+    session_id: u64,
+    out_event: *mut MoqEvent,
+    reason_buf: *mut u8,
+    reason_buf_len: usize,
+) -> i32 {
+    if out_event.is_null() {
+        return -1;
+    }
+
+    let event_queues = WT_EVENT_QUEUES.get().expect("Event queues not initialized");
+
+    let events = match event_queues.get(&session_id) {
+        Some(queue) => queue.clone(),
+        None => return -1,
+    };
+
+    let result = events.pop_into(out_event, reason_buf, reason_buf_len);
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+    if events.drained_after_close() && !sessions.contains_key(&session_id) {
+        event_queues.remove(&session_id);
+    }
+    result
+}
+
+/// Release the event queue a closed session kept for the application
+///
+/// Same as `moq_quic_release`.
+///
+/// # Returns
+/// * 0 on success, -1 if nothing is kept for the ID or the session is still open
+#[no_mangle]
+pub extern "C" fn moq_webtransport_release(session_id: u64) -> i32 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+    if sessions.contains_key(&session_id) {
+        return -1;
+    }
+
+    let event_queues = WT_EVENT_QUEUES.get().expect("Event queues not initialized");
+    match event_queues.remove(&session_id) {
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+/// Switch a session between polled and pushed delivery of received data
+///
+/// Same semantics as `moq_quic_set_receive_callback`: while registered, data
+/// bypasses `moq_webtransport_recv`, `moq_webtransport_recv_data` and
+/// `moq_webtransport_recv_datagram`. Passing a null callback returns to polling.
+///
+/// # Returns
+/// * 0 on success, -1 if the session is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_set_receive_callback(
+    session_id: u64,
+    callback: Option<MoqReceiveCallback>,
+    user_data: *mut std::ffi::c_void,
+) -> i32 {
+    let sessions = WT_SESSIONS.get().expect("Sessions not initialized");
+    if !sessions.contains_key(&session_id) {
+        return -1;
+    }
+
+    let receive_callbacks = WT_RECEIVE_CALLBACKS.get().expect("Receive callbacks not initialized");
+    match callback {
+        Some(callback) => {
+            receive_callbacks.insert(session_id, Arc::new(push::FfiReceiveCallback::new(callback, user_data)));
+        }
+        None => {
+            receive_callbacks.remove(&session_id);
+        }
+    }
+    0
+}
+
+/// Look at buffered control stream data without copying it
+///
+/// Same contract as `moq_quic_recv_peek`: the pointer becomes invalid as soon
+/// as `moq_webtransport_recv_consume`, `moq_webtransport_recv`,
+/// `moq_webtransport_close*` or `moq_webtransport_cleanup` runs on any thread.
+///
+/// # Returns
+/// * Chunk length, or -1 if the session is not found or an output pointer is null
+#[no_mangle]
+pub extern "C" fn moq_webtransport_recv_peek(
+    session_id: u64,
+    out_data: *mut *const u8,
+    out_len: *mut usize,
+) -> i64 {
+    if out_data.is_null() || out_len.is_null() {
+        return -1;
+    }
+
+    let recv_buffers = WT_RECV_BUFFERS.get().expect("Receive buffers not initialized");
+
+    let recv_buffer = match recv_buffers.get(&session_id) {
+        Some(rb) => rb.clone(),
+        None => return -1,
+    };
+
+    crate::receive_buffer::peek_into(&recv_buffer, out_data, out_len)
+}
+
+/// Release control stream data exposed by `moq_webtransport_recv_peek`
+///
+/// # Returns
+/// * Number of bytes discarded, or -1 if the session is not found
+#[no_mangle]
+pub extern "C" fn moq_webtransport_recv_consume(session_id: u64, len: usize) -> i64 {
+    let recv_buffers = WT_RECV_BUFFERS.get().expect("Receive buffers not initialized");
+
+    let recv_buffer = match recv_buffers.get(&session_id) {
+        Some(rb) => rb.clone(),
+        None => return -1,
+    };
+
+    let consumed = recv_buffer.blocking_lock().consume(len);
+    consumed as i64
+}
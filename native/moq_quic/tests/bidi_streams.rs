@@ -0,0 +1,59 @@
+// Bidirectional streams opened by the peer are accepted, announced with an
+// event, and can be replied to on their send side.
+
+mod common;
+
+use tokio::sync::oneshot;
+
+#[test]
+fn peer_opened_bidi_stream_is_accepted() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (reply_tx, reply_rx) = oneshot::channel();
+    let addr = common::quic_server(&server_runtime, |connection| async move {
+        let (mut send, mut recv) = connection.open_bi().await.unwrap();
+        send.write_all(b"request").await.unwrap();
+        send.finish().unwrap();
+        let _ = reply_tx.send(recv.read_to_end(1024).await.unwrap());
+        connection.closed().await;
+    });
+    let connection_id = common::connect_quic(addr);
+
+    let stream_id = common::wait_for("the peer's stream", || {
+        let mut stream_id = 0;
+        (moq_quic::moq_quic_accept_bidi_stream(connection_id, &mut stream_id) == 1).then_some(stream_id)
+    });
+    let mut stream_id_out = 0;
+    assert_eq!(moq_quic::moq_quic_accept_bidi_stream(connection_id, &mut stream_id_out), 0);
+    assert_eq!(moq_quic::moq_quic_accept_bidi_stream(u64::MAX, &mut stream_id_out), -1);
+
+    let mut opened = None;
+    let mut event = moq_quic::MoqEvent::default();
+    while moq_quic::moq_quic_poll_event(connection_id, &mut event, std::ptr::null_mut(), 0) == 1 {
+        if event.kind == moq_quic::MOQ_EVENT_BIDI_STREAM_OPENED {
+            opened = Some(event.stream_id);
+        }
+    }
+    assert_eq!(opened, Some(stream_id));
+
+    // The request is read in full and the receive side ends cleanly
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 64];
+    common::wait_for("the request", || {
+        let n = moq_quic::moq_quic_bidi_recv(connection_id, stream_id, buffer.as_mut_ptr(), buffer.len());
+        assert!(n >= 0);
+        request.extend_from_slice(&buffer[..n as usize]);
+        let state = moq_quic::moq_quic_get_bidi_stream_state(connection_id, stream_id, std::ptr::null_mut());
+        (state == moq_quic::MOQ_STREAM_STATE_FINISHED as i32 && n == 0).then_some(())
+    });
+    assert_eq!(request, b"request");
+
+    // The reply goes out on the same stream
+    let reply = b"response";
+    assert_eq!(moq_quic::moq_quic_stream_write(connection_id, stream_id, reply.as_ptr(), reply.len()), reply.len() as i64);
+    assert_eq!(moq_quic::moq_quic_stream_finish(connection_id, stream_id), 0);
+    assert_eq!(server_runtime.block_on(reply_rx).unwrap(), reply);
+
+    assert_eq!(moq_quic::moq_quic_close_bidi_stream(connection_id, stream_id), 0);
+    assert_eq!(moq_quic::moq_quic_bidi_recv(connection_id, stream_id, buffer.as_mut_ptr(), buffer.len()), -1);
+    moq_quic::moq_quic_close(connection_id);
+}
@@ -0,0 +1,171 @@
+// Test servers and polling helpers shared by the integration tests
+//
+// Servers run on a runtime owned by the test, separate from the one the
+// crate uses for its own connections, and serve a single connection with a
+// self-signed certificate (the client connects with `insecure`).
+
+#![allow(dead_code)]
+
+use std::ffi::CString;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use quinn::crypto::rustls::QuicServerConfig;
+use quinn::{Connection, Endpoint, ServerConfig};
+use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+use web_transport_quinn::proto::{ConnectRequest, ConnectResponse};
+
+/// ALPN the QUIC test server accepts, also the client default for early drafts
+pub const ALPN: &str = "moq-00";
+
+/// Server config with a fresh self-signed certificate for `localhost`
+pub fn server_config(alpn: &str) -> ServerConfig {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.signing_key.serialize_der()));
+    let mut tls = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![certified.cert.der().clone()], key)
+        .unwrap();
+    tls.alpn_protocols = vec![alpn.as_bytes().to_vec()];
+    ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls).unwrap()))
+}
+
+/// Start a raw QUIC server that hands its first connection to `handler`
+pub fn quic_server<F, Fut>(runtime: &tokio::runtime::Runtime, handler: F) -> SocketAddr
+where
+    F: FnOnce(Connection) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    quic_server_with_config(runtime, server_config(ALPN), handler)
+}
+
+/// Start a raw QUIC server with a custom config, e.g. one built from
+/// `server_config` with different transport parameters
+pub fn quic_server_with_config<F, Fut>(runtime: &tokio::runtime::Runtime, config: ServerConfig, handler: F) -> SocketAddr
+where
+    F: FnOnce(Connection) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    runtime.block_on(async {
+        let endpoint = Endpoint::server(config, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        tokio::spawn(async move {
+            let connection = endpoint.accept().await.unwrap().await.unwrap();
+            handler(connection).await;
+            endpoint.wait_idle().await;
+        });
+        addr
+    })
+}
+
+/// Start a WebTransport server that hands its first session to `handler`
+pub fn webtransport_server<F, Fut>(runtime: &tokio::runtime::Runtime, handler: F) -> SocketAddr
+where
+    F: FnOnce(web_transport_quinn::Session) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    webtransport_server_on(runtime, "127.0.0.1:0", |_| ConnectResponse::OK, handler).unwrap()
+}
+
+/// Start a WebTransport server on `bind` that answers the first CONNECT
+/// request with `respond` and hands the session to `handler`
+///
+/// The handler can inspect the request with `Session::request` and `sni`.
+/// Returns `None` if the address cannot be bound (e.g. no IPv6 loopback).
+pub fn webtransport_server_on<R, F, Fut>(
+    runtime: &tokio::runtime::Runtime,
+    bind: &str,
+    respond: R,
+    handler: F,
+) -> Option<SocketAddr>
+where
+    R: FnOnce(&ConnectRequest) -> ConnectResponse + Send + 'static,
+    F: FnOnce(web_transport_quinn::Session) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    runtime.block_on(async {
+        let config = server_config(web_transport_quinn::ALPN);
+        let endpoint = Endpoint::server(config, bind.parse().unwrap()).ok()?;
+        let addr = endpoint.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut server = web_transport_quinn::Server::new(endpoint.clone());
+            let request = server.accept().await.unwrap();
+            let response = respond(&request);
+            let session = request.respond(response).await.unwrap();
+            handler(session).await;
+            endpoint.wait_idle().await;
+        });
+        Some(addr)
+    })
+}
+
+/// Server name the client sent in its TLS handshake
+pub fn sni(connection: &Connection) -> Option<String> {
+    connection
+        .handshake_data()
+        .and_then(|data| data.downcast::<quinn::crypto::rustls::HandshakeData>().ok())
+        .and_then(|data| data.server_name)
+}
+
+/// Connect to a QUIC test server
+pub fn connect_quic(addr: SocketAddr) -> u64 {
+    connect_quic_with_options(addr, &moq_quic::MoqConnectOptions::default())
+}
+
+/// Connect to a QUIC test server with non-default options
+pub fn connect_quic_with_options(addr: SocketAddr, options: &moq_quic::MoqConnectOptions) -> u64 {
+    moq_quic::moq_quic_init();
+    let host = CString::new("127.0.0.1").unwrap();
+    let alpn = CString::new(ALPN).unwrap();
+    let mut connection_id = 0;
+    let result = moq_quic::moq_quic_connect_with_options(
+        host.as_ptr(),
+        addr.port(),
+        1,
+        0,
+        alpn.as_ptr(),
+        options,
+        &mut connection_id,
+    );
+    assert_eq!(result, 0);
+    connection_id
+}
+
+/// Connect to a WebTransport test server
+pub fn connect_webtransport(addr: SocketAddr) -> u64 {
+    connect_webtransport_with_options(addr, &moq_quic::MoqConnectOptions::default())
+}
+
+/// Connect to a WebTransport test server with non-default options
+pub fn connect_webtransport_with_options(addr: SocketAddr, options: &moq_quic::MoqConnectOptions) -> u64 {
+    moq_quic::webtransport::moq_webtransport_init();
+    let host = CString::new("127.0.0.1").unwrap();
+    let path = CString::new("/").unwrap();
+    let protocol = CString::new("moq-00").unwrap();
+    let mut session_id = 0;
+    let result = moq_quic::webtransport::moq_webtransport_connect_with_options(
+        host.as_ptr(),
+        addr.port(),
+        path.as_ptr(),
+        protocol.as_ptr(),
+        1,
+        options,
+        &mut session_id,
+    );
+    assert_eq!(result, 0);
+    session_id
+}
+
+/// Poll `check` until it returns a value, failing the test after 10 seconds
+pub fn wait_for<T>(what: &str, mut check: impl FnMut() -> Option<T>) -> T {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if let Some(value) = check() {
+            return value;
+        }
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
@@ -0,0 +1,63 @@
+// The congestion controller chosen in the connect options is the one the
+// connection runs, and is reported back per connection.
+
+mod common;
+
+use std::ffi::CString;
+
+use moq_quic::MoqConnectOptions;
+
+fn with_controller(congestion_controller: u32) -> MoqConnectOptions {
+    MoqConnectOptions { congestion_controller, ..Default::default() }
+}
+
+#[test]
+fn quic_connections_run_the_selected_controller() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    for (selected, expected) in [
+        (moq_quic::MOQ_CONGESTION_DEFAULT, moq_quic::MOQ_CONGESTION_CUBIC),
+        (moq_quic::MOQ_CONGESTION_CUBIC, moq_quic::MOQ_CONGESTION_CUBIC),
+        (moq_quic::MOQ_CONGESTION_NEW_RENO, moq_quic::MOQ_CONGESTION_NEW_RENO),
+        (moq_quic::MOQ_CONGESTION_BBR, moq_quic::MOQ_CONGESTION_BBR),
+    ] {
+        let addr = common::quic_server(&server_runtime, |connection| async move {
+            connection.closed().await;
+        });
+        let connection_id = common::connect_quic_with_options(addr, &with_controller(selected));
+        assert_eq!(moq_quic::moq_quic_get_congestion_controller(connection_id), expected as i32);
+        moq_quic::moq_quic_close(connection_id);
+    }
+    assert_eq!(moq_quic::moq_quic_get_congestion_controller(u64::MAX), -1);
+}
+
+#[test]
+fn webtransport_sessions_run_the_selected_controller() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let addr = common::webtransport_server(&server_runtime, |session| async move {
+        session.closed().await;
+    });
+    let session_id = common::connect_webtransport_with_options(addr, &with_controller(moq_quic::MOQ_CONGESTION_BBR));
+    assert_eq!(
+        moq_quic::webtransport::moq_webtransport_get_congestion_controller(session_id),
+        moq_quic::MOQ_CONGESTION_BBR as i32
+    );
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
+
+#[test]
+fn unknown_controller_is_rejected() {
+    moq_quic::moq_quic_init();
+    let host = CString::new("127.0.0.1").unwrap();
+    let alpn = CString::new(common::ALPN).unwrap();
+    let mut connection_id = 0;
+    let result = moq_quic::moq_quic_connect_with_options(
+        host.as_ptr(),
+        4433,
+        1,
+        0,
+        alpn.as_ptr(),
+        &with_controller(99),
+        &mut connection_id,
+    );
+    assert_eq!(result, -3);
+}
@@ -0,0 +1,118 @@
+// Closing with an application code and reason: the peer sees both, and the
+// closing side can still read the close it sent from its event queue until
+// the queue is drained.
+
+mod common;
+
+use moq_quic::webtransport::{moq_webtransport_close_with_reason, moq_webtransport_get_close_reason, moq_webtransport_poll_event};
+use moq_quic::{
+    moq_quic_close_with_reason, moq_quic_get_close_reason, moq_quic_poll_event, moq_quic_release, MoqCloseInfo,
+    MoqEvent, MOQ_CLOSE_KIND_APPLICATION, MOQ_CLOSE_SOURCE_LOCAL, MOQ_EVENT_CONNECTION_CLOSED,
+};
+use tokio::sync::oneshot;
+
+type PollEvent = extern "C" fn(u64, *mut MoqEvent, *mut u8, usize) -> i32;
+
+/// Take events until the close, returning it with its reason phrase
+fn take_close_event(poll: PollEvent, id: u64) -> (MoqEvent, Vec<u8>) {
+    let mut reason = [0u8; 64];
+    loop {
+        let mut event = MoqEvent::default();
+        // The close is queued before close_with_reason returns
+        assert_eq!(poll(id, &mut event, reason.as_mut_ptr(), reason.len()), 1);
+        if event.kind == MOQ_EVENT_CONNECTION_CLOSED {
+            return (event, reason[..event.close.reason_len as usize].to_vec());
+        }
+    }
+}
+
+fn assert_local_close(info: MoqCloseInfo, error_code: u64, reason: &[u8]) {
+    assert_eq!(info.source, MOQ_CLOSE_SOURCE_LOCAL);
+    assert_eq!(info.kind, MOQ_CLOSE_KIND_APPLICATION);
+    assert_eq!(info.error_code, error_code);
+    assert_eq!(info.reason_len, reason.len() as u64);
+}
+
+#[test]
+fn quic_close_reaches_peer_and_stays_readable() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (seen_tx, seen_rx) = oneshot::channel();
+    let addr = common::quic_server(&server_runtime, |connection| async move {
+        let _ = seen_tx.send(connection.closed().await);
+    });
+    let connection_id = common::connect_quic(addr);
+
+    assert_eq!(moq_quic_close_with_reason(connection_id, 0x42, b"going away".as_ptr(), 10), 0);
+
+    match server_runtime.block_on(seen_rx).unwrap() {
+        quinn::ConnectionError::ApplicationClosed(close) => {
+            assert_eq!(close.error_code.into_inner(), 0x42);
+            assert_eq!(&close.reason[..], b"going away");
+        }
+        other => panic!("peer saw {:?}", other),
+    }
+
+    // The close reason stays available until the event queue is drained
+    let mut info = MoqCloseInfo::default();
+    let mut reason = [0u8; 64];
+    assert_eq!(moq_quic_get_close_reason(connection_id, &mut info, reason.as_mut_ptr(), reason.len()), 1);
+    assert_local_close(info, 0x42, b"going away");
+    assert_eq!(&reason[..10], b"going away");
+
+    let (event, reason) = take_close_event(moq_quic_poll_event, connection_id);
+    assert_local_close(event.close, 0x42, b"going away");
+    assert_eq!(reason, b"going away");
+
+    let mut event = MoqEvent::default();
+    assert_eq!(moq_quic_poll_event(connection_id, &mut event, std::ptr::null_mut(), 0), -1);
+    assert_eq!(moq_quic_get_close_reason(connection_id, &mut info, std::ptr::null_mut(), 0), -1);
+    assert_eq!(moq_quic_release(connection_id), -1);
+}
+
+#[test]
+fn quic_release_frees_an_undrained_queue() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let addr = common::quic_server(&server_runtime, |connection| async move {
+        connection.closed().await;
+    });
+    let connection_id = common::connect_quic(addr);
+
+    // Still open
+    assert_eq!(moq_quic_release(connection_id), -1);
+    assert_eq!(moq_quic_close_with_reason(connection_id, 0, std::ptr::null(), 0), 0);
+    assert_eq!(moq_quic_release(connection_id), 0);
+
+    let mut event = MoqEvent::default();
+    assert_eq!(moq_quic_poll_event(connection_id, &mut event, std::ptr::null_mut(), 0), -1);
+}
+
+#[test]
+fn webtransport_close_reaches_peer_and_stays_readable() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (seen_tx, seen_rx) = oneshot::channel();
+    let addr = common::webtransport_server(&server_runtime, |session| async move {
+        let _ = seen_tx.send(session.closed().await);
+    });
+    let session_id = common::connect_webtransport(addr);
+
+    assert_eq!(moq_webtransport_close_with_reason(session_id, 7, b"done".as_ptr(), 4), 0);
+
+    match server_runtime.block_on(seen_rx).unwrap() {
+        web_transport_quinn::SessionError::WebTransportError(web_transport_quinn::WebTransportError::Closed(code, reason)) => {
+            assert_eq!(code, 7);
+            assert_eq!(reason, "done");
+        }
+        other => panic!("peer saw {:?}", other),
+    }
+
+    let mut info = MoqCloseInfo::default();
+    assert_eq!(moq_webtransport_get_close_reason(session_id, &mut info, std::ptr::null_mut(), 0), 1);
+    assert_local_close(info, 7, b"done");
+
+    let (event, reason) = take_close_event(moq_webtransport_poll_event, session_id);
+    assert_local_close(event.close, 7, b"done");
+    assert_eq!(reason, b"done");
+
+    let mut event = MoqEvent::default();
+    assert_eq!(moq_webtransport_poll_event(session_id, &mut event, std::ptr::null_mut(), 0), -1);
+}
@@ -0,0 +1,75 @@
+// Waiting datagram sends give up with -6 when the send buffer has no room in
+// time, datagrams that can never be sent fail right away, and datagrams sent
+// through a full buffer are delayed rather than discarded.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use moq_quic::webtransport::{moq_webtransport_max_datagram_size, moq_webtransport_send_datagram_wait};
+use moq_quic::MoqConnectOptions;
+use tokio::sync::mpsc;
+
+const SEND_BUFFER: usize = 1000;
+
+#[test]
+fn waiting_send_times_out_without_room() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let addr = common::webtransport_server(&server_runtime, |session| async move {
+        session.closed().await;
+    });
+    let options = MoqConnectOptions { datagram_send_buffer_size: SEND_BUFFER as u64, ..Default::default() };
+    let session_id = common::connect_webtransport_with_options(addr, &options);
+
+    // Fits in a packet but not in the send buffer, so room never frees up
+    let datagram = [0u8; SEND_BUFFER + 100];
+    assert!(moq_webtransport_max_datagram_size(session_id) as usize >= datagram.len());
+    let started = Instant::now();
+    assert_eq!(moq_webtransport_send_datagram_wait(session_id, datagram.as_ptr(), datagram.len(), 50), -6);
+    assert!(started.elapsed() >= Duration::from_millis(50));
+
+    // A datagram that fits is sent without waiting for the timeout
+    let small = [0u8; 100];
+    assert_eq!(moq_webtransport_send_datagram_wait(session_id, small.as_ptr(), small.len(), 5000), 100);
+
+    // Too large for any packet: fails at once rather than waiting
+    let max = moq_webtransport_max_datagram_size(session_id) as usize;
+    let oversized = vec![0u8; max + 1];
+    let started = Instant::now();
+    assert_eq!(moq_webtransport_send_datagram_wait(session_id, oversized.as_ptr(), oversized.len(), 5000), -3);
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    assert_eq!(moq_webtransport_send_datagram_wait(u64::MAX, small.as_ptr(), small.len(), 50), -1);
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
+
+#[test]
+fn waiting_sends_discard_nothing() {
+    const COUNT: usize = 200;
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (tx, mut received) = mpsc::unbounded_channel();
+    let addr = common::webtransport_server(&server_runtime, |session| async move {
+        while let Ok(datagram) = session.read_datagram().await {
+            let _ = tx.send(datagram[0]);
+        }
+    });
+    let options = MoqConnectOptions { datagram_send_buffer_size: SEND_BUFFER as u64, ..Default::default() };
+    let session_id = common::connect_webtransport_with_options(addr, &options);
+
+    // Each datagram takes half the send buffer, so the sends outpace it
+    for i in 0..COUNT {
+        let datagram = [i as u8; SEND_BUFFER / 2];
+        assert_eq!(moq_webtransport_send_datagram_wait(session_id, datagram.as_ptr(), datagram.len(), 0), datagram.len() as i64);
+    }
+
+    let tags = server_runtime.block_on(async {
+        let mut tags = Vec::new();
+        while tags.len() < COUNT {
+            let tag = tokio::time::timeout(Duration::from_secs(10), received.recv()).await;
+            tags.push(tag.expect("datagrams were not delivered in time").unwrap());
+        }
+        tags
+    });
+    assert_eq!(tags, (0..COUNT).map(|i| i as u8).collect::<Vec<_>>());
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
@@ -0,0 +1,126 @@
+// A data stream whose receive buffer is full either stalls, leaving the
+// sender blocked on flow control until the application reads, or drops what
+// does not fit and reports it.
+
+mod common;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use moq_quic::MoqConnectOptions;
+
+const BUFFER_CAP: usize = 16 * 1024;
+const STREAM_WINDOW: usize = 64 * 1024;
+const PAYLOAD: usize = 1024 * 1024;
+
+fn payload() -> Vec<u8> {
+    (0..PAYLOAD).map(|i| (i % 251) as u8).collect()
+}
+
+/// Start a server that writes `payload()` on one uni stream, setting the flag once `write_all` returns
+fn start_server(runtime: &tokio::runtime::Runtime) -> (std::net::SocketAddr, Arc<AtomicBool>) {
+    let written = Arc::new(AtomicBool::new(false));
+    let written_flag = written.clone();
+    let addr = common::quic_server(runtime, move |connection| async move {
+        let mut stream = connection.open_uni().await.unwrap();
+        stream.write_all(&payload()).await.unwrap();
+        stream.finish().unwrap();
+        written_flag.store(true, Ordering::SeqCst);
+        connection.closed().await;
+    });
+    (addr, written)
+}
+
+fn connect(addr: std::net::SocketAddr, data_overflow_policy: u32) -> u64 {
+    let options = MoqConnectOptions {
+        data_recv_buffer_size: BUFFER_CAP as u64,
+        stream_receive_window: STREAM_WINDOW as u64,
+        data_overflow_policy,
+        ..Default::default()
+    };
+    common::connect_quic_with_options(addr, &options)
+}
+
+fn data_stream(connection_id: u64) -> u64 {
+    common::wait_for("the data stream", || {
+        let mut stream_id = 0;
+        (moq_quic::moq_quic_get_data_streams(connection_id, &mut stream_id, 1) == 1).then_some(stream_id)
+    })
+}
+
+fn stream_bytes_dropped(connection_id: u64) -> u64 {
+    let mut stats = moq_quic::MoqConnectionStats::default();
+    assert_eq!(moq_quic::moq_quic_get_stats(connection_id, &mut stats), 0);
+    stats.stream_bytes_dropped
+}
+
+fn stream_state(connection_id: u64, stream_id: u64) -> u32 {
+    moq_quic::moq_quic_get_data_stream_state(connection_id, stream_id, std::ptr::null_mut()) as u32
+}
+
+/// Read whatever is buffered until the stream has finished and its buffer is empty
+fn read_to_end(connection_id: u64, stream_id: u64) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut buffer = vec![0u8; 8192];
+    common::wait_for("the end of the stream", || {
+        let finished = stream_state(connection_id, stream_id) == moq_quic::MOQ_STREAM_STATE_FINISHED;
+        let n = moq_quic::moq_quic_recv_data(connection_id, stream_id, buffer.as_mut_ptr(), buffer.len());
+        assert!(n >= 0);
+        data.extend_from_slice(&buffer[..n as usize]);
+        (finished && n == 0).then_some(())
+    });
+    data
+}
+
+#[test]
+fn stall_holds_back_the_sender_until_data_is_read() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (addr, written) = start_server(&server_runtime);
+    let connection_id = connect(addr, moq_quic::MOQ_OVERFLOW_STALL);
+    let stream_id = data_stream(connection_id);
+
+    // Nothing is read, so the buffer and then the flow control window fill up
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(!written.load(Ordering::SeqCst), "sender was not held back by flow control");
+    assert_eq!(stream_bytes_dropped(connection_id), 0);
+
+    // Reading frees buffer space, the reader resumes and the sender completes
+    assert_eq!(read_to_end(connection_id, stream_id), payload());
+    assert!(written.load(Ordering::SeqCst));
+    assert_eq!(stream_bytes_dropped(connection_id), 0);
+
+    moq_quic::moq_quic_close(connection_id);
+}
+
+#[test]
+fn drop_discards_what_does_not_fit() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (addr, written) = start_server(&server_runtime);
+    let connection_id = connect(addr, moq_quic::MOQ_OVERFLOW_DROP);
+    let stream_id = data_stream(connection_id);
+
+    // The reader keeps draining the stream without the application reading
+    common::wait_for("the stream to finish", || {
+        (stream_state(connection_id, stream_id) == moq_quic::MOQ_STREAM_STATE_FINISHED).then_some(())
+    });
+    assert!(written.load(Ordering::SeqCst));
+
+    let data = read_to_end(connection_id, stream_id);
+    assert!(data.len() <= BUFFER_CAP);
+    assert_eq!(data[..], payload()[..data.len()]);
+    assert_eq!(stream_bytes_dropped(connection_id), (PAYLOAD - data.len()) as u64);
+
+    let mut dropped = 0;
+    let mut event = moq_quic::MoqEvent::default();
+    while moq_quic::moq_quic_poll_event(connection_id, &mut event, std::ptr::null_mut(), 0) == 1 {
+        if event.kind == moq_quic::MOQ_EVENT_BUFFER_OVERFLOW {
+            assert_eq!(event.buffer, moq_quic::MOQ_BUFFER_DATA_STREAM);
+            assert_eq!(event.stream_id, stream_id);
+            dropped += event.dropped;
+        }
+    }
+    assert_eq!(dropped, (PAYLOAD - data.len()) as u64);
+
+    moq_quic::moq_quic_close(connection_id);
+}
@@ -0,0 +1,83 @@
+// moq_quic_connect_start returns at once; polling the handle follows the
+// connect to its final state, and cancel stops one that is still in flight.
+
+mod common;
+
+use std::ffi::CString;
+use std::net::{SocketAddr, UdpSocket};
+use std::ptr;
+
+use moq_quic::MoqConnectOptions;
+
+fn connect_start(addr: SocketAddr, options: &MoqConnectOptions) -> u64 {
+    moq_quic::moq_quic_init();
+    let host = CString::new("127.0.0.1").unwrap();
+    let alpn = CString::new(common::ALPN).unwrap();
+    let mut handle = 0;
+    let result = moq_quic::moq_quic_connect_start(host.as_ptr(), addr.port(), 1, 0, alpn.as_ptr(), options, &mut handle);
+    assert_eq!(result, 0);
+    handle
+}
+
+/// Poll until the connect leaves the in-progress states
+fn wait_until_done(handle: u64) -> (i32, u64, i32) {
+    common::wait_for("the connect to finish", || {
+        let mut connection_id = 0;
+        let mut error = 0;
+        match moq_quic::moq_quic_connect_poll(handle, &mut connection_id, &mut error) {
+            moq_quic::MOQ_CONNECT_RESOLVING | moq_quic::MOQ_CONNECT_HANDSHAKING => None,
+            state => Some((state, connection_id, error)),
+        }
+    })
+}
+
+#[test]
+fn connect_completes_in_the_background() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let addr = common::quic_server(&server_runtime, |connection| async move {
+        connection.closed().await;
+    });
+
+    let handle = connect_start(addr, &MoqConnectOptions::default());
+    let (state, connection_id, _) = wait_until_done(handle);
+    assert_eq!(state, moq_quic::MOQ_CONNECT_CONNECTED);
+    assert_eq!(moq_quic::moq_quic_is_connected(connection_id), 1);
+
+    // Freeing the handle leaves the connection to the caller
+    assert_eq!(moq_quic::moq_quic_connect_free(handle), 0);
+    assert_eq!(moq_quic::moq_quic_connect_poll(handle, ptr::null_mut(), ptr::null_mut()), -1);
+    assert_eq!(moq_quic::moq_quic_is_connected(connection_id), 1);
+    moq_quic::moq_quic_close(connection_id);
+}
+
+#[test]
+fn unanswered_connect_can_be_cancelled() {
+    // Bound but never answered, so the handshake stays in flight
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let handle = connect_start(silent.local_addr().unwrap(), &MoqConnectOptions::default());
+
+    common::wait_for("the handshake to start", || {
+        let state = moq_quic::moq_quic_connect_poll(handle, ptr::null_mut(), ptr::null_mut());
+        (state == moq_quic::MOQ_CONNECT_HANDSHAKING).then_some(())
+    });
+    assert_eq!(moq_quic::moq_quic_connect_cancel(handle), moq_quic::MOQ_CONNECT_HANDSHAKING);
+    assert_eq!(
+        moq_quic::moq_quic_connect_poll(handle, ptr::null_mut(), ptr::null_mut()),
+        moq_quic::MOQ_CONNECT_CANCELLED
+    );
+    assert_eq!(moq_quic::moq_quic_connect_cancel(handle), moq_quic::MOQ_CONNECT_CANCELLED);
+    assert_eq!(moq_quic::moq_quic_connect_free(handle), 0);
+    assert_eq!(moq_quic::moq_quic_connect_cancel(handle), -1);
+}
+
+#[test]
+fn unanswered_connect_fails_after_the_idle_timeout() {
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let options = MoqConnectOptions { idle_timeout_ms: 200, ..Default::default() };
+    let handle = connect_start(silent.local_addr().unwrap(), &options);
+
+    let (state, _, error) = wait_until_done(handle);
+    assert_eq!(state, moq_quic::MOQ_CONNECT_FAILED);
+    assert!(error < 0);
+    assert_eq!(moq_quic::moq_quic_connect_free(handle), 0);
+}
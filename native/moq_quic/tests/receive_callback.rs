@@ -0,0 +1,63 @@
+// With a receive callback registered, datagrams and data stream chunks are
+// pushed to it as they arrive instead of waiting for the recv calls.
+
+mod common;
+
+use std::ffi::c_void;
+use std::sync::Mutex;
+
+use tokio::sync::oneshot;
+
+const DATAGRAM: &[u8] = b"pushed datagram";
+const STREAM_DATA: &[u8] = b"pushed stream data";
+
+/// Everything the callback was given: (connection, kind, stream, data)
+type Deliveries = Mutex<Vec<(u64, u32, u64, Vec<u8>)>>;
+
+extern "C" fn on_receive(user_data: *mut c_void, connection_id: u64, kind: u32, stream_id: u64, data: *mut u8, len: usize) {
+    let deliveries = unsafe { &*(user_data as *const Deliveries) };
+    let bytes = unsafe { std::slice::from_raw_parts(data, len) }.to_vec();
+    deliveries.lock().unwrap().push((connection_id, kind, stream_id, bytes));
+    moq_quic::moq_buffer_free(data, len);
+}
+
+#[test]
+fn datagrams_and_stream_data_are_pushed() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (go, go_rx) = oneshot::channel::<()>();
+    let addr = common::quic_server(&server_runtime, |connection| async move {
+        // Only send once the client has registered its callback
+        let _ = go_rx.await;
+        connection.send_datagram(DATAGRAM.to_vec().into()).unwrap();
+        let mut stream = connection.open_uni().await.unwrap();
+        stream.write_all(STREAM_DATA).await.unwrap();
+        stream.finish().unwrap();
+        connection.closed().await;
+    });
+    let connection_id = common::connect_quic(addr);
+
+    let deliveries: &'static Deliveries = Box::leak(Box::default());
+    let user_data = deliveries as *const Deliveries as *mut c_void;
+    assert_eq!(moq_quic::moq_quic_set_receive_callback(connection_id, Some(on_receive), user_data), 0);
+    assert_eq!(moq_quic::moq_quic_set_receive_callback(u64::MAX, Some(on_receive), user_data), -1);
+    go.send(()).unwrap();
+
+    let (datagram, stream_id, stream_data) = common::wait_for("pushed data", || {
+        let deliveries = deliveries.lock().unwrap();
+        let datagram = deliveries.iter().find(|d| d.1 == moq_quic::MOQ_RECEIVE_DATAGRAM)?;
+        let chunks: Vec<_> = deliveries.iter().filter(|d| d.1 == moq_quic::MOQ_RECEIVE_DATA_STREAM).collect();
+        let stream_data: Vec<u8> = chunks.iter().flat_map(|d| d.3.iter().copied()).collect();
+        (stream_data.len() == STREAM_DATA.len()).then(|| (datagram.clone(), chunks[0].2, stream_data))
+    });
+    assert_eq!(datagram, (connection_id, moq_quic::MOQ_RECEIVE_DATAGRAM, 0, DATAGRAM.to_vec()));
+    assert_eq!(stream_data, STREAM_DATA);
+    assert!(deliveries.lock().unwrap().iter().all(|d| d.0 == connection_id));
+
+    // Nothing was left for the polling calls
+    let mut buffer = [0u8; 64];
+    assert_eq!(moq_quic::moq_quic_recv_datagram(connection_id, buffer.as_mut_ptr(), buffer.len()), 0);
+    assert_eq!(moq_quic::moq_quic_recv_data(connection_id, stream_id, buffer.as_mut_ptr(), buffer.len()), 0);
+
+    assert_eq!(moq_quic::moq_quic_set_receive_callback(connection_id, None, std::ptr::null_mut()), 0);
+    moq_quic::moq_quic_close(connection_id);
+}
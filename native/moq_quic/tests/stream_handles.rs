@@ -0,0 +1,44 @@
+// Stream handles are allocated per connection, so handles on one connection
+// do not depend on traffic on another, and resolve to the QUIC stream ID.
+
+mod common;
+
+fn open_stream(connection_id: u64) -> u64 {
+    let mut stream_id = 0;
+    assert_eq!(moq_quic::moq_quic_open_stream(connection_id, &mut stream_id), 0);
+    stream_id
+}
+
+fn stream_info(connection_id: u64, stream_id: u64) -> Option<moq_quic::MoqStreamInfo> {
+    let mut info = moq_quic::MoqStreamInfo::default();
+    (moq_quic::moq_quic_get_stream_info(connection_id, stream_id, &mut info) == 0).then_some(info)
+}
+
+#[test]
+fn handles_are_allocated_per_connection() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let connect = || {
+        let addr = common::quic_server(&server_runtime, |connection| async move {
+            connection.closed().await;
+        });
+        common::connect_quic(addr)
+    };
+    let first = connect();
+    let second = connect();
+
+    let first_streams: Vec<_> = (0..3).map(|_| open_stream(first)).collect();
+    let second_stream = open_stream(second);
+    assert_eq!(second_stream, first_streams[0]);
+    assert!(stream_info(second, first_streams[2]).is_none());
+
+    // Client-initiated uni streams are 2, 6, 10, ... after the control stream took bidi 0
+    for (n, stream_id) in first_streams.iter().enumerate() {
+        let info = stream_info(first, *stream_id).unwrap();
+        assert_eq!(info.quic_stream_id, 2 + 4 * n as u64);
+        assert_eq!(info.direction, moq_quic::MOQ_STREAM_DIR_UNI);
+        assert_eq!(info.initiator, moq_quic::MOQ_STREAM_INITIATOR_LOCAL);
+    }
+
+    moq_quic::moq_quic_close(first);
+    moq_quic::moq_quic_close(second);
+}
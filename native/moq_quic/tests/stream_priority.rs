@@ -0,0 +1,93 @@
+// A high-priority stream opened after a bulk low-priority stream must still
+// be delivered first when the connection is congestion limited.
+
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use quinn::{TransportConfig, VarInt};
+use tokio::sync::mpsc;
+
+const CHUNK: usize = 64 * 1024;
+const LOW_CHUNKS: usize = 48;
+const HIGH_CHUNKS: usize = 48;
+
+/// Start a server that reads every incoming uni stream to the end
+///
+/// Each completed stream is reported as its tag (first byte) together with
+/// how many bytes of the low-priority stream had arrived at that point.
+fn start_server(runtime: &tokio::runtime::Runtime) -> (SocketAddr, mpsc::UnboundedReceiver<(u8, usize)>) {
+    // quinn only buffers writes the peer has granted credit for, so make the
+    // windows large enough that both streams are queued at once and only the
+    // congestion window limits sending
+    let mut transport = TransportConfig::default();
+    transport.receive_window(VarInt::from_u32(16 * 1024 * 1024));
+    transport.stream_receive_window(VarInt::from_u32(8 * 1024 * 1024));
+    let mut config = common::server_config(common::ALPN);
+    config.transport_config(Arc::new(transport));
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let addr = common::quic_server_with_config(runtime, config, |connection| async move {
+        let low_received = Arc::new(AtomicUsize::new(0));
+        while let Ok(mut stream) = connection.accept_uni().await {
+            let tx = tx.clone();
+            let low_received = low_received.clone();
+            tokio::spawn(async move {
+                let mut tag = None;
+                while let Some(chunk) = stream.read_chunk(usize::MAX, true).await.unwrap() {
+                    if *tag.get_or_insert(chunk.bytes[0]) == b'L' {
+                        low_received.fetch_add(chunk.bytes.len(), Ordering::SeqCst);
+                    }
+                }
+                let _ = tx.send((tag.unwrap(), low_received.load(Ordering::SeqCst)));
+            });
+        }
+    });
+    (addr, rx)
+}
+
+fn write_chunks(connection_id: u64, stream_id: u64, tag: u8, chunks: usize) {
+    let chunk = vec![tag; CHUNK];
+    for _ in 0..chunks {
+        let written = moq_quic::moq_quic_stream_write(connection_id, stream_id, chunk.as_ptr(), chunk.len());
+        assert_eq!(written, CHUNK as i64);
+    }
+    assert_eq!(moq_quic::moq_quic_stream_finish(connection_id, stream_id), 0);
+}
+
+#[test]
+fn high_priority_stream_drains_first() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (addr, mut completed) = start_server(&server_runtime);
+    let connection_id = common::connect_quic(addr);
+
+    // Queue the bulk stream first so it is already backlogged when the urgent one opens
+    let mut low = 0;
+    assert_eq!(moq_quic::moq_quic_open_stream_with_priority(connection_id, 0, &mut low), 0);
+    write_chunks(connection_id, low, b'L', LOW_CHUNKS);
+
+    let mut high = 0;
+    assert_eq!(moq_quic::moq_quic_open_stream_with_priority(connection_id, 0, &mut high), 0);
+    assert_eq!(moq_quic::moq_quic_stream_set_priority(connection_id, high, 10), 0);
+    write_chunks(connection_id, high, b'H', HIGH_CHUNKS);
+
+    let (first, low_received) = server_runtime.block_on(async {
+        tokio::time::timeout(Duration::from_secs(30), completed.recv())
+            .await
+            .expect("streams were not delivered in time")
+            .unwrap()
+    });
+    assert_eq!(first, b'H');
+    // Round-robin scheduling would have delivered about as much of the low
+    // stream by now; with priority only its head start got through
+    assert!(
+        low_received < LOW_CHUNKS * CHUNK / 2,
+        "{} low-priority bytes arrived before the high-priority stream finished",
+        low_received
+    );
+
+    moq_quic::moq_quic_close(connection_id);
+}
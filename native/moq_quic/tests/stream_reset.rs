@@ -0,0 +1,153 @@
+// The peer sees a local reset with its error code, and STOP_SENDING from the
+// peer is reported as an event and retires the stream writer.
+
+mod common;
+
+use quinn::{ReadError, ReadToEndError, VarInt};
+use tokio::sync::oneshot;
+
+fn open_stream(connection_id: u64) -> u64 {
+    let mut stream_id = 0;
+    assert_eq!(moq_quic::moq_quic_open_stream(connection_id, &mut stream_id), 0);
+    stream_id
+}
+
+fn write(connection_id: u64, stream_id: u64, data: &[u8]) -> i64 {
+    moq_quic::moq_quic_stream_write(connection_id, stream_id, data.as_ptr(), data.len())
+}
+
+#[test]
+fn peer_sees_reset_code() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (seen_tx, seen_rx) = oneshot::channel();
+    let addr = common::quic_server(&server_runtime, |connection| async move {
+        let mut stream = connection.accept_uni().await.unwrap();
+        let _ = seen_tx.send(stream.read_to_end(usize::MAX).await);
+        connection.closed().await;
+    });
+    let connection_id = common::connect_quic(addr);
+
+    let stream_id = open_stream(connection_id);
+    assert_eq!(write(connection_id, stream_id, b"partial object"), 14);
+    assert_eq!(moq_quic::moq_quic_stream_reset(connection_id, stream_id, 1 << 62), -2);
+    assert_eq!(moq_quic::moq_quic_stream_reset(connection_id, stream_id, 0x17), 0);
+
+    let seen = server_runtime.block_on(seen_rx).unwrap();
+    assert!(matches!(seen, Err(ReadToEndError::Read(ReadError::Reset(code))) if code == VarInt::from_u32(0x17)));
+
+    // The stream is gone once reset
+    assert_eq!(moq_quic::moq_quic_stream_reset(connection_id, stream_id, 0x17), -1);
+    assert_eq!(write(connection_id, stream_id, b"more"), -1);
+    moq_quic::moq_quic_close(connection_id);
+}
+
+#[test]
+fn stop_sending_is_reported_and_retires_the_writer() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let addr = common::quic_server(&server_runtime, |connection| async move {
+        let mut stream = connection.accept_uni().await.unwrap();
+        let mut first = [0u8; 4];
+        stream.read_exact(&mut first).await.unwrap();
+        stream.stop(VarInt::from_u32(0x21)).unwrap();
+        connection.closed().await;
+    });
+    let connection_id = common::connect_quic(addr);
+
+    let stream_id = open_stream(connection_id);
+    assert_eq!(write(connection_id, stream_id, b"data"), 4);
+
+    let event = common::wait_for("the STOP_SENDING event", || {
+        let mut event = moq_quic::MoqEvent::default();
+        while moq_quic::moq_quic_poll_event(connection_id, &mut event, std::ptr::null_mut(), 0) == 1 {
+            if event.kind == moq_quic::MOQ_EVENT_STREAM_STOPPED {
+                return Some(event);
+            }
+        }
+        None
+    });
+    assert_eq!(event.stream_id, stream_id);
+    assert_eq!(event.error_code, 0x21);
+
+    // Writes and resets no longer find the stream
+    common::wait_for("the writer to be dropped", || (write(connection_id, stream_id, b"late") == -1).then_some(()));
+    assert_eq!(moq_quic::moq_quic_stream_reset(connection_id, stream_id, 0x17), -1);
+    moq_quic::moq_quic_close(connection_id);
+}
+
+fn open_webtransport_stream(session_id: u64) -> u64 {
+    let mut stream_id = 0;
+    assert_eq!(moq_quic::webtransport::moq_webtransport_open_uni_stream(session_id, &mut stream_id), 0);
+    stream_id
+}
+
+fn write_webtransport(session_id: u64, stream_id: u64, data: &[u8]) -> i64 {
+    moq_quic::webtransport::moq_webtransport_stream_write(session_id, stream_id, data.as_ptr(), data.len())
+}
+
+/// Wait until some stream data has been read
+async fn read_some(stream: &mut web_transport_quinn::RecvStream) {
+    let mut buffer = [0u8; 4];
+    stream.read(&mut buffer).await.unwrap().unwrap();
+}
+
+#[test]
+fn webtransport_peer_sees_reset_code() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (started_tx, started_rx) = std::sync::mpsc::channel();
+    let (seen_tx, seen_rx) = oneshot::channel();
+    let addr = common::webtransport_server(&server_runtime, |session| async move {
+        let mut stream = session.accept_uni().await.unwrap();
+        read_some(&mut stream).await;
+        let _ = started_tx.send(());
+        let _ = seen_tx.send(stream.read_to_end(usize::MAX).await);
+        session.closed().await;
+    });
+    let session_id = common::connect_webtransport(addr);
+
+    let stream_id = open_webtransport_stream(session_id);
+    assert_eq!(write_webtransport(session_id, stream_id, b"partial object"), 14);
+    // Reset only once the stream header got through, or the server never learns of the stream
+    started_rx.recv().unwrap();
+    assert_eq!(moq_quic::webtransport::moq_webtransport_stream_reset(session_id, stream_id, 0x17), 0);
+
+    let seen = server_runtime.block_on(seen_rx).unwrap();
+    assert!(matches!(
+        seen,
+        Err(web_transport_quinn::ReadToEndError::ReadError(web_transport_quinn::ReadError::Reset(0x17)))
+    ));
+    assert_eq!(moq_quic::webtransport::moq_webtransport_stream_reset(session_id, stream_id, 0x17), -1);
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
+
+#[test]
+fn webtransport_write_after_stop_sending_fails_with_its_code() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let addr = common::webtransport_server(&server_runtime, |session| async move {
+        let mut stream = session.accept_uni().await.unwrap();
+        read_some(&mut stream).await;
+        stream.stop(0x21).unwrap();
+        session.closed().await;
+    });
+    let session_id = common::connect_webtransport(addr);
+
+    let stream_id = open_webtransport_stream(session_id);
+    assert_eq!(write_webtransport(session_id, stream_id, b"data"), 4);
+
+    // WebTransport only learns of STOP_SENDING when a write fails
+    let result = common::wait_for("a write to fail", || match write_webtransport(session_id, stream_id, b"more") {
+        4 => None,
+        result => Some(result),
+    });
+    assert_eq!(result, -3);
+    let mut event = moq_quic::MoqEvent::default();
+    let mut stopped = None;
+    while moq_quic::webtransport::moq_webtransport_poll_event(session_id, &mut event, std::ptr::null_mut(), 0) == 1 {
+        if event.kind == moq_quic::MOQ_EVENT_STREAM_STOPPED {
+            stopped = Some((event.stream_id, event.error_code));
+        }
+    }
+    assert_eq!(stopped, Some((stream_id, 0x21)));
+
+    assert_eq!(write_webtransport(session_id, stream_id, b"late"), -1);
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
@@ -0,0 +1,79 @@
+// Incoming data streams remember how they ended: a reset by the peer keeps
+// its error code, and stopping a stream ourselves reaches the peer.
+
+mod common;
+
+use quinn::VarInt;
+use tokio::sync::oneshot;
+
+fn data_stream(connection_id: u64) -> u64 {
+    common::wait_for("the data stream", || {
+        let mut stream_id = 0;
+        (moq_quic::moq_quic_get_data_streams(connection_id, &mut stream_id, 1) == 1).then_some(stream_id)
+    })
+}
+
+/// Wait for the stream to leave the open state, returning its final state and code
+fn final_state(connection_id: u64, stream_id: u64) -> (u32, u64) {
+    common::wait_for("the stream to end", || {
+        let mut error_code = 0;
+        let state = moq_quic::moq_quic_get_data_stream_state(connection_id, stream_id, &mut error_code);
+        assert!(state >= 0);
+        (state as u32 != moq_quic::MOQ_STREAM_STATE_OPEN).then_some((state as u32, error_code))
+    })
+}
+
+#[test]
+fn peer_reset_is_recorded_with_its_code() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let addr = common::quic_server(&server_runtime, |connection| async move {
+        let mut stream = connection.open_uni().await.unwrap();
+        stream.write_all(b"object header").await.unwrap();
+        stream.reset(VarInt::from_u32(0x33)).unwrap();
+        connection.closed().await;
+    });
+    let connection_id = common::connect_quic(addr);
+    let stream_id = data_stream(connection_id);
+
+    assert_eq!(final_state(connection_id, stream_id), (moq_quic::MOQ_STREAM_STATE_RESET, 0x33));
+    let reset = common::wait_for("the reset event", || {
+        let mut event = moq_quic::MoqEvent::default();
+        while moq_quic::moq_quic_poll_event(connection_id, &mut event, std::ptr::null_mut(), 0) == 1 {
+            if event.kind == moq_quic::MOQ_EVENT_DATA_STREAM_RESET {
+                return Some(event);
+            }
+        }
+        None
+    });
+    assert_eq!((reset.stream_id, reset.error_code), (stream_id, 0x33));
+
+    // Nothing left to stop
+    assert_eq!(moq_quic::moq_quic_stop_data_stream(connection_id, stream_id, 0x44), -3);
+    assert_eq!(moq_quic::moq_quic_close_data_stream(connection_id, stream_id), 0);
+    assert_eq!(moq_quic::moq_quic_get_data_stream_state(connection_id, stream_id, std::ptr::null_mut()), -1);
+    moq_quic::moq_quic_close(connection_id);
+}
+
+#[test]
+fn stopping_a_stream_reaches_the_peer() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (stopped_tx, stopped_rx) = oneshot::channel();
+    let addr = common::quic_server(&server_runtime, |connection| async move {
+        let mut stream = connection.open_uni().await.unwrap();
+        stream.write_all(b"old group").await.unwrap();
+        let _ = stopped_tx.send(stream.stopped().await);
+        connection.closed().await;
+    });
+    let connection_id = common::connect_quic(addr);
+    let stream_id = data_stream(connection_id);
+
+    assert_eq!(moq_quic::moq_quic_stop_data_stream(connection_id, stream_id, 1 << 62), -2);
+    assert_eq!(moq_quic::moq_quic_stop_data_stream(connection_id, stream_id, 0x44), 0);
+    assert_eq!(final_state(connection_id, stream_id), (moq_quic::MOQ_STREAM_STATE_STOPPED, 0x44));
+    let stopped = server_runtime.block_on(stopped_rx).unwrap();
+    assert_eq!(stopped.unwrap(), Some(VarInt::from_u32(0x44)));
+
+    assert_eq!(moq_quic::moq_quic_stop_data_stream(connection_id, stream_id, 0x44), -3);
+    assert_eq!(moq_quic::moq_quic_stop_data_stream(connection_id, u64::MAX, 0x44), -1);
+    moq_quic::moq_quic_close(connection_id);
+}
@@ -0,0 +1,349 @@
+// moq_webtransport_connect_url sends the URL as given, can connect somewhere
+// other than the URL host with its own SNI, and handles IPv6 literals. The
+// client offers its subprotocols in order and learns which one the server
+// picked from the CONNECT response. Extra request header fields reach the
+// server, and the response header fields can be read back. A pending connect
+// sends the same request.
+
+mod common;
+
+use std::ffi::CString;
+use std::net::SocketAddr;
+use std::ptr;
+
+use tokio::sync::oneshot;
+use web_transport_quinn::proto::{ConnectRequest, ConnectResponse};
+
+// Subprotocols the test server supports
+const SUPPORTED_PROTOCOLS: &[&str] = &["moq-00", "moqt-15", "moqt-14"];
+
+/// What the server saw of the first session
+struct Seen {
+    url: String,
+    sni: Option<String>,
+    protocols: Vec<String>,
+    headers: Vec<(String, String)>,
+}
+
+/// Select the first offered subprotocol the server supports and add an
+/// `x-relay` field to the response
+fn respond(request: &ConnectRequest) -> ConnectResponse {
+    let response = ConnectResponse::OK.with_header("x-relay", "edge-1");
+    match request.protocols.iter().find(|p| SUPPORTED_PROTOCOLS.contains(&p.as_str())) {
+        Some(protocol) => response.with_protocol(protocol.clone()),
+        None => response,
+    }
+}
+
+/// Start a WebTransport server on `bind` that accepts one session
+///
+/// Returns `None` if the address cannot be bound (e.g. no IPv6 loopback).
+fn start_server(runtime: &tokio::runtime::Runtime, bind: &str) -> Option<(SocketAddr, oneshot::Receiver<Seen>)> {
+    let (seen_tx, seen_rx) = oneshot::channel();
+    let addr = common::webtransport_server_on(runtime, bind, respond, |session| async move {
+        let request = session.request();
+        let seen = Seen {
+            url: request.url.to_string(),
+            sni: common::sni(&session),
+            protocols: request.protocols.clone(),
+            headers: request.headers.clone(),
+        };
+        let _ = seen_tx.send(seen);
+        // Keep the server alive until the client goes away
+        session.closed().await;
+    })?;
+    Some((addr, seen_rx))
+}
+
+/// Build a CONNECT request and hand it to `connect`
+fn with_request<R>(
+    url: &str,
+    sni: Option<&str>,
+    address: Option<&str>,
+    protocols: &[moq_quic::MoqProtocol],
+    headers: &[moq_quic::MoqHttpHeader],
+    connect: impl FnOnce(&moq_quic::MoqConnectRequest) -> R,
+) -> R {
+    let url = CString::new(url).unwrap();
+    let sni = sni.map(|s| CString::new(s).unwrap());
+    let address = address.map(|a| CString::new(a).unwrap());
+    let request = moq_quic::MoqConnectRequest {
+        url: url.as_ptr(),
+        sni: sni.as_ref().map_or(ptr::null(), |s| s.as_ptr()),
+        address: address.as_ref().map_or(ptr::null(), |a| a.as_ptr()),
+        protocols: protocols.as_ptr(),
+        protocol_count: protocols.len(),
+        headers: headers.as_ptr(),
+        header_count: headers.len(),
+    };
+    connect(&request)
+}
+
+fn connect_request(
+    url: &str,
+    sni: Option<&str>,
+    address: Option<&str>,
+    protocols: &[moq_quic::MoqProtocol],
+    headers: &[moq_quic::MoqHttpHeader],
+) -> (i32, u64) {
+    with_request(url, sni, address, protocols, headers, |request| {
+        let mut session_id = 0;
+        let result = moq_quic::webtransport::moq_webtransport_connect_url(request, 1, ptr::null(), &mut session_id);
+        (result, session_id)
+    })
+}
+
+fn connect_url(url: &str, sni: Option<&str>, address: Option<&str>, protocol_name: &'static str) -> (i32, u64) {
+    connect_request(url, sni, address, &[protocol(protocol_name)], &[])
+}
+
+#[test]
+fn address_override_keeps_url_and_sets_sni() {
+    moq_quic::webtransport::moq_webtransport_init();
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (addr, seen) = start_server(&server_runtime, "127.0.0.1:0").unwrap();
+
+    // The URL host does not resolve, so the session can only come up through the override
+    let url = "https://relay.invalid:4443/moq/room?token=a%20b&draft=7";
+    let address = format!("127.0.0.1:{}", addr.port());
+    let (result, session_id) = connect_url(url, Some("localhost"), Some(&address), "moq-00");
+    assert_eq!(result, 0);
+
+    let seen = server_runtime.block_on(seen).unwrap();
+    assert_eq!(seen.url, url);
+    assert_eq!(seen.sni.as_deref(), Some("localhost"));
+    assert_eq!(moq_quic::webtransport::moq_webtransport_get_response_status(session_id), 200);
+
+    // Only the address override was tried, and the attempts belong to this session
+    let mut attempts = [0u8; 256];
+    let n = moq_quic::webtransport::moq_webtransport_get_connect_attempts(session_id, attempts.as_mut_ptr(), attempts.len());
+    assert_eq!(&attempts[..n as usize], format!("{}: connected", address).as_bytes());
+    assert_eq!(moq_quic::webtransport::moq_webtransport_get_connect_attempts(u64::MAX, attempts.as_mut_ptr(), attempts.len()), -1);
+
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
+
+#[test]
+fn ipv6_literal_hosts_are_bracketed() {
+    moq_quic::webtransport::moq_webtransport_init();
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let Some((addr, seen)) = start_server(&server_runtime, "[::1]:0") else {
+        eprintln!("IPv6 loopback unavailable, skipping");
+        return;
+    };
+
+    let url = format!("https://[::1]:{}/moq", addr.port());
+    let (result, session_id) = connect_url(&url, None, None, "moq-00");
+    assert_eq!(result, 0);
+    assert_eq!(server_runtime.block_on(seen).unwrap().url, url);
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+
+    // The host/port/path form accepts an unbracketed literal
+    let (addr, seen) = start_server(&server_runtime, "[::1]:0").unwrap();
+    let host = CString::new("::1").unwrap();
+    let path = CString::new("/moq").unwrap();
+    let protocol = CString::new("moq-00").unwrap();
+    let mut session_id = 0;
+    let result = moq_quic::webtransport::moq_webtransport_connect(
+        host.as_ptr(),
+        addr.port(),
+        path.as_ptr(),
+        protocol.as_ptr(),
+        1,
+        &mut session_id,
+    );
+    assert_eq!(result, 0);
+    assert_eq!(server_runtime.block_on(seen).unwrap().url, format!("https://[::1]:{}/moq", addr.port()));
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
+
+#[test]
+fn invalid_targets_are_rejected() {
+    moq_quic::webtransport::moq_webtransport_init();
+    assert_eq!(connect_url("relay.example.com/moq", None, None, "moq-00").0, -8);
+    assert_eq!(connect_url("http://relay.example.com/moq", None, None, "moq-00").0, -8);
+    assert_eq!(connect_url("https://relay.example.com/moq", None, Some("::1]:443"), "moq-00").0, -8);
+    assert_eq!(connect_url("https://relay.example.com/moq", None, Some("relay:port"), "moq-00").0, -8);
+}
+
+fn negotiated_protocol(session_id: u64) -> String {
+    let mut protocol = [0u8; 64];
+    let n = moq_quic::webtransport::moq_webtransport_get_protocol(session_id, protocol.as_mut_ptr(), protocol.len());
+    assert!(n >= 0);
+    String::from_utf8(protocol[..n as usize].to_vec()).unwrap()
+}
+
+fn protocol(name: &'static str) -> moq_quic::MoqProtocol {
+    moq_quic::MoqProtocol { name: name.as_ptr(), name_len: name.len() }
+}
+
+fn connect_protocols(url: &str, protocols: &[moq_quic::MoqProtocol]) -> (i32, u64) {
+    connect_request(url, None, Some("127.0.0.1"), protocols, &[])
+}
+
+#[test]
+fn server_picks_from_offered_protocols() {
+    moq_quic::webtransport::moq_webtransport_init();
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let (addr, seen) = start_server(&server_runtime, "127.0.0.1:0").unwrap();
+    let url = format!("https://localhost:{}/moq", addr.port());
+    let offered = [protocol("moqt-16"), protocol("moq,lite"), protocol("moqt-15"), protocol("moqt-14")];
+    let (result, session_id) = connect_protocols(&url, &offered);
+    assert_eq!(result, 0);
+    assert_eq!(server_runtime.block_on(seen).unwrap().protocols, ["moqt-16", "moq,lite", "moqt-15", "moqt-14"]);
+    assert_eq!(negotiated_protocol(session_id), "moqt-15");
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+
+    // Nothing in common: the session still comes up, without a protocol
+    let (addr, _seen) = start_server(&server_runtime, "127.0.0.1:0").unwrap();
+    let url = format!("https://localhost:{}/moq", addr.port());
+    let (result, session_id) = connect_protocols(&url, &[protocol("moqt-17")]);
+    assert_eq!(result, 0);
+    assert_eq!(negotiated_protocol(session_id), "");
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
+
+#[test]
+fn single_protocol_is_offered_as_given() {
+    moq_quic::webtransport::moq_webtransport_init();
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+
+    // A comma in the protocol of the host/port/path form is part of the name, not a list separator
+    let (addr, seen) = start_server(&server_runtime, "127.0.0.1:0").unwrap();
+    let host = CString::new("127.0.0.1").unwrap();
+    let path = CString::new("/moq").unwrap();
+    let protocol = CString::new("moqt-16,moqt-15").unwrap();
+    let mut session_id = 0;
+    let result = moq_quic::webtransport::moq_webtransport_connect(
+        host.as_ptr(),
+        addr.port(),
+        path.as_ptr(),
+        protocol.as_ptr(),
+        1,
+        &mut session_id,
+    );
+    assert_eq!(result, 0);
+    assert_eq!(server_runtime.block_on(seen).unwrap().protocols, ["moqt-16,moqt-15"]);
+    assert_eq!(negotiated_protocol(session_id), "");
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
+
+#[test]
+fn invalid_protocol_lists_are_rejected() {
+    moq_quic::webtransport::moq_webtransport_init();
+    let url = "https://relay.example.com/moq";
+    assert_eq!(connect_protocols(url, &[protocol("moqt-15"), protocol("")]).0, -10);
+    assert_eq!(connect_protocols(url, &[protocol("moqt-15"), protocol("moqt-15")]).0, -10);
+    assert_eq!(connect_protocols(url, &[protocol("moqt\t15")]).0, -10);
+}
+
+fn header(name: &'static str, value: &'static str) -> moq_quic::MoqHttpHeader {
+    moq_quic::MoqHttpHeader { name: name.as_ptr(), name_len: name.len(), value: value.as_ptr(), value_len: value.len() }
+}
+
+fn connect_with_headers(url: &str, headers: &[moq_quic::MoqHttpHeader]) -> (i32, u64) {
+    connect_request(url, None, Some("127.0.0.1"), &[protocol("moq-00")], headers)
+}
+
+fn response_header(session_id: u64, name: &str) -> Result<String, i32> {
+    let name = CString::new(name).unwrap();
+    let mut value = [0u8; 64];
+    let n = moq_quic::webtransport::moq_webtransport_get_response_header(
+        session_id,
+        name.as_ptr(),
+        value.as_mut_ptr(),
+        value.len(),
+    );
+    if n < 0 {
+        return Err(n);
+    }
+    Ok(String::from_utf8(value[..n as usize].to_vec()).unwrap())
+}
+
+#[test]
+fn request_headers_reach_the_server_and_response_headers_are_exposed() {
+    moq_quic::webtransport::moq_webtransport_init();
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (addr, seen) = start_server(&server_runtime, "127.0.0.1:0").unwrap();
+
+    let url = format!("https://localhost:{}/moq", addr.port());
+    let headers = [header("Authorization", "Bearer t0ken"), header("X-Client-Version", " 1.2.3 ")];
+    let (result, session_id) = connect_with_headers(&url, &headers);
+    assert_eq!(result, 0);
+
+    let seen = server_runtime.block_on(seen).unwrap();
+    let sent = |name: &str| seen.headers.iter().find(|(field, _)| field == name).map(|(_, value)| value.as_str());
+    assert_eq!(sent("authorization"), Some("Bearer t0ken"));
+    assert_eq!(sent("x-client-version"), Some("1.2.3"));
+    assert_eq!(seen.protocols, ["moq-00"]);
+
+    let mut buffer = [0u8; 256];
+    let n = moq_quic::webtransport::moq_webtransport_get_response_headers(session_id, buffer.as_mut_ptr(), buffer.len());
+    let lines = String::from_utf8(buffer[..n as usize].to_vec()).unwrap();
+    assert!(lines.lines().any(|line| line == "x-relay: edge-1"), "{}", lines);
+    assert!(lines.lines().any(|line| line == "wt-protocol: \"moq-00\""), "{}", lines);
+    assert_eq!(response_header(session_id, "X-Relay").as_deref(), Ok("edge-1"));
+    assert_eq!(response_header(session_id, "x-missing"), Err(-2));
+    assert_eq!(response_header(u64::MAX, "x-relay"), Err(-1));
+
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
+
+#[test]
+fn invalid_request_headers_are_rejected() {
+    moq_quic::webtransport::moq_webtransport_init();
+    let url = "https://relay.example.com/moq";
+    for invalid in [
+        header(":authority", "elsewhere.example.com"),
+        header("wt-available-protocols", "\"moqt-15\""),
+        header("Transfer-Encoding", "chunked"),
+        header("bad name", "x"),
+        header("x-split", "a\r\nx-injected: b"),
+    ] {
+        assert_eq!(connect_with_headers(url, &[invalid]).0, -10);
+    }
+    assert_eq!(connect_with_headers(url, &[header("x-id", "1"), header("X-ID", "2")]).0, -10);
+}
+
+#[test]
+fn pending_connect_sends_the_full_request() {
+    moq_quic::webtransport::moq_webtransport_init();
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (addr, seen) = start_server(&server_runtime, "127.0.0.1:0").unwrap();
+
+    let url = format!("https://relay.invalid:{}/moq?room=1", addr.port());
+    let headers = [header("Authorization", "Bearer t0ken")];
+    let mut handle = 0;
+    let offered = [protocol("moqt-16"), protocol("moqt-15")];
+    let result = with_request(&url, Some("localhost"), Some("127.0.0.1"), &offered, &headers, |request| {
+        moq_quic::webtransport::moq_webtransport_connect_start(request, 1, ptr::null(), &mut handle)
+    });
+    assert_eq!(result, 0);
+    let session_id = common::wait_for("the connect to finish", || {
+        let mut session_id = 0;
+        let mut error = 0;
+        match moq_quic::webtransport::moq_webtransport_connect_poll(handle, &mut session_id, &mut error) {
+            moq_quic::MOQ_CONNECT_CONNECTED => Some(session_id),
+            moq_quic::MOQ_CONNECT_FAILED => panic!("connect failed with {}", error),
+            _ => None,
+        }
+    });
+
+    let seen = server_runtime.block_on(seen).unwrap();
+    assert_eq!(seen.url, url);
+    assert_eq!(seen.sni.as_deref(), Some("localhost"));
+    assert_eq!(seen.protocols, ["moqt-16", "moqt-15"]);
+    assert!(seen.headers.contains(&("authorization".to_string(), "Bearer t0ken".to_string())), "{:?}", seen.headers);
+    assert_eq!(negotiated_protocol(session_id), "moqt-15");
+    assert_eq!(response_header(session_id, "x-relay").as_deref(), Ok("edge-1"));
+    assert_eq!(moq_quic::webtransport::moq_webtransport_connect_free(handle), 0);
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+
+    // Invalid requests fail before a connect is started
+    let result = with_request("https://relay.example.com/moq", None, None, &[], &[header("TE", "trailers")], |request| {
+        moq_quic::webtransport::moq_webtransport_connect_start(request, 1, ptr::null(), &mut handle)
+    });
+    assert_eq!(result, -10);
+    assert_eq!(moq_quic::webtransport::moq_webtransport_connect_start(ptr::null(), 1, ptr::null(), &mut handle), -1);
+}
@@ -0,0 +1,178 @@
+// Incoming WebTransport streams are read concurrently: a stream that stays
+// open must not hold back data from streams opened after it, whether they are
+// polled through the session-wide FIFO or one stream at a time. A stream
+// read to its end keeps its final state until it is closed.
+
+mod common;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use tokio::sync::oneshot;
+
+const CHUNK: usize = 8192;
+
+/// Start a WebTransport server that opens two uni streams on the first session
+///
+/// Stream 'A' gets one chunk and stays open until `finish_a` fires; stream
+/// 'B' is opened afterwards and finished right away.
+fn start_server(runtime: &tokio::runtime::Runtime) -> (SocketAddr, oneshot::Sender<()>) {
+    let (finish_a, finish_a_rx) = oneshot::channel();
+    let addr = common::webtransport_server(runtime, |session| async move {
+        let mut a = session.open_uni().await.unwrap();
+        a.write_all(&[b'A'; CHUNK]).await.unwrap();
+
+        let mut b = session.open_uni().await.unwrap();
+        b.write_all(&[b'B'; CHUNK]).await.unwrap();
+        b.finish().unwrap();
+
+        let _ = finish_a_rx.await;
+        a.finish().unwrap();
+        // Keep the session alive until the client goes away
+        session.closed().await;
+    });
+    (addr, finish_a)
+}
+
+/// Bytes received and completion per stream, keyed by the tag byte
+#[derive(Default)]
+struct Received {
+    streams: HashMap<u8, (usize, bool)>,
+    ids: HashMap<u64, u8>,
+}
+
+impl Received {
+    /// Poll the session until `done` holds or the deadline passes
+    fn poll_until(&mut self, session_id: u64, done: impl Fn(&Self) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut buffer = vec![0u8; 64 * 1024];
+        while !done(self) {
+            assert!(Instant::now() < deadline, "streams were not delivered in time");
+            let mut stream_id = 0;
+            let mut is_complete = 0;
+            let n = moq_quic::webtransport::moq_webtransport_recv_data(
+                session_id,
+                &mut stream_id,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut is_complete,
+            );
+            assert!(n >= 0);
+            if n == 0 && is_complete == 0 {
+                std::thread::sleep(Duration::from_millis(5));
+                continue;
+            }
+            let tag = *self.ids.entry(stream_id).or_insert(buffer[0]);
+            let entry = self.streams.entry(tag).or_default();
+            entry.0 += n as usize;
+            entry.1 |= is_complete != 0;
+        }
+    }
+
+    fn get(&self, tag: u8) -> (usize, bool) {
+        self.streams.get(&tag).copied().unwrap_or_default()
+    }
+}
+
+/// Wait for `stream_id` to reach `expected` buffered or read bytes, reading them
+fn read_stream(session_id: u64, stream_id: u64, expected: usize) -> Vec<u8> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut data = Vec::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    while data.len() < expected {
+        assert!(Instant::now() < deadline, "stream data was not delivered in time");
+        let n = moq_quic::webtransport::moq_webtransport_recv_stream_data(
+            session_id,
+            stream_id,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+        );
+        assert!(n >= 0);
+        if n == 0 {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        data.extend_from_slice(&buffer[..n as usize]);
+    }
+    data
+}
+
+#[test]
+fn open_stream_does_not_block_later_streams() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (addr, finish_a) = start_server(&server_runtime);
+    let session_id = common::connect_webtransport(addr);
+
+    // B completes while A is still open
+    let mut received = Received::default();
+    received.poll_until(session_id, |r| r.get(b'B').1);
+    assert_eq!(received.get(b'B'), (CHUNK, true));
+    received.poll_until(session_id, |r| r.get(b'A').0 == CHUNK);
+    assert!(!received.get(b'A').1);
+
+    finish_a.send(()).unwrap();
+    received.poll_until(session_id, |r| r.get(b'A').1);
+    assert_eq!(received.get(b'A'), (CHUNK, true));
+
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
+
+#[test]
+fn streams_can_be_read_individually() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (addr, finish_a) = start_server(&server_runtime);
+    let session_id = common::connect_webtransport(addr);
+
+    let mut stream_ids = [0u64; 4];
+    common::wait_for("both streams to be accepted", || {
+        let n = moq_quic::webtransport::moq_webtransport_get_data_streams(session_id, stream_ids.as_mut_ptr(), 4);
+        (n == 2).then_some(())
+    });
+
+    // Both streams can be read in full while A is still open
+    let first = read_stream(session_id, stream_ids[0], CHUNK);
+    let second = read_stream(session_id, stream_ids[1], CHUNK);
+    let (a, b) = if first[0] == b'A' { (stream_ids[0], stream_ids[1]) } else { (stream_ids[1], stream_ids[0]) };
+    let (data_a, data_b) = if first[0] == b'A' { (first, second) } else { (second, first) };
+    assert_eq!(data_a, vec![b'A'; CHUNK]);
+    assert_eq!(data_b, vec![b'B'; CHUNK]);
+    let state = moq_quic::webtransport::moq_webtransport_get_data_stream_state(session_id, a, std::ptr::null_mut());
+    assert_eq!(state, moq_quic::MOQ_STREAM_STATE_OPEN as i32);
+
+    assert_eq!(moq_quic::webtransport::moq_webtransport_close_data_stream(session_id, b), 0);
+    let mut remaining = [0u64; 4];
+    assert_eq!(moq_quic::webtransport::moq_webtransport_get_data_streams(session_id, remaining.as_mut_ptr(), 4), 1);
+    assert_eq!(remaining[0], a);
+
+    finish_a.send(()).unwrap();
+    let state = common::wait_for("stream A to finish", || {
+        let state = moq_quic::webtransport::moq_webtransport_get_data_stream_state(session_id, a, std::ptr::null_mut());
+        (state != moq_quic::MOQ_STREAM_STATE_OPEN as i32).then_some(state)
+    });
+    assert_eq!(state, moq_quic::MOQ_STREAM_STATE_FINISHED as i32);
+
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}
+
+#[test]
+fn completed_stream_keeps_its_state_until_closed() {
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let (addr, _finish_a) = start_server(&server_runtime);
+    let session_id = common::connect_webtransport(addr);
+
+    let mut received = Received::default();
+    received.poll_until(session_id, |r| r.get(b'B').1);
+    let b = received.ids.iter().find(|(_, &tag)| tag == b'B').map(|(&id, _)| id).unwrap();
+
+    // The last chunk releases the data but not the final state
+    let mut listed = [0u64; 4];
+    let n = moq_quic::webtransport::moq_webtransport_get_data_streams(session_id, listed.as_mut_ptr(), 4);
+    assert!(!listed[..n as usize].contains(&b));
+    let state = moq_quic::webtransport::moq_webtransport_get_data_stream_state(session_id, b, std::ptr::null_mut());
+    assert_eq!(state, moq_quic::MOQ_STREAM_STATE_FINISHED as i32);
+
+    assert_eq!(moq_quic::webtransport::moq_webtransport_close_data_stream(session_id, b), 0);
+    assert_eq!(moq_quic::webtransport::moq_webtransport_get_data_stream_state(session_id, b, std::ptr::null_mut()), -1);
+
+    moq_quic::webtransport::moq_webtransport_close(session_id);
+}